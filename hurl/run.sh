@@ -0,0 +1,6 @@
+#!/bin/sh
+# Seeds the test-only rows and runs every suite against the server on :3000
+set -e
+
+psql "$DATABASE_URL" -f hurl/seed.sql
+hurl hurl/*.hurl
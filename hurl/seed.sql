@@ -0,0 +1,60 @@
+-- Redirects that only exist for the hurl suite, so it can cover every mode without changing
+-- the real hosts
+INSERT INTO
+  Redirects (redirect_id, host, target_url, status_code, mode)
+VALUES
+  (
+    gen_random_uuid (),
+    'passthrough.redirects.test',
+    'https://coreyja.com',
+    308,
+    'preserve_path_and_query'
+  );
+
+INSERT INTO
+  Redirects (
+    redirect_id,
+    host,
+    path_pattern,
+    pattern_type,
+    priority,
+    target_url,
+    status_code
+  )
+VALUES
+  (
+    gen_random_uuid (),
+    'paths.redirects.test',
+    '*',
+    'glob',
+    0,
+    'https://coreyja.com',
+    302
+  ),
+  (
+    gen_random_uuid (),
+    'paths.redirects.test',
+    '/blog/*',
+    'glob',
+    10,
+    'https://coreyja.com/posts/$1',
+    302
+  ),
+  (
+    gen_random_uuid (),
+    'paths.redirects.test',
+    '/blog/drafts/*',
+    'glob',
+    20,
+    'https://coreyja.com/drafts/$1',
+    302
+  ),
+  (
+    gen_random_uuid (),
+    'paths.redirects.test',
+    '/v/(?P<id>[0-9]+)',
+    'regex',
+    0,
+    'https://coreyja.com/videos/${id}?price=$$5',
+    302
+  );
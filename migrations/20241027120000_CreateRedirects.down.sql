@@ -0,0 +1,4 @@
+-- Add migration script here
+DROP TABLE Redirects;
+
+DROP FUNCTION notify_redirects_changed;
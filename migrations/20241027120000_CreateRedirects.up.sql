@@ -0,0 +1,37 @@
+-- Add migration script here
+CREATE TABLE
+  Redirects (
+    redirect_id UUID PRIMARY KEY NOT NULL,
+    host TEXT NOT NULL,
+    target_url TEXT NOT NULL,
+    status_code INT NOT NULL DEFAULT 303 CHECK (status_code IN (301, 302, 303, 307, 308)),
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW (),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW ()
+  );
+
+CREATE UNIQUE INDEX idx_redirects_on_host ON Redirects (host);
+
+CREATE FUNCTION notify_redirects_changed () RETURNS TRIGGER AS $$
+BEGIN
+  PERFORM pg_notify('redirects_changed', '');
+  RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER redirects_changed
+AFTER INSERT
+OR
+UPDATE
+OR DELETE
+OR TRUNCATE ON Redirects FOR EACH STATEMENT
+EXECUTE FUNCTION notify_redirects_changed ();
+
+INSERT INTO
+  Redirects (redirect_id, host, target_url, status_code)
+VALUES
+  (gen_random_uuid (), 'coreyja.tv', 'https://coreyja.com/videos', 303),
+  (gen_random_uuid (), 'coreyja.tube', 'https://coreyja.com/videos', 303),
+  (gen_random_uuid (), 'coreyja.blog', 'https://coreyja.com/posts', 303),
+  (gen_random_uuid (), 'coreyja.club', 'https://discord.gg/CpAPpXrgUq', 303),
+  (gen_random_uuid (), 'beta.coreyja.com', 'https://coreyja.com', 303);
@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE Redirects
+DROP COLUMN mode;
@@ -0,0 +1,18 @@
+-- Add migration script here
+ALTER TABLE Redirects
+ADD COLUMN mode TEXT NOT NULL DEFAULT 'fixed' CHECK (
+  mode IN ('fixed', 'preserve_path', 'preserve_path_and_query')
+);
+
+UPDATE Redirects
+SET
+  mode = 'preserve_path'
+WHERE
+  host = 'coreyja.blog';
+
+UPDATE Redirects
+SET
+  mode = 'preserve_path_and_query',
+  status_code = 308
+WHERE
+  host = 'beta.coreyja.com';
@@ -0,0 +1,17 @@
+-- Add migration script here
+UPDATE Redirects
+SET
+  target_url = REPLACE(target_url, '$$', '$')
+WHERE
+  target_url LIKE '%$$%';
+
+DROP INDEX idx_redirects_on_host_and_path_pattern;
+
+DROP INDEX idx_redirects_on_host;
+
+CREATE UNIQUE INDEX idx_redirects_on_host ON Redirects (host);
+
+ALTER TABLE Redirects
+DROP COLUMN path_pattern,
+DROP COLUMN pattern_type,
+DROP COLUMN priority;
@@ -0,0 +1,19 @@
+-- Add migration script here
+ALTER TABLE Redirects
+ADD COLUMN path_pattern TEXT NOT NULL DEFAULT '*',
+ADD COLUMN pattern_type TEXT NOT NULL DEFAULT 'glob' CHECK (pattern_type IN ('glob', 'regex')),
+ADD COLUMN priority INT NOT NULL DEFAULT 0;
+
+DROP INDEX idx_redirects_on_host;
+
+CREATE INDEX idx_redirects_on_host ON Redirects (host);
+
+CREATE UNIQUE INDEX idx_redirects_on_host_and_path_pattern ON Redirects (host, path_pattern);
+
+-- Targets are now expanded with the pattern's captures, where `$` starts a capture. Any `$` in an
+-- existing target was meant literally, and `$$` keeps it that way
+UPDATE Redirects
+SET
+  target_url = REPLACE(target_url, '$', '$$')
+WHERE
+  target_url LIKE '%$%';
@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE Redirects
+DROP COLUMN match_apex,
+DROP COLUMN match_www;
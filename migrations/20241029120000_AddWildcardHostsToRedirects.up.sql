@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE Redirects
+ADD COLUMN match_apex BOOLEAN NOT NULL DEFAULT FALSE,
+ADD COLUMN match_www BOOLEAN NOT NULL DEFAULT TRUE;
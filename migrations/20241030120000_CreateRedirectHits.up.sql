@@ -0,0 +1,24 @@
+-- Add migration script here
+CREATE TABLE
+  RedirectHits (
+    redirect_hit_id UUID PRIMARY KEY NOT NULL,
+    redirect_id UUID NOT NULL REFERENCES Redirects (redirect_id) ON DELETE CASCADE,
+    host TEXT NOT NULL,
+    path TEXT NOT NULL,
+    referer_host TEXT,
+    user_agent_class TEXT NOT NULL,
+    hit_at TIMESTAMPTZ NOT NULL DEFAULT NOW ()
+  );
+
+CREATE INDEX idx_redirect_hits_on_hit_at ON RedirectHits (hit_at);
+
+CREATE TABLE
+  RedirectHitRollups (
+    redirect_id UUID NOT NULL REFERENCES Redirects (redirect_id) ON DELETE CASCADE,
+    granularity TEXT NOT NULL CHECK (granularity IN ('hour', 'day')),
+    bucket_start TIMESTAMPTZ NOT NULL,
+    hits BIGINT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW (),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW (),
+    PRIMARY KEY (redirect_id, granularity, bucket_start)
+  );
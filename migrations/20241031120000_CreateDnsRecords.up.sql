@@ -0,0 +1,17 @@
+-- Add migration script here
+CREATE TABLE
+  DnsRecords (
+    dns_record_id UUID PRIMARY KEY NOT NULL,
+    porkbun_domain_id UUID NOT NULL REFERENCES PorkbunDomains (porkbun_domain_id) ON DELETE CASCADE,
+    porkbun_record_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    record_type TEXT NOT NULL,
+    content TEXT NOT NULL,
+    ttl INT NOT NULL,
+    prio INT,
+    notes TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW (),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW ()
+  );
+
+CREATE UNIQUE INDEX idx_dns_records_on_domain_and_porkbun_record_id ON DnsRecords (porkbun_domain_id, porkbun_record_id);
@@ -0,0 +1,4 @@
+-- Add migration script here
+DROP TABLE DnsRecordChanges;
+
+DROP TABLE DnsRecordSyncs;
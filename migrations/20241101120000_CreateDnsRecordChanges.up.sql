@@ -0,0 +1,24 @@
+-- Add migration script here
+CREATE TABLE
+  DnsRecordSyncs (
+    dns_record_sync_id UUID PRIMARY KEY NOT NULL,
+    porkbun_domain_id UUID NOT NULL REFERENCES PorkbunDomains (porkbun_domain_id) ON DELETE CASCADE,
+    baseline BOOLEAN NOT NULL DEFAULT FALSE,
+    change_count INT NOT NULL DEFAULT 0,
+    synced_at TIMESTAMPTZ NOT NULL DEFAULT NOW ()
+  );
+
+CREATE INDEX idx_dns_record_syncs_on_domain_and_synced_at ON DnsRecordSyncs (porkbun_domain_id, synced_at DESC);
+
+CREATE TABLE
+  DnsRecordChanges (
+    dns_record_change_id UUID PRIMARY KEY NOT NULL,
+    dns_record_sync_id UUID NOT NULL REFERENCES DnsRecordSyncs (dns_record_sync_id) ON DELETE CASCADE,
+    porkbun_record_id TEXT NOT NULL,
+    change_type TEXT NOT NULL CHECK (change_type IN ('added', 'removed', 'modified')),
+    previous JSONB,
+    current JSONB,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW ()
+  );
+
+CREATE INDEX idx_dns_record_changes_on_sync ON DnsRecordChanges (dns_record_sync_id);
@@ -0,0 +1,12 @@
+-- Add migration script here
+CREATE TABLE
+  ExpiryAlerts (
+    expiry_alert_id UUID PRIMARY KEY NOT NULL,
+    porkbun_domain_id UUID NOT NULL REFERENCES PorkbunDomains (porkbun_domain_id) ON DELETE CASCADE,
+    window_days INT NOT NULL,
+    expire_date TIMESTAMPTZ NOT NULL,
+    sent_at TIMESTAMPTZ NOT NULL DEFAULT NOW ()
+  );
+
+-- A renewal moves expire_date, which lets the same windows alert again for the next term
+CREATE UNIQUE INDEX idx_expiry_alerts_on_domain_window_and_expire_date ON ExpiryAlerts (porkbun_domain_id, window_days, expire_date);
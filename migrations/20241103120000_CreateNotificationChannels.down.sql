@@ -0,0 +1,4 @@
+-- Add migration script here
+DROP TABLE NotificationDeliveries;
+
+DROP TABLE NotificationChannels;
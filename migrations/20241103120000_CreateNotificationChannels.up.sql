@@ -0,0 +1,29 @@
+-- Add migration script here
+CREATE TABLE
+  NotificationChannels (
+    notification_channel_id UUID PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('email', 'webhook', 'discord')),
+    target TEXT NOT NULL,
+    events TEXT[] NOT NULL DEFAULT '{}',
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW (),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW ()
+  );
+
+CREATE TABLE
+  NotificationDeliveries (
+    notification_delivery_id UUID PRIMARY KEY NOT NULL,
+    notification_channel_id UUID NOT NULL REFERENCES NotificationChannels (notification_channel_id) ON DELETE CASCADE,
+    event TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    body TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'delivered', 'failed')),
+    attempts INT NOT NULL DEFAULT 0,
+    last_error TEXT,
+    delivered_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW (),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW ()
+  );
+
+CREATE INDEX idx_notification_deliveries_on_created_at ON NotificationDeliveries (created_at DESC);
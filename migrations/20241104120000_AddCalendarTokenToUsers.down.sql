@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE Users
+DROP COLUMN calendar_token;
@@ -0,0 +1,5 @@
+-- Add migration script here
+ALTER TABLE Users
+ADD COLUMN calendar_token TEXT;
+
+CREATE UNIQUE INDEX idx_users_calendar_token ON Users (calendar_token);
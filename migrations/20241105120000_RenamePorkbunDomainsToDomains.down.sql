@@ -0,0 +1,21 @@
+-- Add migration script here
+ALTER TABLE ExpiryAlerts
+RENAME COLUMN domain_id TO porkbun_domain_id;
+
+ALTER TABLE DnsRecordSyncs
+RENAME COLUMN domain_id TO porkbun_domain_id;
+
+ALTER TABLE DnsRecords
+RENAME COLUMN domain_id TO porkbun_domain_id;
+
+ALTER TABLE Domains
+DROP COLUMN registrar;
+
+ALTER INDEX idx_domains_on_domain
+RENAME TO idx_porkbun_domains_on_domain;
+
+ALTER TABLE Domains
+RENAME COLUMN domain_id TO porkbun_domain_id;
+
+ALTER TABLE Domains
+RENAME TO PorkbunDomains;
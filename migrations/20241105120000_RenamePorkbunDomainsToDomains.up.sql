@@ -0,0 +1,26 @@
+-- Add migration script here
+ALTER TABLE PorkbunDomains
+RENAME TO Domains;
+
+ALTER TABLE Domains
+RENAME COLUMN porkbun_domain_id TO domain_id;
+
+ALTER INDEX idx_porkbun_domains_on_domain
+RENAME TO idx_domains_on_domain;
+
+-- Every domain so far came from Porkbun
+ALTER TABLE Domains
+ADD COLUMN registrar TEXT NOT NULL DEFAULT 'porkbun';
+
+ALTER TABLE Domains
+ALTER COLUMN registrar
+DROP DEFAULT;
+
+ALTER TABLE DnsRecords
+RENAME COLUMN porkbun_domain_id TO domain_id;
+
+ALTER TABLE DnsRecordSyncs
+RENAME COLUMN porkbun_domain_id TO domain_id;
+
+ALTER TABLE ExpiryAlerts
+RENAME COLUMN porkbun_domain_id TO domain_id;
@@ -0,0 +1,20 @@
+-- Add migration script here
+ALTER TABLE DnsRecordChanges
+RENAME COLUMN provider_record_id TO porkbun_record_id;
+
+ALTER TABLE DnsRecordSyncs
+DROP COLUMN provider;
+
+DELETE FROM DnsRecords
+WHERE
+  provider != 'porkbun';
+
+DROP INDEX idx_dns_records_on_domain_and_provider_record_id;
+
+ALTER TABLE DnsRecords
+DROP COLUMN provider;
+
+ALTER TABLE DnsRecords
+RENAME COLUMN provider_record_id TO porkbun_record_id;
+
+CREATE UNIQUE INDEX idx_dns_records_on_domain_and_porkbun_record_id ON DnsRecords (domain_id, porkbun_record_id);
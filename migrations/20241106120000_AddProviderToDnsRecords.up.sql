@@ -0,0 +1,25 @@
+-- Add migration script here
+-- Records can now come from Cloudflare as well as Porkbun, ids are only unique per provider
+ALTER TABLE DnsRecords
+RENAME COLUMN porkbun_record_id TO provider_record_id;
+
+ALTER TABLE DnsRecords
+ADD COLUMN provider TEXT NOT NULL DEFAULT 'porkbun';
+
+ALTER TABLE DnsRecords
+ALTER COLUMN provider
+DROP DEFAULT;
+
+DROP INDEX idx_dns_records_on_domain_and_porkbun_record_id;
+
+CREATE UNIQUE INDEX idx_dns_records_on_domain_and_provider_record_id ON DnsRecords (domain_id, provider, provider_record_id);
+
+ALTER TABLE DnsRecordSyncs
+ADD COLUMN provider TEXT NOT NULL DEFAULT 'porkbun';
+
+ALTER TABLE DnsRecordSyncs
+ALTER COLUMN provider
+DROP DEFAULT;
+
+ALTER TABLE DnsRecordChanges
+RENAME COLUMN porkbun_record_id TO provider_record_id;
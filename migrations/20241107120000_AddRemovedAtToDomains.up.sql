@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE Domains
+ADD COLUMN removed_at TIMESTAMPTZ;
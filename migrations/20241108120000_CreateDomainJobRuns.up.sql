@@ -0,0 +1,13 @@
+-- Add migration script here
+CREATE TABLE
+  DomainJobRuns (
+    domain_job_run_id UUID PRIMARY KEY NOT NULL,
+    domain_id UUID NOT NULL REFERENCES Domains (domain_id) ON DELETE CASCADE,
+    job_name TEXT NOT NULL,
+    status TEXT NOT NULL CHECK (status IN ('succeeded', 'failed', 'rescheduled')),
+    error TEXT,
+    started_at TIMESTAMPTZ NOT NULL,
+    finished_at TIMESTAMPTZ NOT NULL DEFAULT NOW ()
+  );
+
+CREATE INDEX idx_domain_job_runs_on_domain_and_finished_at ON DomainJobRuns (domain_id, finished_at DESC);
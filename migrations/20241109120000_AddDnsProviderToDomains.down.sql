@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE Domains
+DROP COLUMN dns_provider;
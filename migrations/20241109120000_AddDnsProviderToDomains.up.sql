@@ -0,0 +1,18 @@
+-- Add migration script here
+-- Kept in step with nameservers so the domains list can filter on it in SQL
+ALTER TABLE Domains
+ADD COLUMN dns_provider TEXT NOT NULL DEFAULT 'unknown';
+
+-- The same rules as DnsProvider::from_nameservers, every nameserver has to match
+UPDATE Domains
+SET
+  dns_provider = CASE
+    WHEN NOT EXISTS (SELECT 1 FROM unnest(nameservers) ns WHERE ns NOT LIKE '%.porkbun.com') THEN 'porkbun'
+    WHEN NOT EXISTS (SELECT 1 FROM unnest(nameservers) ns WHERE ns NOT LIKE '%.cloudflare.com') THEN 'cloudflare'
+    WHEN NOT EXISTS (SELECT 1 FROM unnest(nameservers) ns WHERE ns NOT LIKE '%.googledomains.com') THEN 'google_domains'
+    WHEN NOT EXISTS (SELECT 1 FROM unnest(nameservers) ns WHERE ns NOT LIKE '%.vercel-dns.com') THEN 'vercel'
+    WHEN NOT EXISTS (SELECT 1 FROM unnest(nameservers) ns WHERE ns NOT LIKE '%awsdns%') THEN 'route53'
+    ELSE 'unknown'
+  END;
+
+CREATE INDEX idx_domains_on_dns_provider ON Domains (dns_provider);
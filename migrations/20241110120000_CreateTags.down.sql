@@ -0,0 +1,6 @@
+-- Add migration script here
+DROP TABLE TldPrices;
+
+DROP TABLE DomainTags;
+
+DROP TABLE Tags;
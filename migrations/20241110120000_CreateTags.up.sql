@@ -0,0 +1,27 @@
+-- Add migration script here
+CREATE TABLE
+  Tags (
+    tag_id UUID PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW ()
+  );
+
+CREATE TABLE
+  DomainTags (
+    domain_id UUID NOT NULL REFERENCES Domains (domain_id) ON DELETE CASCADE,
+    tag_id UUID NOT NULL REFERENCES Tags (tag_id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW (),
+    PRIMARY KEY (domain_id, tag_id)
+  );
+
+CREATE INDEX idx_domain_tags_on_tag ON DomainTags (tag_id);
+
+-- What each registrar charges to renew a domain for a year, in cents
+CREATE TABLE
+  TldPrices (
+    registrar TEXT NOT NULL,
+    tld TEXT NOT NULL,
+    renewal_cents BIGINT NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW (),
+    PRIMARY KEY (registrar, tld)
+  );
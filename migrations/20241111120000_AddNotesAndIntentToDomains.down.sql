@@ -0,0 +1,4 @@
+-- Add migration script here
+ALTER TABLE Domains
+DROP COLUMN intent,
+DROP COLUMN notes;
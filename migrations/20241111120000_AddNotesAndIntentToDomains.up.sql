@@ -0,0 +1,9 @@
+-- Add migration script here
+-- Why we hold each domain, NULL until someone decides
+ALTER TABLE Domains
+ADD COLUMN notes TEXT NOT NULL DEFAULT '',
+ADD COLUMN intent TEXT CHECK (
+  intent IN ('active_project', 'redirect', 'parked', 'defensive', 'let_expire')
+);
+
+CREATE INDEX idx_domains_on_intent ON Domains (intent);
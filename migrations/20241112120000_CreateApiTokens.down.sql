@@ -0,0 +1,2 @@
+-- Add migration script here
+DROP TABLE ApiTokens;
@@ -0,0 +1,13 @@
+-- Add migration script here
+-- Bearer tokens for /api/v1, only a SHA-256 of each token is kept
+CREATE TABLE
+  ApiTokens (
+    api_token_id UUID PRIMARY KEY NOT NULL,
+    user_id UUID NOT NULL REFERENCES Users (user_id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    token_hash TEXT NOT NULL,
+    last_used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW ()
+  );
+
+CREATE UNIQUE INDEX idx_api_tokens_on_token_hash ON ApiTokens (token_hash);
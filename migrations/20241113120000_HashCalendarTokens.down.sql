@@ -0,0 +1,12 @@
+-- Add migration script here
+-- The tokens can't be recovered from their hashes, so every calendar link has to be generated
+-- again
+UPDATE Users
+SET
+  calendar_token_hash = NULL;
+
+ALTER INDEX idx_users_calendar_token_hash
+RENAME TO idx_users_calendar_token;
+
+ALTER TABLE Users
+RENAME COLUMN calendar_token_hash TO calendar_token;
@@ -0,0 +1,14 @@
+-- Add migration script here
+-- Only keep a hash of the calendar token, the same way as API tokens. Existing feed URLs keep
+-- working since their tokens hash to the stored value
+ALTER TABLE Users
+RENAME COLUMN calendar_token TO calendar_token_hash;
+
+UPDATE Users
+SET
+  calendar_token_hash = encode(sha256(convert_to(calendar_token_hash, 'UTF8')), 'hex')
+WHERE
+  calendar_token_hash IS NOT NULL;
+
+ALTER INDEX idx_users_calendar_token
+RENAME TO idx_users_calendar_token_hash;
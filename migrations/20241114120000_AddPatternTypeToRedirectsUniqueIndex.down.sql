@@ -0,0 +1,19 @@
+-- Add migration script here
+-- Only one of each glob and regex pair with the same pattern can be kept
+DELETE FROM Redirects
+WHERE
+  pattern_type = 'regex'
+  AND EXISTS (
+    SELECT
+      1
+    FROM
+      Redirects glob
+    WHERE
+      glob.host = Redirects.host
+      AND glob.path_pattern = Redirects.path_pattern
+      AND glob.pattern_type = 'glob'
+  );
+
+DROP INDEX idx_redirects_on_host_and_path_pattern;
+
+CREATE UNIQUE INDEX idx_redirects_on_host_and_path_pattern ON Redirects (host, path_pattern);
@@ -0,0 +1,5 @@
+-- Add migration script here
+-- A glob and a regex can be written the same way and still match different paths
+DROP INDEX idx_redirects_on_host_and_path_pattern;
+
+CREATE UNIQUE INDEX idx_redirects_on_host_and_path_pattern ON Redirects (host, path_pattern, pattern_type);
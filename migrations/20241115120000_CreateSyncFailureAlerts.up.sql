@@ -0,0 +1,8 @@
+-- Add migration script here
+-- When each sync failure was last reported, kept apart from NotificationDeliveries so the daily
+-- limit holds even when no channels are set up and the failure is only logged
+CREATE TABLE
+  SyncFailureAlerts (
+    subject TEXT PRIMARY KEY NOT NULL,
+    sent_at TIMESTAMPTZ NOT NULL DEFAULT NOW ()
+  );
@@ -0,0 +1,9 @@
+-- Add migration script here
+UPDATE Redirects
+SET
+  mode = 'preserve_path_and_query',
+  status_code = 308
+WHERE
+  host = 'beta.coreyja.com'
+  AND mode = 'fixed'
+  AND status_code = 303;
@@ -0,0 +1,11 @@
+-- Add migration script here
+-- AddModeToRedirects switched beta.coreyja.com to keep the path and query with a 308, which it
+-- was never meant to. Put it back unless it has been changed since
+UPDATE Redirects
+SET
+  mode = 'fixed',
+  status_code = 303
+WHERE
+  host = 'beta.coreyja.com'
+  AND mode = 'preserve_path_and_query'
+  AND status_code = 308;
@@ -0,0 +1,300 @@
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+
+use axum::{
+    extract::Request,
+    http::{header, HeaderMap},
+};
+
+use cja::app_state::AppState as _;
+use tokio::sync::mpsc;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::AppState;
+
+/// Coarse bucket for a `User-Agent`, so we can tell people from crawlers without keeping the
+/// raw header around
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum UserAgentClass {
+    Bot,
+    Mobile,
+    Desktop,
+    Unknown,
+}
+
+impl UserAgentClass {
+    pub(crate) fn from_user_agent(user_agent: Option<&str>) -> Self {
+        let Some(user_agent) = user_agent.map(str::to_ascii_lowercase) else {
+            return UserAgentClass::Unknown;
+        };
+
+        const BOT_MARKERS: [&str; 8] = [
+            "bot", "crawl", "spider", "slurp", "curl", "wget", "python", "preview",
+        ];
+        if BOT_MARKERS.iter().any(|marker| user_agent.contains(marker)) {
+            return UserAgentClass::Bot;
+        }
+
+        if user_agent.contains("mobile")
+            || user_agent.contains("android")
+            || user_agent.contains("iphone")
+        {
+            return UserAgentClass::Mobile;
+        }
+
+        if user_agent.contains("mozilla") {
+            return UserAgentClass::Desktop;
+        }
+
+        UserAgentClass::Unknown
+    }
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            UserAgentClass::Bot => "bot",
+            UserAgentClass::Mobile => "mobile",
+            UserAgentClass::Desktop => "desktop",
+            UserAgentClass::Unknown => "unknown",
+        }
+    }
+}
+
+/// Only the host of the `Referer` is kept, the full URL can carry things we don't want to store
+fn referer_host(headers: &HeaderMap) -> Option<String> {
+    let referer = headers.get(header::REFERER)?.to_str().ok()?;
+
+    reqwest::Url::parse(referer)
+        .ok()?
+        .host_str()
+        .map(str::to_string)
+}
+
+/// Hits waiting to be written. Past this a burst of traffic drops hits rather than queueing
+/// work that competes with the jobs and pages for the connection pool
+const HIT_QUEUE_SIZE: usize = 10_000;
+
+/// The most hits written with one `INSERT`
+const HIT_BATCH_SIZE: usize = 500;
+
+#[derive(Debug)]
+pub(crate) struct RedirectHit {
+    redirect_id: Uuid,
+    host: String,
+    path: String,
+    referer_host: Option<String>,
+    user_agent_class: UserAgentClass,
+}
+
+/// Queue between the redirect handler and [`write_hits`], which does the inserts in batches
+#[derive(Debug, Clone)]
+pub(crate) struct HitRecorder {
+    sender: mpsc::Sender<RedirectHit>,
+    receiver: Arc<Mutex<Option<mpsc::Receiver<RedirectHit>>>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl HitRecorder {
+    pub(crate) fn new() -> Self {
+        Self::with_capacity(HIT_QUEUE_SIZE)
+    }
+
+    fn with_capacity(capacity: usize) -> Self {
+        let (sender, receiver) = mpsc::channel(capacity);
+
+        Self {
+            sender,
+            receiver: Arc::new(Mutex::new(Some(receiver))),
+            dropped: Arc::default(),
+        }
+    }
+}
+
+/// Queues a hit for `redirect_id` so the redirect itself isn't held up by the insert. Dropped if
+/// the queue is full
+pub(crate) fn record_hit(app_state: &AppState, redirect_id: Uuid, host: &str, request: &Request) {
+    let headers = request.headers();
+    let user_agent = headers
+        .get(header::USER_AGENT)
+        .and_then(|ua| ua.to_str().ok());
+
+    let hit = RedirectHit {
+        redirect_id,
+        host: host.to_string(),
+        path: request.uri().path().to_string(),
+        referer_host: referer_host(headers),
+        user_agent_class: UserAgentClass::from_user_agent(user_agent),
+    };
+
+    if app_state.hits.sender.try_send(hit).is_err() {
+        app_state.hits.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Writes queued hits until the app shuts down, one batch at a time so a burst costs a handful of
+/// queries on a single connection
+pub(crate) async fn write_hits(app_state: AppState) -> cja::Result<()> {
+    let mut receiver = app_state
+        .hits
+        .receiver
+        .lock()
+        .unwrap()
+        .take()
+        .ok_or_else(|| color_eyre::eyre::eyre!("Redirect hits are already being written"))?;
+
+    let mut batch = Vec::with_capacity(HIT_BATCH_SIZE);
+    while receiver.recv_many(&mut batch, HIT_BATCH_SIZE).await > 0 {
+        let dropped = app_state.hits.dropped.swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            warn!(dropped, "Redirect hit queue was full, dropped hits");
+        }
+
+        if let Err(e) = insert_hits(&app_state, &batch).await {
+            warn!(count = batch.len(), "Failed to record redirect hits: {e}");
+        }
+        batch.clear();
+    }
+
+    Ok(())
+}
+
+async fn insert_hits(app_state: &AppState, hits: &[RedirectHit]) -> sqlx::Result<()> {
+    let ids = hits.iter().map(|_| Uuid::new_v4()).collect::<Vec<_>>();
+    let redirect_ids = hits.iter().map(|h| h.redirect_id).collect::<Vec<_>>();
+    let hosts = hits.iter().map(|h| h.host.clone()).collect::<Vec<_>>();
+    let paths = hits.iter().map(|h| h.path.clone()).collect::<Vec<_>>();
+    let referer_hosts = hits
+        .iter()
+        .map(|h| h.referer_host.clone())
+        .collect::<Vec<_>>();
+    let user_agent_classes = hits
+        .iter()
+        .map(|h| h.user_agent_class.as_str().to_string())
+        .collect::<Vec<_>>();
+
+    // Skips hits for rules deleted while they were queued, so one of those doesn't fail the
+    // whole batch on the foreign key
+    sqlx::query!(
+        r#"INSERT INTO RedirectHits (redirect_hit_id, redirect_id, host, path, referer_host, user_agent_class)
+        SELECT u.* FROM UNNEST($1::UUID[], $2::UUID[], $3::TEXT[], $4::TEXT[], $5::TEXT[], $6::TEXT[])
+          AS u (redirect_hit_id, redirect_id, host, path, referer_host, user_agent_class)
+        WHERE EXISTS (SELECT 1 FROM Redirects r WHERE r.redirect_id = u.redirect_id)"#,
+        &ids,
+        &redirect_ids,
+        &hosts,
+        &paths,
+        &referer_hosts as &[Option<String>],
+        &user_agent_classes
+    )
+    .execute(app_state.db())
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use sqlx::PgPool;
+
+    use super::*;
+
+    async fn hit_count(pool: &PgPool) -> i64 {
+        sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM RedirectHits"#)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn writes_queued_hits_and_drops_the_overflow(pool: PgPool) {
+        let mut app_state = AppState::with_pool(pool.clone()).unwrap();
+        app_state.hits = HitRecorder::with_capacity(2);
+
+        let redirect_id = Uuid::new_v4();
+        sqlx::query!(
+            "INSERT INTO Redirects (redirect_id, host, target_url) VALUES ($1, 'old.test', 'https://new.test/')",
+            redirect_id
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let request = Request::builder()
+            .uri("/path")
+            .header(header::USER_AGENT, "Googlebot")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        for _ in 0..3 {
+            record_hit(&app_state, redirect_id, "old.test", &request);
+        }
+        assert_eq!(app_state.hits.dropped.load(Ordering::Relaxed), 1);
+
+        let writer = tokio::spawn(write_hits(app_state.clone()));
+        for _ in 0..100 {
+            if hit_count(&pool).await == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(hit_count(&pool).await, 2);
+
+        let hit = sqlx::query!("SELECT * FROM RedirectHits LIMIT 1")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(hit.path, "/path");
+        assert_eq!(hit.user_agent_class, UserAgentClass::Bot.as_str());
+
+        // Only one writer can own the queue
+        assert!(write_hits(app_state).await.is_err());
+        writer.abort();
+    }
+
+    #[sqlx::test]
+    async fn skips_hits_for_deleted_rules(pool: PgPool) {
+        let app_state = AppState::with_pool(pool.clone()).unwrap();
+
+        let (kept, deleted) = (Uuid::new_v4(), Uuid::new_v4());
+        for (redirect_id, host) in [(kept, "kept.test"), (deleted, "deleted.test")] {
+            sqlx::query!(
+                "INSERT INTO Redirects (redirect_id, host, target_url) VALUES ($1, $2, 'https://new.test/')",
+                redirect_id,
+                host
+            )
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let request = Request::builder()
+            .uri("/")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        record_hit(&app_state, kept, "kept.test", &request);
+        record_hit(&app_state, deleted, "deleted.test", &request);
+
+        sqlx::query!("DELETE FROM Redirects WHERE redirect_id = $1", deleted)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let writer = tokio::spawn(write_hits(app_state.clone()));
+        for _ in 0..100 {
+            if hit_count(&pool).await == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        writer.abort();
+
+        let hits = sqlx::query_scalar!("SELECT redirect_id FROM RedirectHits")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(hits, vec![kept]);
+    }
+}
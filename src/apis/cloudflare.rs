@@ -0,0 +1,354 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tracing::debug;
+
+const DEFAULT_BASE_URL: &str = "https://api.cloudflare.com/client/v4";
+
+/// Cloudflare caps `per_page` at 100 for DNS records and 50 for zones
+const ZONES_PER_PAGE: u32 = 50;
+const RECORDS_PER_PAGE: u32 = 100;
+
+/// Every call shares one connection pool
+fn http_client() -> reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+    CLIENT.get_or_init(reqwest::Client::new).clone()
+}
+
+#[derive(Clone)]
+pub struct Config {
+    api_token: String,
+    base_url: String,
+}
+
+impl Config {
+    /// Reads `CLOUDFLARE_API_TOKEN`, and `CLOUDFLARE_API_URL` if the API lives somewhere other
+    /// than api.cloudflare.com
+    pub fn from_env() -> color_eyre::Result<Self> {
+        Ok(Self {
+            api_token: std::env::var("CLOUDFLARE_API_TOKEN")?,
+            base_url: std::env::var("CLOUDFLARE_API_URL")
+                .unwrap_or_else(|_| DEFAULT_BASE_URL.to_string()),
+        })
+    }
+
+    #[cfg(test)]
+    pub fn new(api_token: &str, base_url: &str) -> Self {
+        Self {
+            api_token: api_token.to_string(),
+            base_url: base_url.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiMessage {
+    code: i64,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResultInfo {
+    page: u32,
+    total_pages: u32,
+}
+
+/// Every Cloudflare response is wrapped in one of these
+#[derive(Debug, Deserialize)]
+struct Envelope<T> {
+    success: bool,
+    #[serde(default)]
+    errors: Vec<ApiMessage>,
+    result: Option<T>,
+    result_info: Option<ResultInfo>,
+}
+
+impl<T> Envelope<T> {
+    fn into_result(self) -> color_eyre::Result<(T, Option<ResultInfo>)> {
+        if !self.success {
+            let errors = self
+                .errors
+                .iter()
+                .map(|e| format!("{} ({})", e.message, e.code))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            return Err(color_eyre::eyre::eyre!(
+                "Cloudflare returned errors: {errors}"
+            ));
+        }
+
+        match self.result {
+            Some(result) => Ok((result, self.result_info)),
+            None => Err(color_eyre::eyre::eyre!("Cloudflare didn't return a result")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Zone {
+    pub id: String,
+    pub name: String,
+    pub status: String,
+    #[serde(default)]
+    pub name_servers: Vec<String>,
+}
+
+/// `name` is fully qualified and `ttl` is 1 when Cloudflare picks it automatically
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsRecord {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub record_type: String,
+    pub content: String,
+    pub ttl: i32,
+    pub priority: Option<i32>,
+    pub comment: Option<String>,
+    #[serde(default)]
+    pub proxied: bool,
+}
+
+/// The fields we set when creating or editing a record, `name` is fully qualified
+#[derive(Debug, Serialize)]
+pub struct DnsRecordInput {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub record_type: String,
+    pub content: String,
+    pub ttl: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<i32>,
+}
+
+#[derive(Deserialize)]
+struct DeletedRecord {
+    #[allow(dead_code)]
+    id: String,
+}
+
+/// Talks to the Cloudflare API with one connection pool, and remembers the zone ids it has looked
+/// up since those don't change
+#[derive(Clone)]
+pub struct CloudflareClient {
+    client: reqwest::Client,
+    base_url: String,
+    api_token: String,
+    zone_ids: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl CloudflareClient {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            client: http_client(),
+            base_url: config.base_url.clone(),
+            api_token: config.api_token.clone(),
+            zone_ids: Arc::default(),
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.base_url.trim_end_matches('/'))
+    }
+
+    async fn send<T: DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> color_eyre::Result<(T, Option<ResultInfo>)> {
+        let response = request.bearer_auth(&self.api_token).send().await?;
+        let status = response.status();
+
+        let text = response.text().await?;
+
+        debug!("response: {:?}", text);
+
+        // Errors come back in the same envelope, so only fall back to the status if the body
+        // isn't one
+        match serde_json::from_str::<Envelope<T>>(&text) {
+            Ok(envelope) => envelope.into_result(),
+            Err(_) if !status.is_success() => Err(color_eyre::eyre::eyre!(
+                "Cloudflare returned {status}: {text}"
+            )),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Fetches every page of a list endpoint
+    async fn get_all<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+        per_page: u32,
+    ) -> color_eyre::Result<Vec<T>> {
+        let mut all = vec![];
+        let mut page = 1;
+
+        loop {
+            let request = self
+                .client
+                .get(self.url(path))
+                .query(query)
+                .query(&[("page", page), ("per_page", per_page)]);
+            let (results, info) = self.send::<Vec<T>>(request).await?;
+            all.extend(results);
+
+            match info {
+                Some(info) if info.page < info.total_pages => page = info.page + 1,
+                _ => return Ok(all),
+            }
+        }
+    }
+
+    /// Lists the zones the token can see, only the one called `name` if it's given
+    pub async fn list_zones(&self, name: Option<&str>) -> color_eyre::Result<Vec<Zone>> {
+        let query = name.map(|name| vec![("name", name)]).unwrap_or_default();
+
+        self.get_all("/zones", &query, ZONES_PER_PAGE).await
+    }
+
+    /// The id of the zone called `name`, looked up the first time it's needed
+    pub async fn zone_id(&self, name: &str) -> color_eyre::Result<String> {
+        if let Some(zone_id) = self.zone_ids.lock().unwrap().get(name) {
+            return Ok(zone_id.clone());
+        }
+
+        let zone_id = self
+            .list_zones(Some(name))
+            .await?
+            .into_iter()
+            .find(|zone| zone.name == name)
+            .map(|zone| zone.id)
+            .ok_or_else(|| {
+                color_eyre::eyre::eyre!("{name} isn't a zone in the Cloudflare account")
+            })?;
+        self.zone_ids
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), zone_id.clone());
+
+        Ok(zone_id)
+    }
+
+    /// Drops the cached id for `name`, for when a request with it failed in case the zone has
+    /// been recreated under a new one
+    pub fn forget_zone(&self, name: &str) {
+        self.zone_ids.lock().unwrap().remove(name);
+    }
+
+    pub async fn list_dns_records(&self, zone_id: &str) -> color_eyre::Result<Vec<DnsRecord>> {
+        self.get_all(
+            &format!("/zones/{zone_id}/dns_records"),
+            &[],
+            RECORDS_PER_PAGE,
+        )
+        .await
+    }
+
+    pub async fn create_dns_record(
+        &self,
+        zone_id: &str,
+        record: &DnsRecordInput,
+    ) -> color_eyre::Result<DnsRecord> {
+        let request = self
+            .client
+            .post(self.url(&format!("/zones/{zone_id}/dns_records")))
+            .json(record);
+        let (record, _) = self.send(request).await?;
+
+        Ok(record)
+    }
+
+    /// Uses PATCH so settings we don't track, like whether the record is proxied, are left alone
+    pub async fn update_dns_record(
+        &self,
+        zone_id: &str,
+        record_id: &str,
+        record: &DnsRecordInput,
+    ) -> color_eyre::Result<DnsRecord> {
+        let request = self
+            .client
+            .patch(self.url(&format!("/zones/{zone_id}/dns_records/{record_id}")))
+            .json(record);
+        let (record, _) = self.send(request).await?;
+
+        Ok(record)
+    }
+
+    pub async fn delete_dns_record(
+        &self,
+        zone_id: &str,
+        record_id: &str,
+    ) -> color_eyre::Result<()> {
+        let request = self
+            .client
+            .delete(self.url(&format!("/zones/{zone_id}/dns_records/{record_id}")));
+        self.send::<DeletedRecord>(request).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod mock;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn lists_every_page_of_zones() {
+        let mock = mock::MockCloudflare::start().await;
+        for i in 0..(ZONES_PER_PAGE + 5) {
+            mock.add_zone(&format!("example{i}.com"));
+        }
+
+        let zones = mock.client().list_zones(None).await.unwrap();
+        assert_eq!(zones.len(), ZONES_PER_PAGE as usize + 5);
+
+        let zones = mock
+            .client()
+            .list_zones(Some("example3.com"))
+            .await
+            .unwrap();
+        assert_eq!(zones.len(), 1);
+        assert_eq!(zones[0].name, "example3.com");
+    }
+
+    #[tokio::test]
+    async fn surfaces_cloudflare_errors() {
+        let mock = mock::MockCloudflare::start().await;
+        let zone_id = mock.add_zone("example.com");
+
+        let err = mock
+            .client()
+            .delete_dns_record(&zone_id, "missing")
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("Record not found (81044)"),
+            "{err}"
+        );
+
+        let bad_token = CloudflareClient::from_config(&Config::new("wrong", &mock.base_url()));
+        let err = bad_token.list_zones(None).await.unwrap_err();
+        assert!(err.to_string().contains("Invalid API Token"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn caches_zone_ids() {
+        let mock = mock::MockCloudflare::start().await;
+        let zone_id = mock.add_zone("example.com");
+        let client = mock.client();
+
+        assert_eq!(client.zone_id("example.com").await.unwrap(), zone_id);
+        mock.remove_zone(&zone_id);
+        assert_eq!(client.zone_id("example.com").await.unwrap(), zone_id);
+
+        client.forget_zone("example.com");
+        assert!(client.zone_id("example.com").await.is_err());
+    }
+}
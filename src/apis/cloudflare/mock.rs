@@ -0,0 +1,284 @@
+//! An in-memory stand in for the parts of the Cloudflare API we use, served on a random local
+//! port so tests can point a [`CloudflareClient`] at it
+
+use std::sync::{Arc, Mutex};
+
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::{get, patch},
+    Json, Router,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{CloudflareClient, Config, DnsRecord, Zone};
+
+const API_TOKEN: &str = "test-token";
+
+#[derive(Default)]
+struct MockState {
+    zones: Vec<Zone>,
+    /// `(zone_id, record)`
+    records: Vec<(String, DnsRecord)>,
+    next_id: u32,
+}
+
+impl MockState {
+    fn next_id(&mut self) -> String {
+        self.next_id += 1;
+        format!("{:032x}", self.next_id)
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct MockCloudflare {
+    state: Arc<Mutex<MockState>>,
+    base_url: String,
+}
+
+impl MockCloudflare {
+    pub(crate) async fn start() -> Self {
+        let state = Arc::new(Mutex::new(MockState::default()));
+
+        let app = Router::new()
+            .route("/zones", get(list_zones))
+            .route(
+                "/zones/:zone_id/dns_records",
+                get(list_records).post(create_record),
+            )
+            .route(
+                "/zones/:zone_id/dns_records/:record_id",
+                patch(update_record).delete(delete_record),
+            )
+            .layer(axum::middleware::from_fn(check_token))
+            .with_state(state.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        Self { state, base_url }
+    }
+
+    pub(crate) fn base_url(&self) -> String {
+        self.base_url.clone()
+    }
+
+    pub(crate) fn client(&self) -> CloudflareClient {
+        CloudflareClient::from_config(&Config::new(API_TOKEN, &self.base_url))
+    }
+
+    /// Returns the new zone's id
+    pub(crate) fn add_zone(&self, name: &str) -> String {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id();
+        state.zones.push(Zone {
+            id: id.clone(),
+            name: name.to_string(),
+            status: "active".to_string(),
+            name_servers: vec![
+                "ada.ns.cloudflare.com".to_string(),
+                "bob.ns.cloudflare.com".to_string(),
+            ],
+        });
+
+        id
+    }
+
+    /// Deletes a zone and its records as if it had been removed in the Cloudflare dashboard
+    pub(crate) fn remove_zone(&self, zone_id: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.zones.retain(|zone| zone.id != zone_id);
+        state.records.retain(|(id, _)| id != zone_id);
+    }
+
+    /// Adds a record as if it had been created in the Cloudflare dashboard
+    pub(crate) fn add_record(&self, zone_id: &str, record: DnsRecord) {
+        self.state
+            .lock()
+            .unwrap()
+            .records
+            .push((zone_id.to_string(), record));
+    }
+}
+
+fn error(status: StatusCode, code: i64, message: &str) -> Response {
+    (
+        status,
+        Json(json!({
+            "success": false,
+            "errors": [{ "code": code, "message": message }],
+            "messages": [],
+            "result": null,
+        })),
+    )
+        .into_response()
+}
+
+fn success(result: impl serde::Serialize) -> Response {
+    Json(json!({
+        "success": true,
+        "errors": [],
+        "messages": [],
+        "result": result,
+    }))
+    .into_response()
+}
+
+async fn check_token(request: Request, next: Next) -> Response {
+    let expected = format!("Bearer {API_TOKEN}");
+    match request.headers().get(header::AUTHORIZATION) {
+        Some(value) if value == expected.as_str() => next.run(request).await,
+        _ => error(StatusCode::UNAUTHORIZED, 1000, "Invalid API Token"),
+    }
+}
+
+#[derive(Deserialize)]
+struct ListQuery {
+    name: Option<String>,
+    page: Option<usize>,
+    per_page: Option<usize>,
+}
+
+fn paginate<T: serde::Serialize + Clone>(items: &[T], query: &ListQuery) -> Response {
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(20).max(1);
+    let total_pages = items.len().div_ceil(per_page).max(1);
+    let results = items
+        .iter()
+        .skip((page - 1) * per_page)
+        .take(per_page)
+        .cloned()
+        .collect::<Vec<_>>();
+
+    Json(json!({
+        "success": true,
+        "errors": [],
+        "messages": [],
+        "result_info": {
+            "page": page,
+            "per_page": per_page,
+            "count": results.len(),
+            "total_count": items.len(),
+            "total_pages": total_pages,
+        },
+        "result": results,
+    }))
+    .into_response()
+}
+
+type SharedState = State<Arc<Mutex<MockState>>>;
+
+async fn list_zones(State(state): SharedState, Query(query): Query<ListQuery>) -> Response {
+    let state = state.lock().unwrap();
+    let zones = state
+        .zones
+        .iter()
+        .filter(|zone| query.name.as_ref().is_none_or(|name| &zone.name == name))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    paginate(&zones, &query)
+}
+
+fn zone_exists(state: &MockState, zone_id: &str) -> bool {
+    state.zones.iter().any(|zone| zone.id == zone_id)
+}
+
+async fn list_records(
+    State(state): SharedState,
+    Path(zone_id): Path<String>,
+    Query(query): Query<ListQuery>,
+) -> Response {
+    let state = state.lock().unwrap();
+    if !zone_exists(&state, &zone_id) {
+        return error(StatusCode::NOT_FOUND, 7003, "Could not route to zone");
+    }
+
+    let records = state
+        .records
+        .iter()
+        .filter(|(id, _)| *id == zone_id)
+        .map(|(_, record)| record.clone())
+        .collect::<Vec<_>>();
+
+    paginate(&records, &query)
+}
+
+#[derive(Deserialize)]
+struct RecordBody {
+    name: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    content: String,
+    ttl: i32,
+    priority: Option<i32>,
+}
+
+async fn create_record(
+    State(state): SharedState,
+    Path(zone_id): Path<String>,
+    Json(body): Json<RecordBody>,
+) -> Response {
+    let mut state = state.lock().unwrap();
+    if !zone_exists(&state, &zone_id) {
+        return error(StatusCode::NOT_FOUND, 7003, "Could not route to zone");
+    }
+
+    let record = DnsRecord {
+        id: state.next_id(),
+        name: body.name,
+        record_type: body.record_type,
+        content: body.content,
+        ttl: body.ttl,
+        priority: body.priority,
+        comment: None,
+        proxied: false,
+    };
+    state.records.push((zone_id, record.clone()));
+
+    success(record)
+}
+
+async fn update_record(
+    State(state): SharedState,
+    Path((zone_id, record_id)): Path<(String, String)>,
+    Json(body): Json<RecordBody>,
+) -> Response {
+    let mut state = state.lock().unwrap();
+    let Some((_, record)) = state
+        .records
+        .iter_mut()
+        .find(|(zone, record)| *zone == zone_id && record.id == record_id)
+    else {
+        return error(StatusCode::NOT_FOUND, 81044, "Record not found");
+    };
+
+    record.name = body.name;
+    record.record_type = body.record_type;
+    record.content = body.content;
+    record.ttl = body.ttl;
+    record.priority = body.priority;
+
+    success(record.clone())
+}
+
+async fn delete_record(
+    State(state): SharedState,
+    Path((zone_id, record_id)): Path<(String, String)>,
+) -> Response {
+    let mut state = state.lock().unwrap();
+    let Some(index) = state
+        .records
+        .iter()
+        .position(|(zone, record)| *zone == zone_id && record.id == record_id)
+    else {
+        return error(StatusCode::NOT_FOUND, 81044, "Record not found");
+    };
+    state.records.remove(index);
+
+    success(json!({ "id": record_id }))
+}
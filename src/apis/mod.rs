@@ -1 +1,48 @@
+pub mod cloudflare;
 pub mod porkbun;
+
+/// Clients for the APIs we talk to. Built from the environment once at startup and carried in
+/// `AppState`, so they share connections and tests can point an app at their own mock servers
+#[derive(Clone, Default)]
+pub(crate) struct ApiConfig {
+    pub(crate) porkbun: Option<porkbun::PorkbunClient>,
+    pub(crate) cloudflare: Option<cloudflare::CloudflareClient>,
+}
+
+impl ApiConfig {
+    /// An API whose variables aren't set is left out, and only errors once something uses it
+    pub(crate) fn from_env() -> Self {
+        Self {
+            porkbun: porkbun::Config::from_env()
+                .ok()
+                .map(|config| porkbun::PorkbunClient::from_config(&config)),
+            cloudflare: cloudflare::Config::from_env()
+                .ok()
+                .map(|config| cloudflare::CloudflareClient::from_config(&config)),
+        }
+    }
+
+    pub(crate) fn porkbun(&self) -> color_eyre::Result<&porkbun::PorkbunClient> {
+        self.porkbun.as_ref().ok_or_else(|| {
+            color_eyre::eyre::eyre!(
+                "Porkbun isn't configured, set PORKBUN_API_KEY and PORKBUN_SECRET_API_KEY"
+            )
+        })
+    }
+
+    pub(crate) fn cloudflare(&self) -> color_eyre::Result<&cloudflare::CloudflareClient> {
+        self.cloudflare.as_ref().ok_or_else(|| {
+            color_eyre::eyre::eyre!("Cloudflare isn't configured, set CLOUDFLARE_API_TOKEN")
+        })
+    }
+}
+
+/// Only says which APIs are configured, so the keys stay out of logs
+impl std::fmt::Debug for ApiConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiConfig")
+            .field("porkbun", &self.porkbun.is_some())
+            .field("cloudflare", &self.cloudflare.is_some())
+            .finish()
+    }
+}
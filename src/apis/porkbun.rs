@@ -1,40 +1,360 @@
-use serde::{Deserialize, Serialize};
-use tracing::debug;
+use std::{
+    collections::HashMap,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
 
+use reqwest::StatusCode;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use self::rate_limit::RateLimiter;
+
+mod rate_limit;
+
+const DEFAULT_BASE_URL: &str = "https://api.porkbun.com/api/json/v3";
+
+/// Retries after a 429 or 5xx, waiting `RETRY_BASE_DELAY` doubled each time
+const MAX_RETRIES: u32 = 4;
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// What Porkbun assigns to new domains, pointing back at these turns Porkbun DNS on again
+pub const DEFAULT_NAMESERVERS: [&str; 4] = [
+    "curitiba.ns.porkbun.com",
+    "fortaleza.ns.porkbun.com",
+    "maceio.ns.porkbun.com",
+    "salvador.ns.porkbun.com",
+];
+
+/// Every `PorkbunClient` shares one connection pool
+fn http_client() -> reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+    CLIENT.get_or_init(reqwest::Client::new).clone()
+}
+
+/// Porkbun limits per API key, so every client in the process draws from one bucket. Requests
+/// that would queue for more than a few seconds fail with [`PorkbunError::Throttled`] so jobs can
+/// reschedule themselves instead of tying up a worker
+fn shared_limiter() -> Arc<RateLimiter> {
+    static LIMITER: OnceLock<Arc<RateLimiter>> = OnceLock::new();
+
+    LIMITER
+        .get_or_init(|| Arc::new(RateLimiter::new(10, 1.0, Duration::from_secs(5))))
+        .clone()
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PorkbunError {
+    #[error("Porkbun rejected the API keys: {0}")]
+    Auth(String),
+    #[error("Porkbun is rate limiting us: {0}")]
+    RateLimited(String),
+    #[error("Holding off on Porkbun requests for {0:?} to stay under the rate limit")]
+    Throttled(Duration),
+    #[error("Porkbun is having trouble: {0}")]
+    Unavailable(String),
+    #[error("Porkbun couldn't find the domain: {0}")]
+    DomainNotFound(String),
+    #[error("Porkbun returned an error: {0}")]
+    Api(String),
+    #[error("Porkbun sent a response we couldn't read: {0}")]
+    MalformedResponse(String),
+    #[error("Couldn't reach Porkbun: {0}")]
+    Http(#[from] reqwest::Error),
+}
+
+impl PorkbunError {
+    /// Porkbun reports every failure as `{"status":"ERROR","message":...}`, the HTTP status and
+    /// the message are all there is to tell them apart
+    fn from_response(status: StatusCode, message: String) -> Self {
+        let lower = message.to_ascii_lowercase();
+
+        if status == StatusCode::TOO_MANY_REQUESTS || lower.contains("rate limit") {
+            PorkbunError::RateLimited(message)
+        } else if status.is_server_error() {
+            PorkbunError::Unavailable(message)
+        } else if status == StatusCode::UNAUTHORIZED
+            || status == StatusCode::FORBIDDEN
+            || lower.contains("api key")
+            || lower.contains("not opted in to api access")
+        {
+            PorkbunError::Auth(message)
+        } else if status == StatusCode::NOT_FOUND
+            || lower.contains("invalid domain")
+            || lower.contains("domain not found")
+        {
+            PorkbunError::DomainNotFound(message)
+        } else {
+            PorkbunError::Api(message)
+        }
+    }
+
+    /// Whether the same request could succeed if we try it again shortly
+    fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            PorkbunError::RateLimited(_) | PorkbunError::Unavailable(_)
+        )
+    }
+
+    /// How long a job should wait before trying again, if this error is about load rather than
+    /// the request itself
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            PorkbunError::Throttled(wait) => Some(*wait),
+            PorkbunError::RateLimited(_) | PorkbunError::Unavailable(_) => {
+                Some(Duration::from_secs(60))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Auth<'a> {
+    apikey: &'a str,
+    secretapikey: &'a str,
+}
+
+/// Adds the credentials to a request body
+#[derive(Serialize)]
+struct Authenticated<'a, T: Serialize> {
+    #[serde(flatten)]
+    auth: Auth<'a>,
+    #[serde(flatten)]
+    body: T,
+}
+
+#[derive(Serialize)]
+struct Empty {}
+
+#[derive(Deserialize)]
+struct Envelope {
+    status: String,
+    message: Option<String>,
+}
+
+#[derive(Clone)]
 pub struct Config {
+    base_url: String,
     api_key: String,
     secret_api_key: String,
 }
 
 impl Config {
+    /// Reads the API keys, and `PORKBUN_API_URL` if the API lives somewhere other than
+    /// api.porkbun.com
     pub fn from_env() -> color_eyre::Result<Self> {
         Ok(Self {
+            base_url: std::env::var("PORKBUN_API_URL")
+                .unwrap_or_else(|_| DEFAULT_BASE_URL.to_string()),
             api_key: std::env::var("PORKBUN_API_KEY")?,
             secret_api_key: std::env::var("PORKBUN_SECRET_API_KEY")?,
         })
     }
 }
 
-#[derive(Debug, Serialize)]
-struct Auth {
-    apikey: String,
-    secretapikey: String,
+#[derive(Clone)]
+pub struct PorkbunClient {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    secret_api_key: String,
+    limiter: Arc<RateLimiter>,
+    retry_base_delay: Duration,
 }
 
-impl Auth {
+impl PorkbunClient {
     pub fn from_config(config: &Config) -> Self {
         Self {
-            apikey: config.api_key.clone(),
-            secretapikey: config.secret_api_key.clone(),
+            client: http_client(),
+            base_url: config.base_url.clone(),
+            api_key: config.api_key.clone(),
+            secret_api_key: config.secret_api_key.clone(),
+            limiter: shared_limiter(),
+            retry_base_delay: RETRY_BASE_DELAY,
+        }
+    }
+
+    /// A client with its own limiter and short retry delays, so tests don't slow each other down
+    #[cfg(test)]
+    pub fn new(api_key: &str, secret_api_key: &str, base_url: &str) -> Self {
+        Self {
+            client: http_client(),
+            base_url: base_url.to_string(),
+            api_key: api_key.to_string(),
+            secret_api_key: secret_api_key.to_string(),
+            limiter: Arc::new(RateLimiter::new(100, 100.0, Duration::from_secs(1))),
+            retry_base_delay: Duration::from_millis(10),
         }
     }
+
+    /// Every Porkbun endpoint is a POST with the credentials in the JSON body. Waits its turn with
+    /// the rate limiter, and retries with exponential backoff when Porkbun is overloaded
+    async fn post<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: impl Serialize,
+    ) -> Result<T, PorkbunError> {
+        let body = serde_json::to_value(Authenticated {
+            auth: Auth {
+                apikey: &self.api_key,
+                secretapikey: &self.secret_api_key,
+            },
+            body,
+        })
+        .expect("request bodies are plain structs that always serialize");
+
+        let mut attempt = 0;
+        loop {
+            self.limiter
+                .acquire()
+                .await
+                .map_err(PorkbunError::Throttled)?;
+
+            match self.send(path, &body).await {
+                Err(e) if e.is_retryable() && attempt < MAX_RETRIES => {
+                    let delay = self.retry_base_delay * 2_u32.pow(attempt);
+                    attempt += 1;
+                    warn!(path, attempt, ?delay, "Retrying Porkbun request: {e}");
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn send<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &serde_json::Value,
+    ) -> Result<T, PorkbunError> {
+        let response = self
+            .client
+            .post(format!("{}{path}", self.base_url.trim_end_matches('/')))
+            .json(body)
+            .send()
+            .await?;
+        let status = response.status();
+
+        let text = response.text().await?;
+
+        debug!("response: {:?}", text);
+
+        let envelope = match serde_json::from_str::<Envelope>(&text) {
+            Ok(envelope) => envelope,
+            Err(_) if !status.is_success() => {
+                return Err(PorkbunError::from_response(status, text));
+            }
+            Err(e) => return Err(PorkbunError::MalformedResponse(e.to_string())),
+        };
+        if envelope.status != "SUCCESS" {
+            return Err(PorkbunError::from_response(
+                status,
+                envelope
+                    .message
+                    .unwrap_or_else(|| format!("status {}", envelope.status)),
+            ));
+        }
+
+        serde_json::from_str(&text).map_err(|e| PorkbunError::MalformedResponse(e.to_string()))
+    }
+
+    pub async fn list_domains(&self) -> Result<Vec<PorkbunDomain>, PorkbunError> {
+        let resp: FetchDomainsResponse = self.post("/domain/listAll", Empty {}).await?;
+
+        Ok(resp.domains)
+    }
+
+    pub async fn get_nameservers(&self, domain: &str) -> Result<Vec<String>, PorkbunError> {
+        let resp: FetchDomainNameserversResponse = self
+            .post(&format!("/domain/getNs/{domain}"), Empty {})
+            .await?;
+
+        Ok(resp.ns)
+    }
+
+    pub async fn update_nameservers(
+        &self,
+        domain: &str,
+        nameservers: &[String],
+    ) -> Result<(), PorkbunError> {
+        let _: Envelope = self
+            .post(
+                &format!("/domain/updateNs/{domain}"),
+                UpdateNameserversRequest { ns: nameservers },
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn list_dns_records(&self, domain: &str) -> Result<Vec<DnsRecord>, PorkbunError> {
+        let resp: FetchDnsRecordsResponse = self
+            .post(&format!("/dns/retrieve/{domain}"), Empty {})
+            .await?;
+
+        Ok(resp.records)
+    }
+
+    /// Creates a record and returns the id Porkbun assigned to it
+    pub async fn create_dns_record(
+        &self,
+        domain: &str,
+        record: &DnsRecordInput,
+    ) -> Result<String, PorkbunError> {
+        let resp: CreateDnsRecordResponse =
+            self.post(&format!("/dns/create/{domain}"), record).await?;
+
+        // Porkbun sends the new id back as a number, everywhere else ids are strings
+        match resp.id {
+            serde_json::Value::Number(id) => Ok(id.to_string()),
+            serde_json::Value::String(id) => Ok(id),
+            id => Err(PorkbunError::MalformedResponse(format!(
+                "expected a record id, got {id}"
+            ))),
+        }
+    }
+
+    pub async fn edit_dns_record(
+        &self,
+        domain: &str,
+        record_id: &str,
+        record: &DnsRecordInput,
+    ) -> Result<(), PorkbunError> {
+        let _: Envelope = self
+            .post(&format!("/dns/edit/{domain}/{record_id}"), record)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_dns_record(
+        &self,
+        domain: &str,
+        record_id: &str,
+    ) -> Result<(), PorkbunError> {
+        let _: Envelope = self
+            .post(&format!("/dns/delete/{domain}/{record_id}"), Empty {})
+            .await?;
+
+        Ok(())
+    }
+
+    /// Current prices for every TLD Porkbun sells, keyed by TLD
+    pub async fn get_pricing(&self) -> Result<HashMap<String, TldPricing>, PorkbunError> {
+        let resp: FetchPricingResponse = self.post("/pricing/get", Empty {}).await?;
+
+        Ok(resp.pricing)
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct FetchDomainsResponse {
     pub domains: Vec<PorkbunDomain>,
 }
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PorkbunDomain {
     pub auto_renew: String,
@@ -48,41 +368,143 @@ pub struct PorkbunDomain {
     pub whois_privacy: String,
 }
 
-pub async fn fetch_domains(config: Config) -> color_eyre::Result<FetchDomainsResponse> {
-    let client = reqwest::Client::new();
-    let response = client
-        .post("https://api.porkbun.com/api/json/v3/domain/listAll")
-        .json(&Auth::from_config(&config))
-        .send()
-        .await?;
+#[derive(Serialize, Deserialize)]
+pub struct FetchPricingResponse {
+    pub pricing: HashMap<String, TldPricing>,
+}
+
+/// Prices in dollars, e.g. `"9.73"`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TldPricing {
+    pub registration: String,
+    pub renewal: String,
+    pub transfer: String,
+}
 
-    Ok(response.json().await?)
+#[derive(Serialize, Deserialize)]
+pub struct FetchDomainNameserversResponse {
+    pub status: String,
+    pub ns: Vec<String>,
 }
 
 #[derive(Serialize, Deserialize)]
-pub struct Nameserver(pub String);
+pub struct DnsRecord {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub record_type: String,
+    pub content: String,
+    pub ttl: String,
+    pub prio: Option<String>,
+    pub notes: Option<String>,
+}
 
 #[derive(Serialize, Deserialize)]
-pub struct FetchDomainNameserversResponse {
+pub struct FetchDnsRecordsResponse {
     pub status: String,
-    pub ns: Vec<Nameserver>,
+    pub records: Vec<DnsRecord>,
+}
+
+/// The fields Porkbun accepts when creating or editing a record
+///
+/// `name` is the subdomain only, blank for the root and `*` for a wildcard.
+#[derive(Debug, Serialize)]
+pub struct DnsRecordInput {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub record_type: String,
+    pub content: String,
+    pub ttl: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prio: Option<String>,
 }
 
-pub async fn fetch_domain_nameservers(
-    config: Config,
-    domain: String,
-) -> color_eyre::Result<FetchDomainNameserversResponse> {
-    let client = reqwest::Client::new();
-    let url = format!("https://api.porkbun.com/api/json/v3/domain/getNs/{domain}");
-    let response = client
-        .post(url)
-        .json(&Auth::from_config(&config))
-        .send()
-        .await?;
+#[derive(Deserialize)]
+struct CreateDnsRecordResponse {
+    id: serde_json::Value,
+}
+
+#[derive(Serialize)]
+struct UpdateNameserversRequest<'a> {
+    ns: &'a [String],
+}
+
+#[cfg(test)]
+pub(crate) mod mock;
 
-    let text = response.text().await?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    debug!("response: {:?}", text);
+    #[tokio::test]
+    async fn reads_domains_and_nameservers() {
+        let mock = mock::MockPorkbun::start().await;
+        let client = mock.client();
 
-    Ok(serde_json::from_str(&text)?)
+        let domains = client.list_domains().await.unwrap();
+        assert_eq!(domains.len(), 3);
+        assert_eq!(domains[0].domain, "coreyja.test");
+
+        let nameservers = client.get_nameservers("coreyja.test").await.unwrap();
+        assert_eq!(nameservers, DEFAULT_NAMESERVERS.map(String::from));
+
+        let new = vec!["a.example.com".to_string(), "b.example.com".to_string()];
+        client
+            .update_nameservers("coreyja.test", &new)
+            .await
+            .unwrap();
+        assert_eq!(client.get_nameservers("coreyja.test").await.unwrap(), new);
+    }
+
+    #[tokio::test]
+    async fn classifies_errors() {
+        let mock = mock::MockPorkbun::start().await;
+
+        let err = mock
+            .client()
+            .get_nameservers("not-ours.test")
+            .await
+            .unwrap_err();
+        assert!(matches!(err, PorkbunError::DomainNotFound(_)), "{err}");
+
+        let bad_keys = PorkbunClient::new("wrong", "wrong", mock.base_url());
+        let err = bad_keys.list_domains().await.unwrap_err();
+        assert!(matches!(err, PorkbunError::Auth(_)), "{err}");
+    }
+
+    #[tokio::test]
+    async fn retries_when_porkbun_is_overloaded() {
+        let mock = mock::MockPorkbun::start().await;
+        mock.fail_next(1, axum::http::StatusCode::TOO_MANY_REQUESTS);
+        mock.fail_next(2, axum::http::StatusCode::SERVICE_UNAVAILABLE);
+
+        let domains = mock.client().list_domains().await.unwrap();
+        assert_eq!(domains.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_too_many_retries() {
+        let mock = mock::MockPorkbun::start().await;
+        mock.fail_next(
+            MAX_RETRIES as usize + 1,
+            axum::http::StatusCode::TOO_MANY_REQUESTS,
+        );
+
+        let err = mock.client().list_domains().await.unwrap_err();
+        assert!(matches!(err, PorkbunError::RateLimited(_)), "{err}");
+        assert!(err.retry_after().is_some());
+    }
+
+    #[tokio::test]
+    async fn throttles_instead_of_queueing_for_long() {
+        let mock = mock::MockPorkbun::start().await;
+        let client = PorkbunClient {
+            limiter: Arc::new(RateLimiter::new(1, 0.1, Duration::from_secs(1))),
+            ..mock.client()
+        };
+
+        client.list_domains().await.unwrap();
+        let err = client.list_domains().await.unwrap_err();
+        assert!(matches!(err, PorkbunError::Throttled(_)), "{err}");
+    }
 }
@@ -0,0 +1,317 @@
+//! An in-memory stand in for the Porkbun API, seeded from the JSON in `fixtures/`
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use axum::{
+    extract::{Path, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use serde_json::{json, Value};
+
+use crate::apis::ApiConfig;
+
+use super::{DnsRecord, PorkbunClient, PorkbunDomain};
+
+pub(crate) const API_KEY: &str = "pk1_test";
+pub(crate) const SECRET_API_KEY: &str = "sk1_test";
+
+struct MockState {
+    domains: Vec<PorkbunDomain>,
+    nameservers: HashMap<String, Vec<String>>,
+    records: HashMap<String, Vec<DnsRecord>>,
+    next_id: u64,
+    /// Statuses to fail the next requests with, before any handler runs
+    failures: VecDeque<StatusCode>,
+}
+
+impl MockState {
+    fn from_fixtures() -> Self {
+        let list_all: super::FetchDomainsResponse =
+            serde_json::from_str(include_str!("fixtures/list_all.json")).unwrap();
+
+        Self {
+            domains: list_all.domains,
+            nameservers: serde_json::from_str(include_str!("fixtures/nameservers.json")).unwrap(),
+            records: serde_json::from_str(include_str!("fixtures/dns_records.json")).unwrap(),
+            next_id: 200_000_000,
+            failures: VecDeque::new(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct MockPorkbun {
+    state: Arc<Mutex<MockState>>,
+    base_url: String,
+}
+
+impl MockPorkbun {
+    pub(crate) async fn start() -> Self {
+        let state = Arc::new(Mutex::new(MockState::from_fixtures()));
+
+        let app = Router::new()
+            .route("/domain/listAll", post(list_all))
+            .route("/domain/getNs/:domain", post(get_ns))
+            .route("/domain/updateNs/:domain", post(update_ns))
+            .route("/dns/retrieve/:domain", post(retrieve))
+            .route("/dns/create/:domain", post(create))
+            .route("/dns/edit/:domain/:id", post(edit))
+            .route("/dns/delete/:domain/:id", post(delete))
+            .route("/pricing/get", post(pricing))
+            .layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                inject_failures,
+            ))
+            .with_state(state.clone());
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        Self { state, base_url }
+    }
+
+    /// A server for the whole test run, for tests that go through `AppState` (like the jobs) to
+    /// point [`MockPorkbun::api_config`] at. It runs on its own thread because each test gets a
+    /// separate runtime. Tests sharing it shouldn't change its data, use [`MockPorkbun::start`]
+    /// for that
+    pub(crate) fn shared() -> &'static MockPorkbun {
+        static SHARED: OnceLock<MockPorkbun> = OnceLock::new();
+
+        SHARED.get_or_init(|| {
+            let (tx, rx) = std::sync::mpsc::channel();
+            std::thread::spawn(move || {
+                tokio::runtime::Runtime::new().unwrap().block_on(async {
+                    tx.send(MockPorkbun::start().await).unwrap();
+                    std::future::pending::<()>().await;
+                });
+            });
+            rx.recv().unwrap()
+        })
+    }
+
+    pub(crate) fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    pub(crate) fn client(&self) -> PorkbunClient {
+        PorkbunClient::new(API_KEY, SECRET_API_KEY, &self.base_url)
+    }
+
+    /// Config for an `AppState` that should talk to this server
+    pub(crate) fn api_config(&self) -> ApiConfig {
+        ApiConfig {
+            porkbun: Some(self.client()),
+            cloudflare: None,
+        }
+    }
+
+    /// Answers the next `count` requests with `status`, whatever they are
+    pub(crate) fn fail_next(&self, count: usize, status: StatusCode) {
+        let mut state = self.state.lock().unwrap();
+        state.failures.extend(std::iter::repeat_n(status, count));
+    }
+}
+
+async fn inject_failures(State(state): SharedState, request: Request, next: Next) -> Response {
+    let failure = state.lock().unwrap().failures.pop_front();
+
+    match failure {
+        Some(StatusCode::TOO_MANY_REQUESTS) => {
+            error(StatusCode::TOO_MANY_REQUESTS, "Rate limit exceeded.")
+        }
+        Some(status) => error(status, "Something went wrong."),
+        None => next.run(request).await,
+    }
+}
+
+fn error(status: StatusCode, message: &str) -> Response {
+    (
+        status,
+        Json(json!({ "status": "ERROR", "message": message })),
+    )
+        .into_response()
+}
+
+fn success(mut body: Value) -> Response {
+    body["status"] = json!("SUCCESS");
+    Json(body).into_response()
+}
+
+/// Every request carries the keys in its body, like the real API
+fn authenticated(body: &Value) -> bool {
+    body["apikey"] == API_KEY && body["secretapikey"] == SECRET_API_KEY
+}
+
+fn invalid_keys() -> Response {
+    error(StatusCode::BAD_REQUEST, "Invalid API key. (002)")
+}
+
+fn invalid_domain() -> Response {
+    error(StatusCode::BAD_REQUEST, "Invalid domain.")
+}
+
+type SharedState = State<Arc<Mutex<MockState>>>;
+
+async fn list_all(State(state): SharedState, Json(body): Json<Value>) -> Response {
+    if !authenticated(&body) {
+        return invalid_keys();
+    }
+
+    let state = state.lock().unwrap();
+    success(json!({ "domains": state.domains }))
+}
+
+async fn get_ns(
+    State(state): SharedState,
+    Path(domain): Path<String>,
+    Json(body): Json<Value>,
+) -> Response {
+    if !authenticated(&body) {
+        return invalid_keys();
+    }
+
+    let state = state.lock().unwrap();
+    match state.nameservers.get(&domain) {
+        Some(ns) => success(json!({ "ns": ns })),
+        None => invalid_domain(),
+    }
+}
+
+async fn update_ns(
+    State(state): SharedState,
+    Path(domain): Path<String>,
+    Json(body): Json<Value>,
+) -> Response {
+    if !authenticated(&body) {
+        return invalid_keys();
+    }
+
+    let Ok(ns) = serde_json::from_value::<Vec<String>>(body["ns"].clone()) else {
+        return error(StatusCode::BAD_REQUEST, "Invalid nameservers.");
+    };
+
+    let mut state = state.lock().unwrap();
+    match state.nameservers.get_mut(&domain) {
+        Some(current) => {
+            *current = ns;
+            success(json!({}))
+        }
+        None => invalid_domain(),
+    }
+}
+
+async fn retrieve(
+    State(state): SharedState,
+    Path(domain): Path<String>,
+    Json(body): Json<Value>,
+) -> Response {
+    if !authenticated(&body) {
+        return invalid_keys();
+    }
+
+    let state = state.lock().unwrap();
+    match state.records.get(&domain) {
+        Some(records) => success(json!({ "records": records })),
+        None => invalid_domain(),
+    }
+}
+
+/// Turns a create or edit body into a record, Porkbun stores the full name
+fn record_from_body(id: String, domain: &str, body: &Value) -> DnsRecord {
+    let subdomain = body["name"].as_str().unwrap_or_default();
+    let name = if subdomain.is_empty() {
+        domain.to_string()
+    } else {
+        format!("{subdomain}.{domain}")
+    };
+
+    DnsRecord {
+        id,
+        name,
+        record_type: body["type"].as_str().unwrap_or_default().to_string(),
+        content: body["content"].as_str().unwrap_or_default().to_string(),
+        ttl: body["ttl"].as_str().unwrap_or("600").to_string(),
+        prio: body["prio"].as_str().map(String::from),
+        notes: None,
+    }
+}
+
+async fn create(
+    State(state): SharedState,
+    Path(domain): Path<String>,
+    Json(body): Json<Value>,
+) -> Response {
+    if !authenticated(&body) {
+        return invalid_keys();
+    }
+
+    let mut state = state.lock().unwrap();
+    state.next_id += 1;
+    let id = state.next_id;
+    let Some(records) = state.records.get_mut(&domain) else {
+        return invalid_domain();
+    };
+    records.push(record_from_body(id.to_string(), &domain, &body));
+
+    // The real API sends the id back as a number
+    success(json!({ "id": id }))
+}
+
+async fn edit(
+    State(state): SharedState,
+    Path((domain, id)): Path<(String, String)>,
+    Json(body): Json<Value>,
+) -> Response {
+    if !authenticated(&body) {
+        return invalid_keys();
+    }
+
+    let mut state = state.lock().unwrap();
+    let Some(records) = state.records.get_mut(&domain) else {
+        return invalid_domain();
+    };
+    let Some(record) = records.iter_mut().find(|r| r.id == id) else {
+        return error(StatusCode::BAD_REQUEST, "Edit error: Invalid record ID.");
+    };
+    *record = record_from_body(id, &domain, &body);
+
+    success(json!({}))
+}
+
+async fn delete(
+    State(state): SharedState,
+    Path((domain, id)): Path<(String, String)>,
+    Json(body): Json<Value>,
+) -> Response {
+    if !authenticated(&body) {
+        return invalid_keys();
+    }
+
+    let mut state = state.lock().unwrap();
+    let Some(records) = state.records.get_mut(&domain) else {
+        return invalid_domain();
+    };
+    let Some(index) = records.iter().position(|r| r.id == id) else {
+        return error(StatusCode::BAD_REQUEST, "Delete error: Invalid record ID.");
+    };
+    records.remove(index);
+
+    success(json!({}))
+}
+
+async fn pricing(Json(body): Json<Value>) -> Response {
+    if !authenticated(&body) {
+        return invalid_keys();
+    }
+
+    let pricing: Value = serde_json::from_str(include_str!("fixtures/pricing.json")).unwrap();
+    success(json!({ "pricing": pricing }))
+}
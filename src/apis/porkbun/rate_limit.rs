@@ -0,0 +1,92 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// A token bucket. Each request takes a token and tokens refill at a steady rate, so short bursts
+/// go straight through but a long run of requests settles at `per_second`
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    capacity: f64,
+    per_second: f64,
+    /// Callers wait in line for up to this long, past that they're told to come back later
+    max_wait: Duration,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Goes negative while callers are waiting on tokens that haven't refilled yet
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(capacity: u32, per_second: f64, max_wait: Duration) -> Self {
+        Self {
+            capacity: capacity as f64,
+            per_second,
+            max_wait,
+            bucket: Mutex::new(Bucket {
+                tokens: capacity as f64,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Takes a token, returning how long to wait before using it. `Err` with the wait if that's
+    /// longer than `max_wait`, in which case no token was taken
+    fn take(&self, now: Instant) -> Result<Duration, Duration> {
+        let mut bucket = self.bucket.lock().unwrap();
+
+        let elapsed = now.saturating_duration_since(bucket.refilled_at);
+        bucket.tokens =
+            (bucket.tokens + elapsed.as_secs_f64() * self.per_second).min(self.capacity);
+        bucket.refilled_at = now;
+
+        let wait = if bucket.tokens >= 1.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64((1.0 - bucket.tokens) / self.per_second)
+        };
+        if wait > self.max_wait {
+            return Err(wait);
+        }
+
+        bucket.tokens -= 1.0;
+        Ok(wait)
+    }
+
+    /// Waits for a token, or returns how long until one is free if that's too long to wait
+    pub(crate) async fn acquire(&self) -> Result<(), Duration> {
+        let wait = self.take(Instant::now())?;
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bursts_then_waits_for_refills() {
+        let limiter = RateLimiter::new(2, 1.0, Duration::from_secs(2));
+        let start = Instant::now();
+
+        assert_eq!(limiter.take(start), Ok(Duration::ZERO));
+        assert_eq!(limiter.take(start), Ok(Duration::ZERO));
+        assert_eq!(limiter.take(start), Ok(Duration::from_secs(1)));
+        assert_eq!(limiter.take(start), Ok(Duration::from_secs(2)));
+        // The queue is already two seconds long
+        assert_eq!(limiter.take(start), Err(Duration::from_secs(3)));
+
+        // Refilling pays back the queued tokens before there are any to spare
+        let later = start + Duration::from_secs(3);
+        assert_eq!(limiter.take(later), Ok(Duration::ZERO));
+        assert_eq!(limiter.take(later), Ok(Duration::from_secs(1)));
+    }
+}
@@ -7,10 +7,17 @@ use cja::{
     app_state::AppState as _,
     server::session::{DBSession, SessionRedirect},
 };
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
 use crate::AppState;
 
+/// API and calendar tokens are random, so a plain hash is enough to keep them out of the
+/// database
+pub(crate) fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
 #[allow(dead_code)]
 pub(crate) struct User {
     pub(crate) user_id: Uuid,
@@ -19,6 +26,7 @@ pub(crate) struct User {
     pub(crate) is_admin: bool,
     pub(crate) created_at: chrono::DateTime<chrono::Utc>,
     pub(crate) updated_at: chrono::DateTime<chrono::Utc>,
+    pub(crate) calendar_token_hash: Option<String>,
 }
 
 #[allow(dead_code)]
@@ -0,0 +1,119 @@
+//! An iCalendar (RFC 5545) feed of domain expiry dates
+
+use chrono::{DateTime, Days, Utc};
+
+use crate::routes::domains::Domain;
+
+/// Lines longer than this many octets have to be folded
+const MAX_LINE_LEN: usize = 75;
+
+/// Escapes a TEXT value
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Splits long content lines with CRLF followed by a space, without breaking up characters
+fn push_line(out: &mut String, line: &str) {
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > MAX_LINE_LEN {
+            out.push_str("\r\n ");
+            len = 1;
+        }
+        out.push(c);
+        len += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+/// One all-day event per domain on its expiry date, with an alarm for each of
+/// `reminder_days` before it
+pub(crate) fn expiry_calendar(
+    domains: &[Domain],
+    reminder_days: &[i32],
+    now: DateTime<Utc>,
+) -> String {
+    let mut out = String::new();
+    let dtstamp = now.format("%Y%m%dT%H%M%SZ");
+
+    for line in [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        "PRODID:-//coreyja//domains//EN",
+        "CALSCALE:GREGORIAN",
+        "METHOD:PUBLISH",
+        "X-WR-CALNAME:Domain expiries",
+    ] {
+        push_line(&mut out, line);
+    }
+
+    for domain in domains {
+        let expire_date = domain.expire_date.date_naive();
+        let (summary, description) = if domain.auto_renew {
+            (
+                format!("{} renews", domain.domain),
+                format!(
+                    "{} expires today and auto-renew is on, so {} should renew it.",
+                    domain.domain,
+                    domain.registrar_name()
+                ),
+            )
+        } else {
+            (
+                format!("{} expires", domain.domain),
+                format!(
+                    "{} expires today and auto-renew is off. Renew it on {} if you want to keep it.",
+                    domain.domain,
+                    domain.registrar_name()
+                ),
+            )
+        };
+
+        push_line(&mut out, "BEGIN:VEVENT");
+        push_line(
+            &mut out,
+            &format!(
+                "UID:{}-{}@domains.coreyja.com",
+                domain.domain_id,
+                expire_date.format("%Y%m%d")
+            ),
+        );
+        push_line(&mut out, &format!("DTSTAMP:{dtstamp}"));
+        push_line(
+            &mut out,
+            &format!("DTSTART;VALUE=DATE:{}", expire_date.format("%Y%m%d")),
+        );
+        if let Some(next_day) = expire_date.checked_add_days(Days::new(1)) {
+            push_line(
+                &mut out,
+                &format!("DTEND;VALUE=DATE:{}", next_day.format("%Y%m%d")),
+            );
+        }
+        push_line(&mut out, &format!("SUMMARY:{}", escape(&summary)));
+        push_line(&mut out, &format!("DESCRIPTION:{}", escape(&description)));
+        push_line(&mut out, "TRANSP:TRANSPARENT");
+
+        for days in reminder_days {
+            push_line(&mut out, "BEGIN:VALARM");
+            push_line(&mut out, "ACTION:DISPLAY");
+            push_line(
+                &mut out,
+                &format!(
+                    "DESCRIPTION:{}",
+                    escape(&format!("{summary} in {days} days"))
+                ),
+            );
+            push_line(&mut out, &format!("TRIGGER:-P{days}D"));
+            push_line(&mut out, "END:VALARM");
+        }
+
+        push_line(&mut out, "END:VEVENT");
+    }
+
+    push_line(&mut out, "END:VCALENDAR");
+
+    out
+}
@@ -4,7 +4,10 @@ use cja::cron::{CronRegistry, Worker};
 
 use crate::{
     jobs::{
+        prune_domain_job_runs::PruneDomainJobRuns,
         refresh_domain_nameservers::RefreshDomainsNameservers, refresh_domains::RefreshDomains,
+        refresh_tld_prices::RefreshTldPrices, rollup_redirect_hits::RollupRedirectHits,
+        send_expiry_alerts::SendExpiryAlerts,
     },
     AppState,
 };
@@ -20,8 +23,12 @@ pub fn one_day() -> Duration {
 fn cron_registry() -> CronRegistry<AppState> {
     let mut registry = CronRegistry::new();
 
-    registry.register_job(RefreshDomains, one_hour());
+    registry.register_job(RefreshDomains::default(), one_hour());
     registry.register_job(RefreshDomainsNameservers, one_day());
+    registry.register_job(RollupRedirectHits, one_hour());
+    registry.register_job(PruneDomainJobRuns, one_day());
+    registry.register_job(SendExpiryAlerts, one_day());
+    registry.register_job(RefreshTldPrices, one_day());
 
     registry
 }
@@ -0,0 +1,345 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::routes::domains::DnsProvider;
+
+/// Record types Porkbun lets us manage through its API
+pub(crate) const RECORD_TYPES: [&str; 12] = [
+    "A", "AAAA", "CNAME", "ALIAS", "MX", "TXT", "NS", "SRV", "TLSA", "CAA", "HTTPS", "SVCB",
+];
+
+/// Record types that use the `prio` field
+pub(crate) const PRIO_RECORD_TYPES: [&str; 2] = ["MX", "SRV"];
+
+/// Porkbun rejects anything lower than this
+pub(crate) const MIN_TTL: i32 = 600;
+
+/// Cloudflare stores its "Auto" TTL as 1
+pub(crate) const AUTO_TTL: i32 = 1;
+
+/// The TTLs a DNS provider accepts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct TtlLimits {
+    pub(crate) min: i32,
+    pub(crate) max: Option<i32>,
+    /// Whether [`AUTO_TTL`] is allowed as well
+    pub(crate) auto: bool,
+}
+
+impl TtlLimits {
+    pub(crate) fn for_provider(provider: DnsProvider) -> Self {
+        match provider {
+            DnsProvider::Cloudflare => Self {
+                min: 60,
+                max: Some(86400),
+                auto: true,
+            },
+            _ => Self {
+                min: MIN_TTL,
+                max: None,
+                auto: false,
+            },
+        }
+    }
+
+    /// The lowest TTL allowed, which is also what new records start with
+    pub(crate) fn lowest(&self) -> i32 {
+        if self.auto {
+            AUTO_TTL
+        } else {
+            self.min
+        }
+    }
+
+    pub(crate) fn validate(&self, ttl: i32) -> Result<i32, String> {
+        if self.auto && ttl == AUTO_TTL {
+            return Ok(ttl);
+        }
+
+        if ttl < self.min {
+            return Err(if self.auto {
+                format!("TTL must be {AUTO_TTL} for Auto or at least {}", self.min)
+            } else {
+                format!("TTL must be at least {}", self.min)
+            });
+        }
+        if let Some(max) = self.max.filter(|max| ttl > *max) {
+            return Err(format!("TTL can't be more than {max}"));
+        }
+
+        Ok(ttl)
+    }
+
+    /// The closest TTL to `ttl` the provider will take
+    pub(crate) fn clamp(&self, ttl: i32) -> i32 {
+        if self.auto && ttl == AUTO_TTL {
+            return ttl;
+        }
+
+        let ttl = ttl.max(self.min);
+        self.max.map_or(ttl, |max| ttl.min(max))
+    }
+
+    /// How to show `ttl` in a table
+    pub(crate) fn display(&self, ttl: i32) -> String {
+        if self.auto && ttl == AUTO_TTL {
+            "Auto".to_string()
+        } else {
+            ttl.to_string()
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub(crate) struct DnsRecord {
+    pub(crate) dns_record_id: Uuid,
+    pub(crate) domain_id: Uuid,
+    pub(crate) provider_record_id: String,
+    pub(crate) name: String,
+    pub(crate) record_type: String,
+    pub(crate) content: String,
+    pub(crate) ttl: i32,
+    pub(crate) prio: Option<i32>,
+    pub(crate) notes: Option<String>,
+    pub(crate) created_at: chrono::DateTime<chrono::Utc>,
+    pub(crate) updated_at: chrono::DateTime<chrono::Utc>,
+    pub(crate) provider: String,
+}
+
+impl DnsRecord {
+    /// The part of `name` in front of `domain`, empty for the root
+    pub(crate) fn subdomain(&self, domain: &str) -> String {
+        subdomain_of(&self.name, domain)
+    }
+
+    pub(crate) fn snapshot(&self) -> RecordSnapshot {
+        RecordSnapshot {
+            name: self.name.clone(),
+            record_type: self.record_type.clone(),
+            content: self.content.clone(),
+            ttl: self.ttl,
+            prio: self.prio,
+            notes: self.notes.clone(),
+        }
+    }
+}
+
+/// The parts of a record we compare between syncs, stored as JSON in `DnsRecordChanges`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct RecordSnapshot {
+    pub(crate) name: String,
+    pub(crate) record_type: String,
+    pub(crate) content: String,
+    pub(crate) ttl: i32,
+    pub(crate) prio: Option<i32>,
+    pub(crate) notes: Option<String>,
+}
+
+impl std::fmt::Display for RecordSnapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} ", self.name, self.record_type)?;
+        if let Some(prio) = self.prio {
+            write!(f, "{prio} ")?;
+        }
+        write!(f, "{} (TTL {})", self.content, self.ttl)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ChangeType {
+    Added,
+    Removed,
+    Modified,
+}
+
+impl ChangeType {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            ChangeType::Added => "added",
+            ChangeType::Removed => "removed",
+            ChangeType::Modified => "modified",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct RecordChange {
+    pub(crate) provider_record_id: String,
+    pub(crate) change_type: ChangeType,
+    pub(crate) previous: Option<RecordSnapshot>,
+    pub(crate) current: Option<RecordSnapshot>,
+}
+
+/// Compares two snapshots of a domain's records, keyed by the id their DNS provider gave them
+pub(crate) fn diff_records(
+    previous: &HashMap<String, RecordSnapshot>,
+    current: &HashMap<String, RecordSnapshot>,
+) -> Vec<RecordChange> {
+    let mut changes = vec![];
+
+    for (id, record) in current {
+        match previous.get(id) {
+            None => changes.push(RecordChange {
+                provider_record_id: id.clone(),
+                change_type: ChangeType::Added,
+                previous: None,
+                current: Some(record.clone()),
+            }),
+            Some(old) if old != record => changes.push(RecordChange {
+                provider_record_id: id.clone(),
+                change_type: ChangeType::Modified,
+                previous: Some(old.clone()),
+                current: Some(record.clone()),
+            }),
+            Some(_) => {}
+        }
+    }
+
+    for (id, record) in previous {
+        if !current.contains_key(id) {
+            changes.push(RecordChange {
+                provider_record_id: id.clone(),
+                change_type: ChangeType::Removed,
+                previous: Some(record.clone()),
+                current: None,
+            });
+        }
+    }
+
+    changes.sort_by(|a, b| a.provider_record_id.cmp(&b.provider_record_id));
+    changes
+}
+
+/// Strips `domain` off a fully qualified record name. Porkbun stores full names but wants just
+/// the subdomain when creating or editing
+pub(crate) fn subdomain_of(name: &str, domain: &str) -> String {
+    if name == domain {
+        return String::new();
+    }
+
+    name.strip_suffix(&format!(".{domain}"))
+        .unwrap_or(name)
+        .to_string()
+}
+
+/// The inverse of [`subdomain_of`]
+pub(crate) fn fqdn(subdomain: &str, domain: &str) -> String {
+    if subdomain.is_empty() {
+        domain.to_string()
+    } else {
+        format!("{subdomain}.{domain}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(content: &str) -> RecordSnapshot {
+        RecordSnapshot {
+            name: "coreyja.tv".to_string(),
+            record_type: "A".to_string(),
+            content: content.to_string(),
+            ttl: 600,
+            prio: None,
+            notes: None,
+        }
+    }
+
+    fn records(records: &[(&str, RecordSnapshot)]) -> HashMap<String, RecordSnapshot> {
+        records
+            .iter()
+            .map(|(id, record)| (id.to_string(), record.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn diffs_records_by_provider_id() {
+        let previous = records(&[
+            ("1", snapshot("10.0.0.1")),
+            ("2", snapshot("10.0.0.2")),
+            ("3", snapshot("10.0.0.3")),
+        ]);
+        let current = records(&[
+            ("1", snapshot("10.0.0.1")),
+            (
+                "2",
+                RecordSnapshot {
+                    ttl: 3600,
+                    ..snapshot("10.0.0.2")
+                },
+            ),
+            ("4", snapshot("10.0.0.4")),
+        ]);
+
+        let changes = diff_records(&previous, &current);
+        let summary = changes
+            .iter()
+            .map(|c| (c.provider_record_id.as_str(), c.change_type))
+            .collect::<Vec<_>>();
+        // Sorted by id, and the unchanged record isn't included
+        assert_eq!(
+            summary,
+            [
+                ("2", ChangeType::Modified),
+                ("3", ChangeType::Removed),
+                ("4", ChangeType::Added),
+            ]
+        );
+
+        assert_eq!(changes[0].previous.as_ref().map(|r| r.ttl), Some(600));
+        assert_eq!(changes[0].current.as_ref().map(|r| r.ttl), Some(3600));
+        assert_eq!(changes[1].previous, Some(snapshot("10.0.0.3")));
+        assert_eq!(changes[1].current, None);
+        assert_eq!(changes[2].previous, None);
+        assert_eq!(changes[2].current, Some(snapshot("10.0.0.4")));
+    }
+
+    #[test]
+    fn notices_changes_to_notes_and_priority() {
+        let previous = records(&[("1", snapshot("10.0.0.1"))]);
+
+        for changed in [
+            RecordSnapshot {
+                notes: Some("Home server".to_string()),
+                ..snapshot("10.0.0.1")
+            },
+            RecordSnapshot {
+                prio: Some(10),
+                ..snapshot("10.0.0.1")
+            },
+        ] {
+            let changes = diff_records(&previous, &records(&[("1", changed)]));
+            assert_eq!(changes.len(), 1);
+            assert_eq!(changes[0].change_type, ChangeType::Modified);
+        }
+
+        assert!(diff_records(&previous, &previous).is_empty());
+        assert!(diff_records(&HashMap::new(), &HashMap::new()).is_empty());
+    }
+
+    #[test]
+    fn limits_ttls_per_provider() {
+        let porkbun = TtlLimits::for_provider(DnsProvider::Porkbun);
+        assert_eq!(porkbun.lowest(), 600);
+        assert!(porkbun.validate(AUTO_TTL).is_err());
+        assert!(porkbun.validate(599).is_err());
+        assert_eq!(porkbun.validate(86400 * 7), Ok(86400 * 7));
+        assert_eq!(porkbun.clamp(300), 600);
+        assert_eq!(porkbun.display(600), "600");
+
+        let cloudflare = TtlLimits::for_provider(DnsProvider::Cloudflare);
+        assert_eq!(cloudflare.lowest(), AUTO_TTL);
+        assert_eq!(cloudflare.validate(AUTO_TTL), Ok(AUTO_TTL));
+        assert_eq!(cloudflare.validate(60), Ok(60));
+        assert!(cloudflare.validate(30).is_err());
+        assert!(cloudflare.validate(86401).is_err());
+        assert_eq!(cloudflare.clamp(AUTO_TTL), AUTO_TTL);
+        assert_eq!(cloudflare.clamp(30), 60);
+        assert_eq!(cloudflare.clamp(604800), 86400);
+        assert_eq!(cloudflare.display(AUTO_TTL), "Auto");
+    }
+}
@@ -0,0 +1,130 @@
+//! The DNS providers we can read and edit records with. A domain's records are synced from
+//! whichever of these its nameservers point at, see [`client`]
+
+use crate::{apis::ApiConfig, registrars::porkbun::Porkbun, routes::domains::DnsProvider};
+
+pub(crate) mod cloudflare;
+
+/// A DNS record as the provider reports it, `name` is fully qualified
+#[derive(Debug, Clone)]
+pub(crate) struct HostedRecord {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    pub(crate) record_type: String,
+    pub(crate) content: String,
+    pub(crate) ttl: i32,
+    pub(crate) prio: Option<i32>,
+    pub(crate) notes: Option<String>,
+}
+
+/// A record to create or update. `subdomain` is blank for the root
+#[derive(Debug, Clone)]
+pub(crate) struct RecordInput {
+    pub(crate) subdomain: String,
+    pub(crate) record_type: String,
+    pub(crate) content: String,
+    pub(crate) ttl: i32,
+    pub(crate) prio: Option<i32>,
+}
+
+#[async_trait::async_trait]
+pub(crate) trait DnsHost: Send + Sync {
+    fn provider(&self) -> DnsProvider;
+
+    async fn get_records(&self, domain: &str) -> color_eyre::Result<Vec<HostedRecord>>;
+
+    /// Returns the id the provider assigned to the new record
+    async fn create_record(&self, domain: &str, record: &RecordInput)
+        -> color_eyre::Result<String>;
+
+    async fn update_record(
+        &self,
+        domain: &str,
+        record_id: &str,
+        record: &RecordInput,
+    ) -> color_eyre::Result<()>;
+
+    async fn delete_record(&self, domain: &str, record_id: &str) -> color_eyre::Result<()>;
+}
+
+/// The providers [`client`] has an implementation for
+pub(crate) const MANAGED_PROVIDERS: [DnsProvider; 2] =
+    [DnsProvider::Porkbun, DnsProvider::Cloudflare];
+
+/// A client for `provider`, or `None` if it isn't one we can manage records with
+pub(crate) fn client(
+    provider: DnsProvider,
+    apis: &ApiConfig,
+) -> color_eyre::Result<Option<Box<dyn DnsHost>>> {
+    Ok(match provider {
+        DnsProvider::Porkbun => Some(Box::new(Porkbun::new(apis.porkbun()?.clone()))),
+        DnsProvider::Cloudflare => Some(Box::new(cloudflare::Cloudflare::new(
+            apis.cloudflare()?.clone(),
+        ))),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        apis::{cloudflare::mock::MockCloudflare, porkbun::mock::MockPorkbun},
+        dns::fqdn,
+    };
+
+    use super::*;
+
+    fn input(subdomain: &str, content: &str) -> RecordInput {
+        RecordInput {
+            subdomain: subdomain.to_string(),
+            record_type: "A".to_string(),
+            content: content.to_string(),
+            ttl: 600,
+            prio: None,
+        }
+    }
+
+    /// Creates, edits and deletes a record on `domain`, checking the listing after each step
+    async fn manages_records(host: &dyn DnsHost, domain: &str) {
+        let existing = host.get_records(domain).await.unwrap().len();
+
+        let id = host
+            .create_record(domain, &input("api", "1.2.3.4"))
+            .await
+            .unwrap();
+        let records = host.get_records(domain).await.unwrap();
+        assert_eq!(records.len(), existing + 1);
+        let created = records.iter().find(|r| r.id == id).unwrap();
+        assert_eq!(created.name, fqdn("api", domain));
+        assert_eq!(created.content, "1.2.3.4");
+
+        host.update_record(domain, &id, &input("api", "5.6.7.8"))
+            .await
+            .unwrap();
+        let records = host.get_records(domain).await.unwrap();
+        assert_eq!(
+            records.iter().find(|r| r.id == id).unwrap().content,
+            "5.6.7.8"
+        );
+
+        host.delete_record(domain, &id).await.unwrap();
+        let records = host.get_records(domain).await.unwrap();
+        assert_eq!(records.len(), existing);
+        assert!(records.iter().all(|r| r.id != id));
+    }
+
+    #[tokio::test]
+    async fn manages_porkbun_records() {
+        let mock = MockPorkbun::start().await;
+
+        manages_records(&Porkbun::new(mock.client()), "coreyja.test").await;
+    }
+
+    #[tokio::test]
+    async fn manages_cloudflare_records() {
+        let mock = MockCloudflare::start().await;
+        mock.add_zone("example.com");
+
+        manages_records(&cloudflare::Cloudflare::new(mock.client()), "example.com").await;
+    }
+}
@@ -0,0 +1,162 @@
+use crate::{
+    apis::cloudflare::{CloudflareClient, DnsRecordInput},
+    dns::fqdn,
+    routes::domains::DnsProvider,
+};
+
+use super::{DnsHost, HostedRecord, RecordInput};
+
+/// Cloudflare addresses records by zone, and a domain's zone has the same name as it. The client
+/// caches the zone ids, so a failed request drops the domain's in case the zone was recreated
+pub(crate) struct Cloudflare {
+    client: CloudflareClient,
+}
+
+impl Cloudflare {
+    pub(crate) fn new(client: CloudflareClient) -> Self {
+        Self { client }
+    }
+
+    fn forget_zone_on_error<T>(
+        &self,
+        domain: &str,
+        result: color_eyre::Result<T>,
+    ) -> color_eyre::Result<T> {
+        if result.is_err() {
+            self.client.forget_zone(domain);
+        }
+
+        result
+    }
+}
+
+fn record_input(record: &RecordInput, domain: &str) -> DnsRecordInput {
+    DnsRecordInput {
+        name: fqdn(&record.subdomain, domain),
+        record_type: record.record_type.clone(),
+        content: record.content.clone(),
+        ttl: record.ttl,
+        priority: record.prio,
+    }
+}
+
+#[async_trait::async_trait]
+impl DnsHost for Cloudflare {
+    fn provider(&self) -> DnsProvider {
+        DnsProvider::Cloudflare
+    }
+
+    async fn get_records(&self, domain: &str) -> color_eyre::Result<Vec<HostedRecord>> {
+        let zone_id = self.client.zone_id(domain).await?;
+        let records =
+            self.forget_zone_on_error(domain, self.client.list_dns_records(&zone_id).await)?;
+
+        Ok(records
+            .into_iter()
+            .map(|record| HostedRecord {
+                id: record.id,
+                name: record.name,
+                record_type: record.record_type,
+                content: record.content,
+                ttl: record.ttl,
+                prio: record.priority,
+                notes: record.comment.filter(|comment| !comment.is_empty()),
+            })
+            .collect())
+    }
+
+    async fn create_record(
+        &self,
+        domain: &str,
+        record: &RecordInput,
+    ) -> color_eyre::Result<String> {
+        let zone_id = self.client.zone_id(domain).await?;
+        let created = self.forget_zone_on_error(
+            domain,
+            self.client
+                .create_dns_record(&zone_id, &record_input(record, domain))
+                .await,
+        )?;
+
+        Ok(created.id)
+    }
+
+    async fn update_record(
+        &self,
+        domain: &str,
+        record_id: &str,
+        record: &RecordInput,
+    ) -> color_eyre::Result<()> {
+        let zone_id = self.client.zone_id(domain).await?;
+        self.forget_zone_on_error(
+            domain,
+            self.client
+                .update_dns_record(&zone_id, record_id, &record_input(record, domain))
+                .await,
+        )?;
+
+        Ok(())
+    }
+
+    async fn delete_record(&self, domain: &str, record_id: &str) -> color_eyre::Result<()> {
+        let zone_id = self.client.zone_id(domain).await?;
+        self.forget_zone_on_error(
+            domain,
+            self.client.delete_dns_record(&zone_id, record_id).await,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::apis::cloudflare::{mock::MockCloudflare, DnsRecord};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn syncs_records_from_the_matching_zone() {
+        let mock = MockCloudflare::start().await;
+        mock.add_zone("other.com");
+        let zone_id = mock.add_zone("example.com");
+        mock.add_record(
+            &zone_id,
+            DnsRecord {
+                id: "abc".to_string(),
+                name: "example.com".to_string(),
+                record_type: "MX".to_string(),
+                content: "mail.example.com".to_string(),
+                ttl: 1,
+                priority: Some(10),
+                comment: Some(String::new()),
+                proxied: false,
+            },
+        );
+        let host = Cloudflare::new(mock.client());
+
+        let records = host.get_records("example.com").await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].id, "abc");
+        assert_eq!(records[0].prio, Some(10));
+        assert_eq!(records[0].notes, None);
+
+        let id = host
+            .create_record(
+                "example.com",
+                &RecordInput {
+                    subdomain: "www".to_string(),
+                    record_type: "CNAME".to_string(),
+                    content: "example.com".to_string(),
+                    ttl: 600,
+                    prio: None,
+                },
+            )
+            .await
+            .unwrap();
+        let records = host.get_records("example.com").await.unwrap();
+        let created = records.iter().find(|r| r.id == id).unwrap();
+        assert_eq!(created.name, "www.example.com");
+
+        let err = host.get_records("missing.com").await.unwrap_err();
+        assert!(err.to_string().contains("isn't a zone"), "{err}");
+    }
+}
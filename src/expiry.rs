@@ -0,0 +1,51 @@
+use chrono::Utc;
+
+use crate::routes::domains::Domain;
+
+const DEFAULT_ALERT_DAYS: [i32; 3] = [60, 30, 7];
+
+/// How many days ahead of expiry to alert, from `EXPIRY_ALERT_DAYS` (e.g. `60,30,7`), largest
+/// first
+pub(crate) fn alert_windows() -> Vec<i32> {
+    let mut windows = std::env::var("EXPIRY_ALERT_DAYS")
+        .ok()
+        .map(|days| {
+            days.split(',')
+                .filter_map(|day| day.trim().parse::<i32>().ok())
+                .filter(|day| *day > 0)
+                .collect::<Vec<_>>()
+        })
+        .filter(|windows| !windows.is_empty())
+        .unwrap_or_else(|| DEFAULT_ALERT_DAYS.to_vec());
+
+    windows.sort_unstable_by(|a, b| b.cmp(a));
+    windows.dedup();
+    windows
+}
+
+/// The smallest window `domain` falls into, so a domain that is already close to expiring only
+/// gets the most urgent alert instead of every window at once
+pub(crate) fn current_window(domain: &Domain, windows: &[i32]) -> Option<i32> {
+    let days_left = domain.days_until_expiry();
+
+    windows
+        .iter()
+        .copied()
+        .filter(|window| days_left <= i64::from(*window))
+        .min()
+}
+
+/// Domains that haven't expired yet but will within `days`, soonest first
+pub(crate) async fn expiring_domains(db: &sqlx::PgPool, days: i32) -> sqlx::Result<Vec<Domain>> {
+    sqlx::query_as!(
+        Domain,
+        "SELECT * FROM Domains
+        WHERE removed_at IS NULL
+          AND expire_date > $1 AND expire_date <= $1 + make_interval(days => $2)
+        ORDER BY expire_date",
+        Utc::now(),
+        days
+    )
+    .fetch_all(db)
+    .await
+}
@@ -0,0 +1,71 @@
+use cja::app_state::AppState as _;
+use maud::{html, Markup, Render};
+use serde::{Deserialize, Serialize};
+use tower_cookies::{Cookie, Cookies};
+
+use crate::AppState;
+
+const FLASH_COOKIE: &str = "flash";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub(crate) enum FlashLevel {
+    Success,
+    Error,
+}
+
+/// A one-off message shown on the next page load, usually after a form submission redirects
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Flash {
+    pub(crate) level: FlashLevel,
+    pub(crate) message: String,
+}
+
+impl Flash {
+    pub(crate) fn success(message: impl Into<String>) -> Self {
+        Self {
+            level: FlashLevel::Success,
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn error(message: impl Into<String>) -> Self {
+        Self {
+            level: FlashLevel::Error,
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn set(&self, cookies: &Cookies, app_state: &AppState) {
+        let value = serde_json::to_string(self).expect("Flash is always serializable");
+
+        let mut cookie = Cookie::new(FLASH_COOKIE, value);
+        cookie.set_path("/");
+
+        cookies.private(app_state.cookie_key()).add(cookie);
+    }
+
+    /// Reads the pending flash, if any, and clears it so it is only shown once
+    pub(crate) fn take(cookies: &Cookies, app_state: &AppState) -> Option<Self> {
+        let private = cookies.private(app_state.cookie_key());
+        let cookie = private.get(FLASH_COOKIE)?;
+
+        let mut removal = Cookie::new(FLASH_COOKIE, "");
+        removal.set_path("/");
+        private.remove(removal);
+
+        serde_json::from_str(cookie.value()).ok()
+    }
+}
+
+impl Render for Flash {
+    fn render(&self) -> Markup {
+        let class = match self.level {
+            FlashLevel::Success => "flash flash-success",
+            FlashLevel::Error => "flash flash-error",
+        };
+
+        html! {
+            p class=(class) role="status" { (self.message) }
+        }
+    }
+}
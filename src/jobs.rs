@@ -1,13 +1,238 @@
+use chrono::{DateTime, Utc};
+use cja::{app_state::AppState as _, jobs::Job};
+use deliver_notification::DeliverNotification;
+use refresh_domain_dns_records::RefreshDomainDnsRecords;
 use refresh_domain_nameservers::{RefreshDomainNameservers, RefreshDomainsNameservers};
+use tracing::warn;
+use uuid::Uuid;
 
-use crate::{jobs::refresh_domains::RefreshDomains, AppState};
+use crate::{
+    apis::porkbun::PorkbunError,
+    jobs::{
+        prune_domain_job_runs::PruneDomainJobRuns, refresh_domains::RefreshDomains,
+        refresh_tld_prices::RefreshTldPrices, rollup_redirect_hits::RollupRedirectHits,
+        send_expiry_alerts::SendExpiryAlerts,
+    },
+    AppState,
+};
 
+mod delayed;
+pub mod deliver_notification;
+pub mod prune_domain_job_runs;
+pub mod refresh_domain_dns_records;
 pub mod refresh_domain_nameservers;
 pub mod refresh_domains;
+pub mod refresh_tld_prices;
+pub mod rollup_redirect_hits;
+pub mod send_expiry_alerts;
+
+pub(crate) use delayed::enqueue_at;
 
 cja::impl_job_registry!(
     AppState,
     RefreshDomains,
     RefreshDomainsNameservers,
-    RefreshDomainNameservers
+    RefreshDomainNameservers,
+    RefreshDomainDnsRecords,
+    RefreshTldPrices,
+    RollupRedirectHits,
+    PruneDomainJobRuns,
+    SendExpiryAlerts,
+    DeliverNotification
 );
+
+/// How many times a job is tried when Porkbun is rate limiting us or down before it's reported
+/// as failed
+const MAX_ATTEMPTS: u32 = 5;
+
+/// A job that counts how many times it has been put back on the queue, in its payload
+pub(crate) trait Retry: Job<AppState> {
+    /// Starting from 0
+    fn attempt(&self) -> u32;
+
+    /// The same job, for the next attempt
+    fn retry(&self) -> Self;
+}
+
+/// Puts `job` back on the queue if it failed because Porkbun asked us to slow down, returning
+/// whether it did. Those failures sort themselves out so they aren't worth reporting, unless
+/// Porkbun keeps failing for [`MAX_ATTEMPTS`]. Our own limiter holding us back never made a
+/// request, so that is always rescheduled without counting an attempt
+pub(crate) async fn reschedule_if_throttled<J: Retry>(
+    app_state: &AppState,
+    job: &J,
+    error: &color_eyre::Report,
+) -> cja::Result<bool> {
+    let Some(porkbun_error) = error.downcast_ref::<PorkbunError>() else {
+        return Ok(false);
+    };
+    let Some(wait) = porkbun_error.retry_after() else {
+        return Ok(false);
+    };
+
+    let job = if matches!(porkbun_error, PorkbunError::Throttled(_)) {
+        job.clone()
+    } else if job.attempt() + 1 < MAX_ATTEMPTS {
+        job.retry()
+    } else {
+        warn!(
+            job = J::NAME,
+            attempts = MAX_ATTEMPTS,
+            "Giving up on job: {error}"
+        );
+        return Ok(false);
+    };
+
+    warn!(
+        job = J::NAME,
+        ?wait,
+        attempt = job.attempt(),
+        "Rescheduling job: {error}"
+    );
+    enqueue_at(
+        app_state,
+        &job,
+        Utc::now() + chrono::Duration::from_std(wait)?,
+        format!("Rescheduled after: {error}"),
+    )
+    .await?;
+
+    Ok(true)
+}
+
+/// How a per-domain job run ended, stored in `DomainJobRuns.status`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum JobRunStatus {
+    Succeeded,
+    Failed,
+    /// Porkbun asked us to slow down, so the job was queued again for later
+    Rescheduled,
+}
+
+impl JobRunStatus {
+    pub(crate) const ALL: [JobRunStatus; 3] = [
+        JobRunStatus::Succeeded,
+        JobRunStatus::Failed,
+        JobRunStatus::Rescheduled,
+    ];
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            JobRunStatus::Succeeded => "succeeded",
+            JobRunStatus::Failed => "failed",
+            JobRunStatus::Rescheduled => "rescheduled",
+        }
+    }
+
+    pub(crate) fn parse(status: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|s| s.as_str() == status)
+            .ok_or_else(|| format!("{status} isn't a valid job run status"))
+    }
+}
+
+impl std::fmt::Display for JobRunStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JobRunStatus::Succeeded => write!(f, "Succeeded"),
+            JobRunStatus::Failed => write!(f, "Failed"),
+            JobRunStatus::Rescheduled => write!(f, "Rescheduled"),
+        }
+    }
+}
+
+/// Keeps a record of a job that ran for one domain, for the history on the domain's page
+pub(crate) async fn record_domain_job_run<J: Job<AppState>>(
+    app_state: &AppState,
+    domain_id: Uuid,
+    started_at: DateTime<Utc>,
+    status: JobRunStatus,
+    error: Option<&color_eyre::Report>,
+) -> cja::Result<()> {
+    sqlx::query!(
+        "INSERT INTO DomainJobRuns (domain_job_run_id, domain_id, job_name, status, error, started_at)
+        VALUES ($1, $2, $3, $4, $5, $6)",
+        Uuid::new_v4(),
+        domain_id,
+        J::NAME,
+        status.as_str(),
+        error.map(|e| format!("{e:#}")),
+        started_at
+    )
+    .execute(app_state.db())
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use sqlx::PgPool;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn reschedules_throttled_jobs(pool: PgPool) {
+        let app_state = AppState::with_pool(pool.clone()).unwrap();
+        let job = RefreshDomainNameservers::new(Uuid::new_v4());
+
+        let other = color_eyre::eyre::eyre!("Something else went wrong");
+        assert!(!reschedule_if_throttled(&app_state, &job, &other)
+            .await
+            .unwrap());
+
+        let throttled = PorkbunError::Throttled(Duration::from_secs(30)).into();
+        assert!(reschedule_if_throttled(&app_state, &job, &throttled)
+            .await
+            .unwrap());
+
+        let jobs = sqlx::query!("SELECT * FROM Jobs")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].name, RefreshDomainNameservers::NAME);
+        assert!(jobs[0].run_at > Utc::now() + chrono::Duration::seconds(25));
+        // Waiting on our own limiter doesn't count as an attempt
+        assert_eq!(jobs[0].payload["attempt"], 0);
+    }
+
+    #[test]
+    fn reads_payloads_queued_before_the_domains_rename() {
+        let domain_id = Uuid::new_v4();
+        let payload = serde_json::json!({ "porkbun_domain_id": domain_id });
+
+        let job: RefreshDomainNameservers = serde_json::from_value(payload.clone()).unwrap();
+        assert_eq!(job.domain_id, domain_id);
+        let job: RefreshDomainDnsRecords = serde_json::from_value(payload).unwrap();
+        assert_eq!(job.domain_id, domain_id);
+    }
+
+    #[sqlx::test]
+    async fn gives_up_when_porkbun_keeps_failing(pool: PgPool) {
+        let app_state = AppState::with_pool(pool.clone()).unwrap();
+        let mut job = RefreshDomainNameservers::new(Uuid::new_v4());
+        let unavailable = PorkbunError::Unavailable("Down for maintenance".to_string()).into();
+
+        for attempt in 1..MAX_ATTEMPTS {
+            assert!(reschedule_if_throttled(&app_state, &job, &unavailable)
+                .await
+                .unwrap());
+
+            let payload =
+                sqlx::query_scalar!("SELECT payload FROM Jobs ORDER BY created_at DESC LIMIT 1")
+                    .fetch_one(&pool)
+                    .await
+                    .unwrap();
+            job = serde_json::from_value(payload).unwrap();
+            assert_eq!(job.attempt(), attempt);
+        }
+
+        assert!(!reschedule_if_throttled(&app_state, &job, &unavailable)
+            .await
+            .unwrap());
+    }
+}
@@ -0,0 +1,73 @@
+//! cja's `Job::enqueue` always queues a job to run straight away. Everything that needs a job to
+//! wait goes through [`enqueue_at`] instead, which writes the same row into cja's `Jobs` table
+//! with a later `run_at`. The worker only picks up jobs whose `run_at` has passed, so nothing else
+//! is needed to delay them.
+//!
+//! This is the one place that knows cja's table layout. If a cja upgrade changes what `enqueue`
+//! inserts, the test below will start failing and this needs to follow it.
+
+use chrono::{DateTime, Utc};
+use cja::{app_state::AppState as _, jobs::Job};
+use uuid::Uuid;
+
+use crate::AppState;
+
+/// Like `Job::enqueue` but the job won't be picked up before `run_at`
+pub(crate) async fn enqueue_at<J: Job<AppState>>(
+    app_state: &AppState,
+    job: &J,
+    run_at: DateTime<Utc>,
+    context: String,
+) -> cja::Result<()> {
+    sqlx::query!(
+        "INSERT INTO Jobs (job_id, name, payload, priority, run_at, context)
+        VALUES ($1, $2, $3, 0, $4, $5)",
+        Uuid::new_v4(),
+        J::NAME,
+        serde_json::to_value(job)?,
+        run_at,
+        context
+    )
+    .execute(app_state.db())
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use crate::jobs::refresh_domain_nameservers::RefreshDomainNameservers;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn writes_the_same_row_as_cja(pool: PgPool) {
+        let app_state = AppState::with_pool(pool.clone()).unwrap();
+        let job = RefreshDomainNameservers::new(Uuid::new_v4());
+        let run_at = Utc::now() + chrono::Duration::hours(1);
+
+        job.clone()
+            .enqueue(app_state.clone(), "now".to_string())
+            .await
+            .unwrap();
+        enqueue_at(&app_state, &job, run_at, "later".to_string())
+            .await
+            .unwrap();
+
+        let jobs = sqlx::query!("SELECT * FROM Jobs ORDER BY run_at")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(jobs.len(), 2);
+        let (now, later) = (&jobs[0], &jobs[1]);
+        assert_eq!(later.name, now.name);
+        assert_eq!(later.payload, now.payload);
+        assert_eq!(later.priority, now.priority);
+        assert_eq!(later.locked_at, now.locked_at);
+        assert_eq!(later.locked_by, now.locked_by);
+        assert_eq!(later.context, "later");
+        assert_eq!(later.run_at.timestamp(), run_at.timestamp());
+    }
+}
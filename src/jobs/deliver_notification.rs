@@ -0,0 +1,105 @@
+use chrono::{Duration, Utc};
+use cja::{app_state::AppState as _, jobs::Job};
+use color_eyre::eyre::eyre;
+use uuid::Uuid;
+
+use crate::{
+    jobs::enqueue_at,
+    notifications::{Event, Notification, NotificationChannel},
+    AppState,
+};
+
+/// Attempts before a delivery is marked as failed
+const MAX_ATTEMPTS: i32 = 5;
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct DeliverNotification {
+    pub notification_delivery_id: Uuid,
+}
+
+#[async_trait::async_trait]
+impl Job<AppState> for DeliverNotification {
+    const NAME: &'static str = "DeliverNotification";
+
+    /// Failures are recorded on the delivery and retried with exponential backoff rather than
+    /// failing the job, so the delivery log always shows why something didn't arrive
+    async fn run(&self, app_state: AppState) -> cja::Result<()> {
+        let Some(delivery) = sqlx::query!(
+            "SELECT * FROM NotificationDeliveries WHERE notification_delivery_id = $1",
+            self.notification_delivery_id
+        )
+        .fetch_optional(app_state.db())
+        .await?
+        else {
+            return Ok(());
+        };
+        if delivery.status != "pending" {
+            return Ok(());
+        }
+
+        let channel = sqlx::query_as!(
+            NotificationChannel,
+            "SELECT * FROM NotificationChannels WHERE notification_channel_id = $1",
+            delivery.notification_channel_id
+        )
+        .fetch_one(app_state.db())
+        .await?;
+
+        let notification = Notification {
+            event: Event::parse(&delivery.event).map_err(|e| eyre!(e))?,
+            subject: delivery.subject,
+            body: delivery.body,
+        };
+        let result = match channel.notifier() {
+            Ok(notifier) => notifier.notify(&notification).await,
+            Err(e) => Err(e),
+        };
+
+        let attempts = delivery.attempts + 1;
+        match result {
+            Ok(()) => {
+                sqlx::query!(
+                    "UPDATE NotificationDeliveries
+                    SET status = 'delivered', attempts = $1, last_error = NULL, delivered_at = NOW(), updated_at = NOW()
+                    WHERE notification_delivery_id = $2",
+                    attempts,
+                    self.notification_delivery_id
+                )
+                .execute(app_state.db())
+                .await?;
+            }
+            Err(e) => {
+                let status = if attempts < MAX_ATTEMPTS {
+                    "pending"
+                } else {
+                    "failed"
+                };
+
+                sqlx::query!(
+                    "UPDATE NotificationDeliveries
+                    SET status = $1, attempts = $2, last_error = $3, updated_at = NOW()
+                    WHERE notification_delivery_id = $4",
+                    status,
+                    attempts,
+                    format!("{e:#}"),
+                    self.notification_delivery_id
+                )
+                .execute(app_state.db())
+                .await?;
+
+                if attempts < MAX_ATTEMPTS {
+                    let backoff = Duration::minutes(2_i64.pow(attempts as u32));
+                    enqueue_at(
+                        &app_state,
+                        self,
+                        Utc::now() + backoff,
+                        format!("Retry {attempts} of notification delivery"),
+                    )
+                    .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use cja::{app_state::AppState as _, jobs::Job};
+
+use crate::AppState;
+
+/// How long the per-domain job history is kept, matching the raw redirect hits
+const JOB_RUN_RETENTION_DAYS: i32 = 30;
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct PruneDomainJobRuns;
+
+#[async_trait::async_trait]
+impl Job<AppState> for PruneDomainJobRuns {
+    const NAME: &'static str = "PruneDomainJobRuns";
+
+    /// Every domain gets a couple of runs a day, so without this the history grows forever
+    async fn run(&self, app_state: AppState) -> cja::Result<()> {
+        sqlx::query!(
+            "DELETE FROM DomainJobRuns WHERE finished_at < NOW() - make_interval(days => $1)",
+            JOB_RUN_RETENTION_DAYS
+        )
+        .execute(app_state.db())
+        .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn deletes_runs_past_the_retention(pool: PgPool) {
+        let domain_id = Uuid::new_v4();
+        sqlx::query!(
+            "INSERT INTO Domains
+              (domain_id, auto_renew, purchase_date, domain, expire_date, not_local, security_lock, status, tld, whois_privacy, registrar)
+            VALUES ($1, false, NOW(), 'history.test', NOW(), false, false, NULL, 'test', false, 'porkbun')",
+            domain_id
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        for days_ago in [1, JOB_RUN_RETENTION_DAYS + 1] {
+            sqlx::query!(
+                "INSERT INTO DomainJobRuns (domain_job_run_id, domain_id, job_name, status, started_at, finished_at)
+                VALUES ($1, $2, 'RefreshDomainNameservers', 'succeeded', NOW() - make_interval(days => $3), NOW() - make_interval(days => $3))",
+                Uuid::new_v4(),
+                domain_id,
+                days_ago
+            )
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        PruneDomainJobRuns
+            .run(AppState::with_pool(pool.clone()).unwrap())
+            .await
+            .unwrap();
+
+        let remaining = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM DomainJobRuns WHERE finished_at > NOW() - INTERVAL '2 days'"#
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        let total = sqlx::query_scalar!(r#"SELECT COUNT(*) AS "count!" FROM DomainJobRuns"#)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!((remaining, total), (1, 1));
+    }
+}
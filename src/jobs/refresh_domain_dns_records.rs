@@ -0,0 +1,430 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+use cja::{app_state::AppState as _, jobs::Job};
+use tracing::{debug, info};
+use uuid::Uuid;
+
+use crate::{
+    dns::{diff_records, DnsRecord, RecordChange, RecordSnapshot},
+    dns_hosts::MANAGED_PROVIDERS,
+    jobs::{record_domain_job_run, reschedule_if_throttled, JobRunStatus, Retry},
+    notifications::{emit, emit_sync_failure, Event, Notification},
+    routes::domains::Domain,
+    AppState,
+};
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct RefreshDomainDnsRecords {
+    /// Queued before `PorkbunDomains` became `Domains` under its old name
+    #[serde(alias = "porkbun_domain_id")]
+    pub domain_id: Uuid,
+    #[serde(default)]
+    attempt: u32,
+}
+
+impl Retry for RefreshDomainDnsRecords {
+    fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    fn retry(&self) -> Self {
+        Self {
+            attempt: self.attempt + 1,
+            ..self.clone()
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Job<AppState> for RefreshDomainDnsRecords {
+    const NAME: &'static str = "RefreshDomainDnsRecords";
+
+    async fn run(&self, app_state: AppState) -> cja::Result<()> {
+        let started_at = Utc::now();
+        let db_domain = sqlx::query_as!(
+            Domain,
+            "SELECT * FROM Domains WHERE domain_id = $1",
+            self.domain_id
+        )
+        .fetch_one(app_state.db())
+        .await?;
+
+        // Jobs queued before the domain left the registrar
+        if db_domain.removed_at.is_some() {
+            return Ok(());
+        }
+
+        // Records are synced from whichever provider the nameservers point at. Registrars keep
+        // records around even when the domain points somewhere else, but those aren't the ones
+        // being served
+        let provider = db_domain.dns_provider();
+        if !MANAGED_PROVIDERS.contains(&provider) {
+            debug!(
+                domain = db_domain.domain,
+                %provider,
+                "Skipping DNS sync, can't read records from this provider"
+            );
+
+            // Anything synced before the domain moved here is no longer what's being served. The
+            // sync history goes too, so moving back starts from a fresh baseline instead of
+            // reporting every record as added
+            let mut tx = app_state.db().begin().await?;
+            sqlx::query!(
+                "DELETE FROM DnsRecords WHERE domain_id = $1",
+                self.domain_id
+            )
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query!(
+                "DELETE FROM DnsRecordSyncs WHERE domain_id = $1",
+                self.domain_id
+            )
+            .execute(&mut *tx)
+            .await?;
+            tx.commit().await?;
+
+            return Ok(());
+        }
+
+        let changes = match self.sync(&app_state, &db_domain).await {
+            Ok(changes) => changes,
+            Err(e) => {
+                if reschedule_if_throttled(&app_state, self, &e).await? {
+                    record_domain_job_run::<Self>(
+                        &app_state,
+                        self.domain_id,
+                        started_at,
+                        JobRunStatus::Rescheduled,
+                        Some(&e),
+                    )
+                    .await?;
+                    return Ok(());
+                }
+
+                record_domain_job_run::<Self>(
+                    &app_state,
+                    self.domain_id,
+                    started_at,
+                    JobRunStatus::Failed,
+                    Some(&e),
+                )
+                .await?;
+                emit_sync_failure(
+                    &app_state,
+                    format!("Syncing DNS records for {} failed", db_domain.domain),
+                    &e,
+                )
+                .await?;
+                return Err(e);
+            }
+        };
+
+        record_domain_job_run::<Self>(
+            &app_state,
+            self.domain_id,
+            started_at,
+            JobRunStatus::Succeeded,
+            None,
+        )
+        .await?;
+
+        if !changes.is_empty() {
+            info!(
+                domain = db_domain.domain,
+                changes = changes.len(),
+                "DNS records changed outside the dashboard"
+            );
+
+            let body = changes
+                .iter()
+                .map(|change| match (&change.previous, &change.current) {
+                    (Some(previous), Some(current)) => format!("Modified: {previous} → {current}"),
+                    (None, Some(current)) => format!("Added: {current}"),
+                    (Some(previous), None) => format!("Removed: {previous}"),
+                    (None, None) => String::new(),
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            emit(
+                &app_state,
+                Notification {
+                    event: Event::DnsDrift,
+                    subject: format!(
+                        "{} DNS records changed on {}",
+                        changes.len(),
+                        db_domain.domain
+                    ),
+                    body,
+                },
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl RefreshDomainDnsRecords {
+    pub(crate) fn new(domain_id: Uuid) -> Self {
+        Self {
+            domain_id,
+            attempt: 0,
+        }
+    }
+
+    /// Pulls the records from the domain's DNS provider and stores them, returning what changed
+    /// since the last sync
+    async fn sync(
+        &self,
+        app_state: &AppState,
+        db_domain: &Domain,
+    ) -> cja::Result<Vec<RecordChange>> {
+        let host = db_domain.dns_host(&app_state.apis)?.ok_or_else(|| {
+            color_eyre::eyre::eyre!("Can't sync records from {}", db_domain.dns_provider())
+        })?;
+        let provider = host.provider().as_str();
+        let records = host.get_records(&db_domain.domain).await?;
+
+        let current = records
+            .into_iter()
+            .map(|record| {
+                (
+                    record.id,
+                    RecordSnapshot {
+                        name: record.name,
+                        record_type: record.record_type,
+                        content: record.content,
+                        ttl: record.ttl,
+                        prio: record.prio,
+                        notes: record.notes,
+                    },
+                )
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut tx = app_state.db().begin().await?;
+
+        let previous = sqlx::query_as!(
+            DnsRecord,
+            "SELECT * FROM DnsRecords WHERE domain_id = $1 AND provider = $2",
+            self.domain_id,
+            provider
+        )
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|record| (record.provider_record_id.clone(), record.snapshot()))
+        .collect::<HashMap<_, _>>();
+
+        // Without an earlier sync from this provider there is nothing to compare against, so the
+        // first one just becomes the baseline instead of reporting every record as added
+        let baseline = !sqlx::query_scalar!(
+            r#"SELECT EXISTS(
+              SELECT 1 FROM DnsRecordSyncs WHERE domain_id = $1 AND provider = $2
+            ) AS "exists!""#,
+            self.domain_id,
+            provider
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+        let changes = if baseline {
+            vec![]
+        } else {
+            diff_records(&previous, &current)
+        };
+
+        for (provider_record_id, record) in &current {
+            sqlx::query!(
+                "INSERT INTO DnsRecords
+                  (dns_record_id, domain_id, provider, provider_record_id, name, record_type, content, ttl, prio, notes)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                ON CONFLICT (domain_id, provider, provider_record_id)
+                DO UPDATE SET
+                  name = excluded.name,
+                  record_type = excluded.record_type,
+                  content = excluded.content,
+                  ttl = excluded.ttl,
+                  prio = excluded.prio,
+                  notes = excluded.notes,
+                  updated_at = NOW()",
+                Uuid::new_v4(),
+                self.domain_id,
+                provider,
+                provider_record_id,
+                record.name,
+                record.record_type,
+                record.content,
+                record.ttl,
+                record.prio,
+                record.notes
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        // Also clears out records left over from a provider the domain has moved away from
+        let seen_ids = current.keys().cloned().collect::<Vec<_>>();
+        sqlx::query!(
+            "DELETE FROM DnsRecords
+            WHERE domain_id = $1 AND (provider != $2 OR provider_record_id != ALL($3))",
+            self.domain_id,
+            provider,
+            &seen_ids
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let dns_record_sync_id = Uuid::new_v4();
+        sqlx::query!(
+            "INSERT INTO DnsRecordSyncs (dns_record_sync_id, domain_id, provider, baseline, change_count)
+            VALUES ($1, $2, $3, $4, $5)",
+            dns_record_sync_id,
+            self.domain_id,
+            provider,
+            baseline,
+            changes.len() as i32
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        for change in &changes {
+            sqlx::query!(
+                "INSERT INTO DnsRecordChanges
+                  (dns_record_change_id, dns_record_sync_id, provider_record_id, change_type, previous, current)
+                VALUES ($1, $2, $3, $4, $5, $6)",
+                Uuid::new_v4(),
+                dns_record_sync_id,
+                change.provider_record_id,
+                change.change_type.as_str(),
+                change.previous.as_ref().map(serde_json::to_value).transpose()?,
+                change.current.as_ref().map(serde_json::to_value).transpose()?
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use crate::apis::porkbun::mock::MockPorkbun;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn clears_records_once_the_provider_cant_be_synced(pool: PgPool) {
+        let domain_id = Uuid::new_v4();
+        sqlx::query!(
+            "INSERT INTO Domains
+              (domain_id, auto_renew, purchase_date, domain, expire_date, not_local, security_lock, status, tld, whois_privacy, registrar, nameservers)
+            VALUES ($1, false, NOW(), 'moved.test', NOW(), false, false, NULL, 'test', false, 'porkbun', ARRAY['ns1.vercel-dns.com', 'ns2.vercel-dns.com'])",
+            domain_id
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query!(
+            "INSERT INTO DnsRecords
+              (dns_record_id, domain_id, provider, provider_record_id, name, record_type, content, ttl)
+            VALUES ($1, $2, 'porkbun', '123', 'moved.test', 'A', '127.0.0.1', 600)",
+            Uuid::new_v4(),
+            domain_id
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        RefreshDomainDnsRecords::new(domain_id)
+            .run(AppState::with_pool(pool.clone()).unwrap())
+            .await
+            .unwrap();
+
+        let remaining = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM DnsRecords WHERE domain_id = $1"#,
+            domain_id
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(remaining, 0);
+    }
+
+    #[sqlx::test]
+    async fn starts_a_new_baseline_after_moving_back(pool: PgPool) {
+        let mock = MockPorkbun::start().await;
+        let mut app_state = AppState::with_pool(pool.clone()).unwrap();
+        app_state.apis = mock.api_config();
+
+        let domain_id = Uuid::new_v4();
+        sqlx::query!(
+            "INSERT INTO Domains
+              (domain_id, auto_renew, purchase_date, domain, expire_date, not_local, security_lock, status, tld, whois_privacy, registrar, nameservers)
+            VALUES ($1, false, NOW(), 'coreyja.test', NOW(), false, false, NULL, 'test', false, 'porkbun', ARRAY[]::TEXT[])",
+            domain_id
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let set_nameservers = |nameservers: Vec<String>| {
+            let pool = pool.clone();
+            async move {
+                sqlx::query!(
+                    "UPDATE Domains SET nameservers = $2 WHERE domain_id = $1",
+                    domain_id,
+                    &nameservers
+                )
+                .execute(&pool)
+                .await
+                .unwrap();
+            }
+        };
+
+        RefreshDomainDnsRecords::new(domain_id)
+            .run(app_state.clone())
+            .await
+            .unwrap();
+
+        set_nameservers(vec![
+            "ns1.vercel-dns.com".to_string(),
+            "ns2.vercel-dns.com".to_string(),
+        ])
+        .await;
+        RefreshDomainDnsRecords::new(domain_id)
+            .run(app_state.clone())
+            .await
+            .unwrap();
+
+        set_nameservers(vec![]).await;
+        RefreshDomainDnsRecords::new(domain_id)
+            .run(app_state.clone())
+            .await
+            .unwrap();
+
+        let syncs = sqlx::query!(
+            "SELECT baseline, change_count FROM DnsRecordSyncs WHERE domain_id = $1",
+            domain_id
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(syncs.len(), 1);
+        assert!(syncs[0].baseline);
+        assert_eq!(syncs[0].change_count, 0);
+
+        let records = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM DnsRecords WHERE domain_id = $1"#,
+            domain_id
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(records, 3);
+    }
+}
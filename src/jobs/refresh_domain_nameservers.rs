@@ -1,7 +1,20 @@
+use chrono::{Duration, Utc};
 use cja::{app_state::AppState as _, jobs::Job};
 use uuid::Uuid;
 
-use crate::{apis::porkbun::fetch_domain_nameservers, routes::domains::PorkbunDomain, AppState};
+use crate::{
+    jobs::{
+        enqueue_at, record_domain_job_run, refresh_domain_dns_records::RefreshDomainDnsRecords,
+        reschedule_if_throttled, JobRunStatus, Retry,
+    },
+    notifications::emit_sync_failure,
+    routes::domains::{DnsProvider, Domain},
+    AppState,
+};
+
+/// Each domain's refreshes start this long after the previous domain's, so a large portfolio
+/// trickles through under Porkbun's rate limit rather than arriving all at once
+const STAGGER: Duration = Duration::seconds(2);
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct RefreshDomainsNameservers;
@@ -12,18 +25,21 @@ impl Job<AppState> for RefreshDomainsNameservers {
 
     async fn run(&self, app_state: AppState) -> cja::Result<()> {
         let domains = sqlx::query_as!(
-            PorkbunDomain,
-            "SELECT * FROM PorkbunDomains ORDER BY purchase_date DESC"
+            Domain,
+            "SELECT * FROM Domains WHERE removed_at IS NULL ORDER BY purchase_date DESC"
         )
         .fetch_all(app_state.db())
         .await?;
 
-        for domain in domains {
-            RefreshDomainNameservers {
-                porkbun_domain_id: domain.porkbun_domain_id,
-            }
-            .enqueue(
-                app_state.clone(),
+        let now = Utc::now();
+        for (i, domain) in domains.iter().enumerate() {
+            let run_at = now + STAGGER * i as i32;
+
+            // Each of these queues the domain's DNS record sync once it has stored the provider
+            enqueue_at(
+                &app_state,
+                &RefreshDomainNameservers::new(domain.domain_id),
+                run_at,
                 "RefreshDomainsNameservers bulk".to_string(),
             )
             .await?;
@@ -35,35 +51,267 @@ impl Job<AppState> for RefreshDomainsNameservers {
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct RefreshDomainNameservers {
-    pub porkbun_domain_id: Uuid,
+    /// Queued before `PorkbunDomains` became `Domains` under its old name
+    #[serde(alias = "porkbun_domain_id")]
+    pub domain_id: Uuid,
+    #[serde(default)]
+    attempt: u32,
+}
+
+impl Retry for RefreshDomainNameservers {
+    fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    fn retry(&self) -> Self {
+        Self {
+            attempt: self.attempt + 1,
+            ..self.clone()
+        }
+    }
 }
 #[async_trait::async_trait]
 impl Job<AppState> for RefreshDomainNameservers {
     const NAME: &'static str = "RefreshDomainNameservers";
 
     async fn run(&self, app_state: AppState) -> cja::Result<()> {
-        let config =
-            crate::apis::porkbun::Config::from_env().expect("Failed to get porkbun config");
-
+        let started_at = Utc::now();
         let db_domain = sqlx::query_as!(
-            PorkbunDomain,
-            "SELECT * FROM PorkbunDomains WHERE porkbun_domain_id = $1",
-            self.porkbun_domain_id
+            Domain,
+            "SELECT * FROM Domains WHERE domain_id = $1",
+            self.domain_id
         )
         .fetch_one(app_state.db())
         .await?;
 
-        let resp = fetch_domain_nameservers(config, db_domain.domain).await?;
-        let nameservers: Vec<_> = resp.ns.into_iter().map(|ns| ns.0).collect();
+        // Jobs queued before the domain left the registrar
+        if db_domain.removed_at.is_some() {
+            return Ok(());
+        }
+
+        if let Err(e) = self.refresh(&app_state, &db_domain).await {
+            if reschedule_if_throttled(&app_state, self, &e).await? {
+                record_domain_job_run::<Self>(
+                    &app_state,
+                    self.domain_id,
+                    started_at,
+                    JobRunStatus::Rescheduled,
+                    Some(&e),
+                )
+                .await?;
+                return Ok(());
+            }
+
+            record_domain_job_run::<Self>(
+                &app_state,
+                self.domain_id,
+                started_at,
+                JobRunStatus::Failed,
+                Some(&e),
+            )
+            .await?;
+            emit_sync_failure(
+                &app_state,
+                format!("Syncing nameservers for {} failed", db_domain.domain),
+                &e,
+            )
+            .await?;
+            return Err(e);
+        }
+
+        record_domain_job_run::<Self>(
+            &app_state,
+            self.domain_id,
+            started_at,
+            JobRunStatus::Succeeded,
+            None,
+        )
+        .await?;
+
+        Ok(())
+    }
+}
+
+impl RefreshDomainNameservers {
+    pub(crate) fn new(domain_id: Uuid) -> Self {
+        Self {
+            domain_id,
+            attempt: 0,
+        }
+    }
+
+    async fn refresh(&self, app_state: &AppState, db_domain: &Domain) -> cja::Result<()> {
+        let nameservers = db_domain
+            .registrar(&app_state.apis)?
+            .get_nameservers(&db_domain.domain)
+            .await?;
 
         sqlx::query!(
-            "UPDATE PorkbunDomains SET nameservers = $1 WHERE porkbun_domain_id = $2",
+            "UPDATE Domains SET nameservers = $1, dns_provider = $2 WHERE domain_id = $3",
             &nameservers,
-            self.porkbun_domain_id
+            DnsProvider::from_nameservers(&nameservers).as_str(),
+            self.domain_id
         )
         .execute(app_state.db())
         .await?;
 
+        // Only now that the provider is stored, so the records are synced from the right place
+        RefreshDomainDnsRecords::new(self.domain_id)
+            .enqueue(app_state.clone(), "Nameservers refreshed".to_string())
+            .await?;
+
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use crate::{
+        apis::porkbun::{mock::MockPorkbun, PorkbunError},
+        jobs::refresh_domains::RefreshDomains,
+    };
+
+    use super::*;
+
+    async fn domain_id(pool: &PgPool, domain: &str) -> Uuid {
+        sqlx::query!("SELECT domain_id FROM Domains WHERE domain = $1", domain)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+            .domain_id
+    }
+
+    #[sqlx::test]
+    async fn stores_the_nameservers(pool: PgPool) {
+        let mut app_state = AppState::with_pool(pool.clone()).unwrap();
+        app_state.apis = MockPorkbun::shared().api_config();
+        RefreshDomains::default()
+            .run(app_state.clone())
+            .await
+            .unwrap();
+
+        let domain_id = domain_id(&pool, "coreyja.cloud.test").await;
+        RefreshDomainNameservers::new(domain_id)
+            .run(app_state)
+            .await
+            .unwrap();
+
+        let domain = sqlx::query!(
+            "SELECT nameservers, dns_provider FROM Domains WHERE domain_id = $1",
+            domain_id
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            domain.nameservers,
+            ["ada.ns.cloudflare.com", "bob.ns.cloudflare.com"]
+        );
+        assert_eq!(domain.dns_provider, DnsProvider::Cloudflare.as_str());
+
+        let record_syncs = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM Jobs
+            WHERE name = 'RefreshDomainDnsRecords' AND payload->>'domain_id' = $1"#,
+            domain_id.to_string()
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(record_syncs, 1);
+
+        let runs = sqlx::query!(
+            "SELECT * FROM DomainJobRuns WHERE domain_id = $1",
+            domain_id
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].job_name, RefreshDomainNameservers::NAME);
+        assert_eq!(runs[0].status, JobRunStatus::Succeeded.as_str());
+    }
+
+    #[sqlx::test]
+    async fn staggers_the_per_domain_jobs(pool: PgPool) {
+        let mut app_state = AppState::with_pool(pool.clone()).unwrap();
+        app_state.apis = MockPorkbun::shared().api_config();
+        RefreshDomains::default()
+            .run(app_state.clone())
+            .await
+            .unwrap();
+        sqlx::query!(
+            "INSERT INTO Domains
+              (domain_id, auto_renew, purchase_date, domain, expire_date, not_local, security_lock, status, tld, whois_privacy, registrar, removed_at)
+            VALUES ($1, false, NOW(), 'former.test', NOW(), false, false, NULL, 'test', false, 'porkbun', NOW())",
+            Uuid::new_v4()
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        RefreshDomainsNameservers.run(app_state).await.unwrap();
+
+        let jobs = sqlx::query!(
+            "SELECT run_at FROM Jobs WHERE name = 'RefreshDomainNameservers' ORDER BY run_at"
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        // Nothing for the removed domain
+        assert_eq!(jobs.len(), 3);
+        assert_eq!(jobs[1].run_at - jobs[0].run_at, STAGGER);
+        assert_eq!(jobs[2].run_at - jobs[1].run_at, STAGGER);
+
+        // Those are left to the nameserver jobs, which know when the provider is up to date
+        let record_syncs = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM Jobs WHERE name = 'RefreshDomainDnsRecords'"#
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(record_syncs, 0);
+    }
+
+    #[sqlx::test]
+    async fn fails_for_a_domain_porkbun_doesnt_know(pool: PgPool) {
+        let mut app_state = AppState::with_pool(pool.clone()).unwrap();
+        app_state.apis = MockPorkbun::shared().api_config();
+        RefreshDomains::default()
+            .run(app_state.clone())
+            .await
+            .unwrap();
+
+        let domain_id = domain_id(&pool, "side-project.test").await;
+        sqlx::query!(
+            "UPDATE Domains SET domain = 'gone.test' WHERE domain_id = $1",
+            domain_id
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let err = RefreshDomainNameservers::new(domain_id)
+            .run(app_state)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                err.downcast_ref::<PorkbunError>(),
+                Some(PorkbunError::DomainNotFound(_))
+            ),
+            "{err}"
+        );
+
+        let run = sqlx::query!(
+            "SELECT * FROM DomainJobRuns WHERE domain_id = $1",
+            domain_id
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(run.status, JobRunStatus::Failed.as_str());
+        assert!(run.error.unwrap().contains("Invalid domain"));
+    }
+}
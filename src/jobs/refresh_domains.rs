@@ -1,52 +1,66 @@
-use chrono::NaiveDateTime;
 use cja::jobs::Job;
+use tracing::{info, warn};
 
-use crate::AppState;
+use crate::{
+    jobs::{reschedule_if_throttled, Retry},
+    notifications::emit_sync_failure,
+    registrars::RegistrarKind,
+    AppState,
+};
 
 use super::refresh_domain_nameservers::RefreshDomainsNameservers;
 
-#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
-pub struct RefreshDomains;
+#[derive(Clone, Debug, Default, serde::Serialize)]
+pub struct RefreshDomains {
+    attempt: u32,
+}
+
+impl<'de> serde::Deserialize<'de> for RefreshDomains {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        struct Payload {
+            #[serde(default)]
+            attempt: u32,
+        }
+
+        // Jobs queued before attempts were counted have a `null` payload
+        let payload = Option::<Payload>::deserialize(deserializer)?;
+
+        Ok(Self {
+            attempt: payload.map_or(0, |p| p.attempt),
+        })
+    }
+}
+
+impl Retry for RefreshDomains {
+    fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    fn retry(&self) -> Self {
+        Self {
+            attempt: self.attempt + 1,
+        }
+    }
+}
 
 #[async_trait::async_trait]
 impl Job<AppState> for RefreshDomains {
     const NAME: &'static str = "RefreshDomains";
 
     async fn run(&self, app_state: AppState) -> cja::Result<()> {
-        let config =
-            crate::apis::porkbun::Config::from_env().expect("Failed to get porkbun config");
-
-        let resp = crate::apis::porkbun::fetch_domains(config).await.unwrap();
-
-        let format = "%Y-%m-%d %H:%M:%S";
-
-        for domain in resp.domains {
-            sqlx::query!("
-          INSERT INTO PorkbunDomains
-            (porkbun_domain_id, auto_renew, purchase_date, domain, expire_date, not_local, security_lock, status, tld, whois_privacy)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
-            ON CONFLICT (domain)
-            DO UPDATE SET
-              auto_renew = excluded.auto_renew,
-              purchase_date = excluded.purchase_date,
-              expire_date = excluded.expire_date,
-              not_local = excluded.not_local,
-              security_lock = excluded.security_lock,
-              status = excluded.status,
-              tld = excluded.tld,
-              whois_privacy = excluded.whois_privacy
-              ",
-              uuid::Uuid::new_v4(),
-            domain.auto_renew == "1",
-            NaiveDateTime::parse_from_str(&domain.create_date, &format)?.and_utc(),
-            domain.domain,
-            NaiveDateTime::parse_from_str(&domain.expire_date, &format)?.and_utc(),
-            domain.not_local == 1,
-            domain.security_lock == "1",
-            domain.status,
-            domain.tld,
-            domain.whois_privacy == "1"
-            ).execute(&app_state.db).await?;
+        if let Err(e) = self.refresh(&app_state).await {
+            if reschedule_if_throttled(&app_state, self, &e).await? {
+                return Ok(());
+            }
+
+            emit_sync_failure(
+                &app_state,
+                "Refreshing domains from the registrars failed".to_string(),
+                &e,
+            )
+            .await?;
+            return Err(e);
         }
 
         RefreshDomainsNameservers
@@ -56,3 +70,164 @@ impl Job<AppState> for RefreshDomains {
         Ok(())
     }
 }
+
+impl RefreshDomains {
+    async fn refresh(&self, app_state: &AppState) -> cja::Result<()> {
+        for kind in RegistrarKind::ALL {
+            let domains = kind.client(&app_state.apis)?.list_domains().await?;
+
+            // An empty list is far more likely to be a bad response than every domain leaving at
+            // once, so don't mark the whole portfolio as removed over it
+            if domains.is_empty() {
+                warn!(registrar = %kind, "Registrar listed no domains, not marking any as removed");
+                continue;
+            }
+
+            let names = domains.iter().map(|d| d.domain.clone()).collect::<Vec<_>>();
+            let removed = sqlx::query_scalar!(
+                "UPDATE Domains SET removed_at = NOW()
+                WHERE registrar = $1 AND removed_at IS NULL AND domain <> ALL($2)
+                RETURNING domain",
+                kind.as_str(),
+                &names
+            )
+            .fetch_all(&app_state.db)
+            .await?;
+            for domain in removed {
+                info!(domain, registrar = %kind, "Domain is no longer with the registrar");
+            }
+
+            for domain in domains {
+                sqlx::query!("
+              INSERT INTO Domains
+                (domain_id, auto_renew, purchase_date, domain, expire_date, not_local, security_lock, status, tld, whois_privacy, registrar)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                ON CONFLICT (domain)
+                DO UPDATE SET
+                  auto_renew = excluded.auto_renew,
+                  purchase_date = excluded.purchase_date,
+                  expire_date = excluded.expire_date,
+                  not_local = excluded.not_local,
+                  security_lock = excluded.security_lock,
+                  status = excluded.status,
+                  tld = excluded.tld,
+                  whois_privacy = excluded.whois_privacy,
+                  registrar = excluded.registrar,
+                  removed_at = NULL
+                  ",
+                  uuid::Uuid::new_v4(),
+                domain.auto_renew,
+                domain.purchase_date,
+                domain.domain,
+                domain.expire_date,
+                domain.not_local,
+                domain.security_lock,
+                domain.status,
+                domain.tld,
+                domain.whois_privacy,
+                kind.as_str()
+                ).execute(&app_state.db).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use crate::apis::porkbun::mock::MockPorkbun;
+
+    use super::*;
+
+    #[test]
+    fn reads_payloads_from_before_attempts_were_counted() {
+        let job: RefreshDomains = serde_json::from_value(serde_json::Value::Null).unwrap();
+        assert_eq!(job.attempt(), 0);
+
+        let job: RefreshDomains =
+            serde_json::from_value(serde_json::json!({ "attempt": 2 })).unwrap();
+        assert_eq!(job.attempt(), 2);
+    }
+
+    #[sqlx::test]
+    async fn upserts_domains_from_porkbun(pool: PgPool) {
+        let mut app_state = AppState::with_pool(pool.clone()).unwrap();
+        app_state.apis = MockPorkbun::shared().api_config();
+
+        RefreshDomains::default()
+            .run(app_state.clone())
+            .await
+            .unwrap();
+        // A second run updates the rows rather than duplicating them
+        RefreshDomains::default().run(app_state).await.unwrap();
+
+        let domains = sqlx::query!("SELECT * FROM Domains ORDER BY domain")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(
+            domains
+                .iter()
+                .map(|d| d.domain.as_str())
+                .collect::<Vec<_>>(),
+            ["coreyja.cloud.test", "coreyja.test", "side-project.test"]
+        );
+        assert!(domains.iter().all(|d| d.registrar == "porkbun"));
+
+        let coreyja = &domains[1];
+        assert!(coreyja.auto_renew);
+        assert!(coreyja.security_lock);
+        assert!(!coreyja.not_local);
+        assert_eq!(
+            coreyja.expire_date.to_rfc3339(),
+            "2030-03-02T18:24:51+00:00"
+        );
+        assert!(domains[2].not_local);
+
+        let enqueued = sqlx::query!(
+            "SELECT COUNT(*) as count FROM Jobs WHERE name = 'RefreshDomainsNameservers'"
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(enqueued.count, Some(2));
+    }
+
+    #[sqlx::test]
+    async fn marks_domains_the_registrar_no_longer_lists(pool: PgPool) {
+        let mut app_state = AppState::with_pool(pool.clone()).unwrap();
+        app_state.apis = MockPorkbun::shared().api_config();
+        RefreshDomains::default()
+            .run(app_state.clone())
+            .await
+            .unwrap();
+
+        sqlx::query!(
+            "INSERT INTO Domains
+              (domain_id, auto_renew, purchase_date, domain, expire_date, not_local, security_lock, status, tld, whois_privacy, registrar)
+            VALUES ($1, false, NOW(), 'transferred-out.test', NOW(), false, false, NULL, 'test', false, 'porkbun')",
+            uuid::Uuid::new_v4()
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query!("UPDATE Domains SET removed_at = NOW() WHERE domain = 'coreyja.test'")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        RefreshDomains::default().run(app_state).await.unwrap();
+
+        let removed = sqlx::query_scalar!(
+            "SELECT domain FROM Domains WHERE removed_at IS NOT NULL ORDER BY domain"
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        // coreyja.test is listed again so it's back
+        assert_eq!(removed, ["transferred-out.test"]);
+    }
+}
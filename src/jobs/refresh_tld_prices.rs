@@ -0,0 +1,87 @@
+use cja::{app_state::AppState as _, jobs::Job};
+
+use crate::{notifications::emit_sync_failure, registrars::RegistrarKind, AppState};
+
+/// Keeps `TldPrices` current so tags can total up what their domains cost to renew
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct RefreshTldPrices;
+
+#[async_trait::async_trait]
+impl Job<AppState> for RefreshTldPrices {
+    const NAME: &'static str = "RefreshTldPrices";
+
+    async fn run(&self, app_state: AppState) -> cja::Result<()> {
+        if let Err(e) = self.refresh(&app_state).await {
+            emit_sync_failure(
+                &app_state,
+                "Refreshing TLD prices from the registrars failed".to_string(),
+                &e,
+            )
+            .await?;
+            return Err(e);
+        }
+
+        Ok(())
+    }
+}
+
+impl RefreshTldPrices {
+    async fn refresh(&self, app_state: &AppState) -> cja::Result<()> {
+        for kind in RegistrarKind::ALL {
+            let (tlds, prices): (Vec<_>, Vec<_>) = kind
+                .client(&app_state.apis)?
+                .renewal_prices()
+                .await?
+                .into_iter()
+                .unzip();
+
+            sqlx::query!(
+                "INSERT INTO TldPrices (registrar, tld, renewal_cents)
+                SELECT $1, * FROM UNNEST($2::TEXT[], $3::BIGINT[])
+                ON CONFLICT (registrar, tld)
+                DO UPDATE SET renewal_cents = excluded.renewal_cents, updated_at = NOW()",
+                kind.as_str(),
+                &tlds,
+                &prices
+            )
+            .execute(app_state.db())
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use crate::apis::porkbun::mock::MockPorkbun;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn stores_renewal_prices_in_cents(pool: PgPool) {
+        let mut app_state = AppState::with_pool(pool.clone()).unwrap();
+        app_state.apis = MockPorkbun::shared().api_config();
+
+        RefreshTldPrices.run(app_state.clone()).await.unwrap();
+        RefreshTldPrices.run(app_state).await.unwrap();
+
+        let prices = sqlx::query!("SELECT tld, renewal_cents FROM TldPrices ORDER BY tld")
+            .fetch_all(&pool)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|row| (row.tld, row.renewal_cents))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            prices,
+            [
+                ("com".to_string(), 1108),
+                ("dev".to_string(), 1287),
+                ("test".to_string(), 950)
+            ]
+        );
+    }
+}
@@ -0,0 +1,58 @@
+use cja::{app_state::AppState as _, jobs::Job};
+
+use crate::AppState;
+
+/// How long raw hits are kept around after they have been rolled up
+const RAW_HIT_RETENTION_DAYS: i32 = 30;
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct RollupRedirectHits;
+
+#[async_trait::async_trait]
+impl Job<AppState> for RollupRedirectHits {
+    const NAME: &'static str = "RollupRedirectHits";
+
+    /// Recomputes the hourly and daily buckets from the raw hits since the start of yesterday
+    ///
+    /// Recomputing instead of incrementing keeps this idempotent, so a retried or overlapping
+    /// run can't double count. The window is wider than the cron interval so late-arriving
+    /// hits for the previous day are still picked up.
+    async fn run(&self, app_state: AppState) -> cja::Result<()> {
+        let mut tx = app_state.db().begin().await?;
+
+        sqlx::query!(
+            "INSERT INTO RedirectHitRollups (redirect_id, granularity, bucket_start, hits)
+            SELECT redirect_id, 'hour', date_trunc('hour', hit_at), COUNT(*)
+            FROM RedirectHits
+            WHERE hit_at >= date_trunc('day', NOW() - INTERVAL '1 day')
+            GROUP BY redirect_id, date_trunc('hour', hit_at)
+            ON CONFLICT (redirect_id, granularity, bucket_start)
+            DO UPDATE SET hits = excluded.hits, updated_at = NOW()"
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "INSERT INTO RedirectHitRollups (redirect_id, granularity, bucket_start, hits)
+            SELECT redirect_id, 'day', date_trunc('day', hit_at), COUNT(*)
+            FROM RedirectHits
+            WHERE hit_at >= date_trunc('day', NOW() - INTERVAL '1 day')
+            GROUP BY redirect_id, date_trunc('day', hit_at)
+            ON CONFLICT (redirect_id, granularity, bucket_start)
+            DO UPDATE SET hits = excluded.hits, updated_at = NOW()"
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "DELETE FROM RedirectHits WHERE hit_at < NOW() - make_interval(days => $1)",
+            RAW_HIT_RETENTION_DAYS
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use cja::{app_state::AppState as _, jobs::Job};
+use tracing::error;
+use uuid::Uuid;
+
+use crate::{
+    expiry::{alert_windows, current_window, expiring_domains},
+    notifications::{emit_counting, Event, Notification},
+    AppState,
+};
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct SendExpiryAlerts;
+
+#[async_trait::async_trait]
+impl Job<AppState> for SendExpiryAlerts {
+    const NAME: &'static str = "SendExpiryAlerts";
+
+    async fn run(&self, app_state: AppState) -> cja::Result<()> {
+        let windows = alert_windows();
+        let Some(largest) = windows.first().copied() else {
+            return Ok(());
+        };
+
+        // One domain's alert failing shouldn't hold back the rest
+        let mut failed = vec![];
+        for domain in expiring_domains(app_state.db(), largest).await? {
+            let Some(window) = current_window(&domain, &windows) else {
+                continue;
+            };
+
+            // Claim the alert before emitting so overlapping runs can't both send it
+            let claimed = sqlx::query_scalar!(
+                "INSERT INTO ExpiryAlerts (expiry_alert_id, domain_id, window_days, expire_date)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (domain_id, window_days, expire_date) DO NOTHING
+                RETURNING expiry_alert_id",
+                Uuid::new_v4(),
+                domain.domain_id,
+                window,
+                domain.expire_date
+            )
+            .fetch_optional(app_state.db())
+            .await?;
+            let Some(expiry_alert_id) = claimed else {
+                continue;
+            };
+
+            let days_left = domain.days_until_expiry();
+            let notification = if domain.auto_renew {
+                Notification {
+                    event: Event::DomainExpiring,
+                    subject: format!("{} renews in {days_left} days", domain.domain),
+                    body: format!(
+                        "{} is set to auto-renew on {}. Make sure the {} account has a working payment method.",
+                        domain.domain,
+                        domain.expire_date.format("%Y-%m-%d"),
+                        domain.registrar_name()
+                    ),
+                }
+            } else {
+                Notification {
+                    event: Event::DomainExpiring,
+                    subject: format!(
+                        "{} expires in {days_left} days and won't auto-renew",
+                        domain.domain
+                    ),
+                    body: format!(
+                        "{} expires on {} and auto-renew is off. Renew it on {} or let it lapse on purpose.",
+                        domain.domain,
+                        domain.expire_date.format("%Y-%m-%d"),
+                        domain.registrar_name()
+                    ),
+                }
+            };
+
+            let mut queued = 0;
+            if let Err(e) = emit_counting(&app_state, notification, &mut queued).await {
+                error!(
+                    domain = domain.domain,
+                    queued, "Failed to send expiry alert: {e:#}"
+                );
+
+                // Release the claim so the next run tries again, unless some channels already
+                // have the alert and would get it twice
+                if queued == 0 {
+                    sqlx::query!(
+                        "DELETE FROM ExpiryAlerts WHERE expiry_alert_id = $1",
+                        expiry_alert_id
+                    )
+                    .execute(app_state.db())
+                    .await?;
+                }
+
+                failed.push(domain.domain);
+            }
+        }
+
+        if !failed.is_empty() {
+            return Err(color_eyre::eyre::eyre!(
+                "Failed to send expiry alerts for {}",
+                failed.join(", ")
+            ));
+        }
+
+        Ok(())
+    }
+}
@@ -1,7 +1,8 @@
 use axum::{
     extract::{Host, Request, State},
-    response::{IntoResponse, Redirect, Response},
-    routing::get,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
 };
 use cja::{
     app_state::AppState as _,
@@ -12,11 +13,22 @@ use maud::html;
 use sqlx::{postgres::PgPoolOptions, PgPool};
 use tracing::info;
 
+mod analytics;
 mod apis;
 mod auth;
+mod calendar;
 mod cron;
+mod dns;
+mod dns_hosts;
+mod expiry;
+mod flash;
 mod jobs;
+mod markdown;
+mod notifications;
+mod redirects;
+mod registrars;
 mod routes;
+mod zone;
 
 fn main() -> color_eyre::Result<()> {
     let _sentry_guard = setup_sentry();
@@ -35,10 +47,14 @@ async fn _main() -> cja::Result<()> {
 
     cja::sqlx::migrate!().run(app_state.db()).await?;
 
+    app_state.redirects.refresh(app_state.db()).await?;
+
     info!("Spawning Tasks");
     let mut futures = vec![
         tokio::spawn(run_server(routes(app_state.clone()))),
         tokio::spawn(cja::jobs::worker::job_worker(app_state.clone(), jobs::Jobs)),
+        tokio::spawn(redirects::watch_redirects(app_state.clone())),
+        tokio::spawn(analytics::write_hits(app_state.clone())),
     ];
     if std::env::var("CRON_DISABLED").unwrap_or_else(|_| "false".to_string()) != "true" {
         info!("Cron Enabled");
@@ -46,7 +62,8 @@ async fn _main() -> cja::Result<()> {
     }
     info!("Tasks Spawned");
 
-    futures::future::try_join_all(futures).await?;
+    // Exit as soon as any task fails rather than carrying on without it
+    futures::future::try_join_all(futures.into_iter().map(|task| async { task.await? })).await?;
 
     Ok(())
 }
@@ -55,6 +72,9 @@ async fn _main() -> cja::Result<()> {
 struct AppState {
     db: sqlx::Pool<sqlx::Postgres>,
     cookie_key: cja::server::cookies::CookieKey,
+    redirects: redirects::RedirectCache,
+    hits: analytics::HitRecorder,
+    apis: apis::ApiConfig,
 }
 
 impl cja::app_state::AppState for AppState {
@@ -75,11 +95,21 @@ impl AppState {
     pub async fn from_env() -> color_eyre::Result<Self> {
         let pool = setup_db_pool().await.unwrap();
 
+        Ok(Self {
+            apis: apis::ApiConfig::from_env(),
+            ..Self::with_pool(pool)?
+        })
+    }
+
+    pub fn with_pool(pool: PgPool) -> color_eyre::Result<Self> {
         let cookie_key = cja::server::cookies::CookieKey::from_env_or_generate()?;
 
         Ok(Self {
             db: pool,
             cookie_key,
+            redirects: redirects::RedirectCache::default(),
+            hits: analytics::HitRecorder::new(),
+            apis: apis::ApiConfig::default(),
         })
     }
 }
@@ -104,6 +134,12 @@ async fn handler(session: Option<DBSession>, State(app_state): State<AppState>)
                 a href="/logout" { "Logout" }
 
                 a href="/domains" { "Domains" }
+
+                a href="/analytics" { "Redirect Analytics" }
+
+                a href="/notifications" { "Notifications" }
+
+                a href="/api-tokens" { "API tokens" }
             }
             .into_response()
         } else {
@@ -115,20 +151,18 @@ async fn handler(session: Option<DBSession>, State(app_state): State<AppState>)
 }
 
 async fn host_redirection(
+    State(app_state): State<AppState>,
     Host(host): Host,
     request: Request,
     next: axum::middleware::Next,
 ) -> Response {
-    let redirect_to = match host.as_str() {
-        "coreyja.tv" | "coreyja.tube" => Some("https://coreyja.com/videos"),
-        "coreyja.blog" => Some("https://coreyja.com/posts"),
-        "coreyja.club" => Some("https://discord.gg/CpAPpXrgUq"),
-        "beta.coreyja.com" => Some("https://coreyja.com"),
-        _ => None,
-    };
+    if let Some(redirect) = app_state.redirects.resolve(&host, request.uri()).await {
+        analytics::record_hit(&app_state, redirect.redirect_id, &host, &request);
 
-    if let Some(redirect_to) = redirect_to {
-        return Redirect::to(redirect_to).into_response();
+        let status =
+            StatusCode::from_u16(redirect.status_code as u16).unwrap_or(StatusCode::SEE_OTHER);
+
+        return (status, [(header::LOCATION, redirect.location)]).into_response();
     };
 
     next.run(request).await
@@ -140,9 +174,96 @@ fn routes(app_state: AppState) -> axum::Router {
         .route("/login", get(routes::login::show))
         .route("/login/callback", get(routes::login::callback))
         .route("/logout", get(routes::login::logout))
+        .route("/analytics", get(routes::analytics::show))
+        .route(
+            "/notifications",
+            get(routes::notifications::index).post(routes::notifications::create),
+        )
+        .route(
+            "/notifications/:notification_channel_id",
+            get(routes::notifications::edit).post(routes::notifications::update),
+        )
+        .route(
+            "/notifications/:notification_channel_id/delete",
+            post(routes::notifications::destroy),
+        )
+        .route(
+            "/notifications/:notification_channel_id/test",
+            post(routes::notifications::test),
+        )
+        .route(
+            "/api-tokens",
+            get(routes::api_tokens::index).post(routes::api_tokens::create),
+        )
+        .route(
+            "/api-tokens/:api_token_id/delete",
+            post(routes::api_tokens::destroy),
+        )
+        .nest("/api/v1", routes::api::router())
+        .route("/calendar", get(routes::calendar::show))
+        .route("/calendar/token", post(routes::calendar::regenerate_token))
+        .route("/calendar/:calendar_file", get(routes::calendar::feed))
         .route("/domains", get(routes::domains::show))
-        .with_state(app_state)
-        .layer(axum::middleware::from_fn(host_redirection))
+        .route("/domains/:domain", get(routes::domains::detail))
+        .route("/domains/:domain/refresh", post(routes::domains::refresh))
+        .route(
+            "/domains/:domain/notes",
+            post(routes::domains::update_notes),
+        )
+        .route("/domains/:domain/tags", post(routes::tags::tag_domain))
+        .route(
+            "/domains/:domain/tags/:tag_id/delete",
+            post(routes::tags::untag_domain),
+        )
+        .route("/tags", get(routes::tags::index).post(routes::tags::create))
+        .route("/tags/:tag_id/delete", post(routes::tags::destroy))
+        .route(
+            "/domains/:domain/dns",
+            get(routes::dns::index).post(routes::dns::create),
+        )
+        .route("/domains/:domain/dns/history", get(routes::dns::history))
+        .route("/domains/:domain/dns/export", get(routes::zone::export))
+        .route(
+            "/domains/:domain/dns/import",
+            get(routes::zone::import_form).post(routes::zone::preview),
+        )
+        .route(
+            "/domains/:domain/dns/import/apply",
+            post(routes::zone::apply),
+        )
+        .route(
+            "/domains/:domain/dns/:dns_record_id",
+            get(routes::dns::edit).post(routes::dns::update),
+        )
+        .route(
+            "/domains/:domain/dns/:dns_record_id/delete",
+            post(routes::dns::destroy),
+        )
+        .route(
+            "/domains/:domain/nameservers",
+            get(routes::nameservers::edit).post(routes::nameservers::confirm),
+        )
+        .route(
+            "/domains/:domain/nameservers/apply",
+            post(routes::nameservers::apply),
+        )
+        .route(
+            "/domains/:domain/redirects",
+            get(routes::redirects::index).post(routes::redirects::create),
+        )
+        .route(
+            "/domains/:domain/redirects/:redirect_id",
+            get(routes::redirects::edit).post(routes::redirects::update),
+        )
+        .route(
+            "/domains/:domain/redirects/:redirect_id/delete",
+            post(routes::redirects::destroy),
+        )
+        .with_state(app_state.clone())
+        .layer(axum::middleware::from_fn_with_state(
+            app_state,
+            host_redirection,
+        ))
 }
 
 #[tracing::instrument(err)]
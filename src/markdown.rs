@@ -0,0 +1,80 @@
+use maud::{Markup, PreEscaped};
+use pulldown_cmark::{html, Event, Options, Parser, Tag};
+
+/// Link targets that can't run script when clicked
+fn safe_url(url: &str) -> bool {
+    match url.split_once(':') {
+        Some((scheme, _)) if !scheme.contains(['/', '?', '#']) => {
+            matches!(
+                scheme.to_ascii_lowercase().as_str(),
+                "http" | "https" | "mailto"
+            )
+        }
+        _ => true,
+    }
+}
+
+/// Renders notes written in markdown. Raw HTML in the source is shown as text rather than passed
+/// through, and links or images with other schemes (`javascript:` and friends) lose their target
+pub(crate) fn render(source: &str) -> Markup {
+    let events = Parser::new_ext(
+        source,
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH,
+    )
+    .map(|event| match event {
+        Event::Html(html) => Event::Text(html),
+        Event::Start(Tag::Link(kind, url, title)) if !safe_url(&url) => {
+            Event::Start(Tag::Link(kind, "".into(), title))
+        }
+        Event::Start(Tag::Image(kind, url, title)) if !safe_url(&url) => {
+            Event::Start(Tag::Image(kind, "".into(), title))
+        }
+        event => event,
+    });
+
+    let mut rendered = String::new();
+    html::push_html(&mut rendered, events);
+
+    PreEscaped(rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_markdown() {
+        assert_eq!(
+            render("Bought for **the blog**, see [the repo](https://github.com/coreyja)").0,
+            "<p>Bought for <strong>the blog</strong>, see <a href=\"https://github.com/coreyja\">the repo</a></p>\n"
+        );
+    }
+
+    #[test]
+    fn escapes_raw_html() {
+        assert_eq!(
+            render("<script>alert(1)</script>").0,
+            "&lt;script&gt;alert(1)&lt;/script&gt;"
+        );
+        assert_eq!(
+            render("a <img src=x onerror=alert(1)> b").0,
+            "<p>a &lt;img src=x onerror=alert(1)&gt; b</p>\n"
+        );
+    }
+
+    #[test]
+    fn drops_script_links() {
+        assert_eq!(
+            render("[click](javascript:alert(1))").0,
+            "<p><a href=\"\">click</a></p>\n"
+        );
+        assert_eq!(
+            render("[click](JavaScript:alert(1))").0,
+            "<p><a href=\"\">click</a></p>\n"
+        );
+        assert_eq!(
+            render("[relative](/domains?tag=a:b)").0,
+            "<p><a href=\"/domains?tag=a:b\">relative</a></p>\n"
+        );
+    }
+}
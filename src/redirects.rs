@@ -0,0 +1,797 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use cja::app_state::AppState as _;
+use regex::Regex;
+use sqlx::{postgres::PgListener, PgPool};
+use tokio::sync::RwLock;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::AppState;
+
+/// Postgres channel the `Redirects` trigger notifies whenever a row changes
+const REDIRECTS_CHANGED_CHANNEL: &str = "redirects_changed";
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub(crate) struct RedirectRule {
+    pub(crate) redirect_id: Uuid,
+    pub(crate) host: String,
+    pub(crate) target_url: String,
+    pub(crate) status_code: i32,
+    pub(crate) enabled: bool,
+    pub(crate) created_at: chrono::DateTime<chrono::Utc>,
+    pub(crate) updated_at: chrono::DateTime<chrono::Utc>,
+    pub(crate) mode: String,
+    pub(crate) path_pattern: String,
+    pub(crate) pattern_type: String,
+    pub(crate) priority: i32,
+    pub(crate) match_apex: bool,
+    pub(crate) match_www: bool,
+}
+
+/// Prefix that turns a rule's host into a wildcard covering every subdomain
+pub(crate) const WILDCARD_PREFIX: &str = "*.";
+
+/// Replaced in a wildcard rule's target with the subdomain that matched
+const SUBDOMAIN_PLACEHOLDER: &str = "${subdomain}";
+
+/// Whether a subdomain taken from the `Host` header is plain enough to put into a URL
+fn valid_subdomain(subdomain: &str) -> bool {
+    !subdomain.is_empty()
+        && subdomain
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-' || b == b'.')
+}
+
+/// How much of the incoming request a redirect carries over to its target
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum RedirectMode {
+    /// Always send visitors to `target_url` as-is
+    Fixed,
+    /// Append the request path to `target_url`'s path
+    PreservePath,
+    /// Append the request path and pass the query string through as well
+    PreservePathAndQuery,
+}
+
+impl RedirectMode {
+    pub(crate) const ALL: [RedirectMode; 3] = [
+        RedirectMode::Fixed,
+        RedirectMode::PreservePath,
+        RedirectMode::PreservePathAndQuery,
+    ];
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            RedirectMode::Fixed => "fixed",
+            RedirectMode::PreservePath => "preserve_path",
+            RedirectMode::PreservePathAndQuery => "preserve_path_and_query",
+        }
+    }
+
+    pub(crate) fn parse(mode: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|m| m.as_str() == mode)
+            .ok_or_else(|| format!("{mode} is not a supported redirect mode"))
+    }
+}
+
+/// Whether `target_url` refers to any of the path pattern's captures, like `$1` or `${slug}`
+fn uses_captures(target_url: &str) -> bool {
+    target_url
+        .replace("$$", "")
+        .replace(SUBDOMAIN_PLACEHOLDER, "")
+        .contains('$')
+}
+
+/// Parses `mode` and checks it can be used with `target_url`. A target built from captures
+/// already says where the path goes, so keeping the path as well would repeat it
+pub(crate) fn validate_mode(mode: &str, target_url: &str) -> Result<RedirectMode, String> {
+    let mode = RedirectMode::parse(mode)?;
+
+    if mode != RedirectMode::Fixed && uses_captures(target_url) {
+        return Err(format!(
+            "{target_url} uses captures from the path pattern, so it needs a fixed target"
+        ));
+    }
+
+    Ok(mode)
+}
+
+impl std::fmt::Display for RedirectMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RedirectMode::Fixed => write!(f, "Fixed target"),
+            RedirectMode::PreservePath => write!(f, "Keep path"),
+            RedirectMode::PreservePathAndQuery => write!(f, "Keep path and query string"),
+        }
+    }
+}
+
+/// How a rule's `path_pattern` is interpreted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum PatternType {
+    /// `*` matches anything, including `/`. Each `*` is a numbered capture group
+    Glob,
+    /// A regular expression matched against the whole path
+    Regex,
+}
+
+impl PatternType {
+    pub(crate) const ALL: [PatternType; 2] = [PatternType::Glob, PatternType::Regex];
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            PatternType::Glob => "glob",
+            PatternType::Regex => "regex",
+        }
+    }
+
+    pub(crate) fn parse(pattern_type: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|t| t.as_str() == pattern_type)
+            .ok_or_else(|| format!("{pattern_type} is not a supported pattern type"))
+    }
+}
+
+impl std::fmt::Display for PatternType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatternType::Glob => write!(f, "Wildcard"),
+            PatternType::Regex => write!(f, "Regex"),
+        }
+    }
+}
+
+/// Compiles a path pattern into an anchored regex so both pattern types match the same way
+pub(crate) fn compile_path_pattern(
+    pattern_type: PatternType,
+    path_pattern: &str,
+) -> Result<Regex, String> {
+    let regex = match pattern_type {
+        PatternType::Glob => {
+            let parts: Vec<_> = path_pattern.split('*').map(regex::escape).collect();
+            format!("^{}$", parts.join("(.*)"))
+        }
+        PatternType::Regex => format!("^(?:{path_pattern})$"),
+    };
+
+    Regex::new(&regex).map_err(|e| format!("{path_pattern} is not a valid pattern: {e}"))
+}
+
+impl RedirectRule {
+    pub(crate) fn mode(&self) -> RedirectMode {
+        RedirectMode::parse(&self.mode).unwrap_or(RedirectMode::Fixed)
+    }
+
+    pub(crate) fn pattern_type(&self) -> PatternType {
+        PatternType::parse(&self.pattern_type).unwrap_or(PatternType::Glob)
+    }
+
+    pub(crate) fn is_wildcard(&self) -> bool {
+        self.host.starts_with(WILDCARD_PREFIX)
+    }
+
+    /// Builds the `Location` to send for a request to `uri` on this rule's host
+    ///
+    /// Captures from the path pattern are substituted first (`$1`, `${name}`), so a literal `$`
+    /// in the target has to be written as `$$`. Then for wildcard hosts `${subdomain}` is
+    /// replaced with the matched subdomain, which comes from the `Host` header and so must
+    /// already have passed [`valid_subdomain`]. Doing it in this order keeps anything in the
+    /// header from being expanded as a capture. Finally the mode decides how much of the request
+    /// to carry over.
+    fn location(
+        &self,
+        uri: &axum::http::Uri,
+        captures: &regex::Captures,
+        subdomain: &str,
+    ) -> String {
+        debug_assert!(subdomain.is_empty() || valid_subdomain(subdomain));
+
+        // `$$` survives `expand` as a literal `$`, leaving `${subdomain}` for afterwards
+        let target_url = self
+            .target_url
+            .replace(SUBDOMAIN_PLACEHOLDER, &format!("${SUBDOMAIN_PLACEHOLDER}"));
+
+        let mut target = String::new();
+        captures.expand(&target_url, &mut target);
+        let target = target.replace(SUBDOMAIN_PLACEHOLDER, subdomain);
+
+        // Rules saved before `validate_mode` rejected captures with the other modes would repeat
+        // the path, so they're treated as fixed
+        let mode = self.mode();
+        if mode == RedirectMode::Fixed || uses_captures(&self.target_url) {
+            return target;
+        }
+
+        let Ok(mut url) = reqwest::Url::parse(&target) else {
+            return target;
+        };
+
+        if uri.path() != "/" {
+            let path = format!("{}{}", url.path().trim_end_matches('/'), uri.path());
+            url.set_path(&path);
+        }
+
+        if mode == RedirectMode::PreservePathAndQuery {
+            if let Some(request_query) = uri.query().filter(|q| !q.is_empty()) {
+                let query = match url.query() {
+                    Some(target_query) if !target_query.is_empty() => {
+                        format!("{target_query}&{request_query}")
+                    }
+                    _ => request_query.to_string(),
+                };
+                url.set_query(Some(&query));
+            }
+        }
+
+        url.to_string()
+    }
+}
+
+/// Status codes an admin can pick for a redirect, with a short label for the UI
+pub(crate) const STATUS_CODES: [(i32, &str); 5] = [
+    (301, "301 Moved Permanently"),
+    (302, "302 Found"),
+    (303, "303 See Other"),
+    (307, "307 Temporary Redirect"),
+    (308, "308 Permanent Redirect"),
+];
+
+/// Checks that `target_url` is an absolute http(s) URL that won't send the request straight back
+/// to this rule
+///
+/// It loops when the target is on `host` and its path matches `path_matcher`, the compiled path
+/// pattern. For a wildcard host, being on `host` means any subdomain of its parent, and the parent
+/// itself when the rule also matches the apex. A rule matching the apex can't use `${subdomain}`, since there is
+/// no subdomain to fill in there. Returns the URL to store, or a message suitable for showing to
+/// the admin.
+pub(crate) fn validate_target_url(
+    host: &str,
+    match_apex: bool,
+    path_matcher: &Regex,
+    target_url: &str,
+) -> Result<String, String> {
+    let url = reqwest::Url::parse(target_url.trim())
+        .map_err(|_| format!("{target_url} is not an absolute URL"))?;
+
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(format!("{target_url} must use http or https"));
+    }
+
+    let Some(target_host) = url.host_str() else {
+        return Err(format!("{target_url} is missing a host"));
+    };
+
+    let target_host = target_host.to_ascii_lowercase();
+    let same_host = match host.strip_prefix(WILDCARD_PREFIX) {
+        Some(parent) => {
+            target_host.ends_with(&format!(".{parent}")) || (match_apex && target_host == parent)
+        }
+        None => target_host == host,
+    };
+    if same_host && path_matcher.is_match(url.path()) {
+        return Err(format!("{host} can't redirect to itself"));
+    }
+
+    if match_apex && target_url.contains(SUBDOMAIN_PLACEHOLDER) {
+        return Err(format!(
+            "{target_url} uses {SUBDOMAIN_PLACEHOLDER}, which is empty when the rule matches the apex"
+        ));
+    }
+
+    // Store what the admin typed rather than `url.to_string()`, which would percent-encode
+    // capture references like `${slug}`
+    Ok(target_url.trim().to_string())
+}
+
+/// Checks that `host` is `domain`, one of its subdomains, or a `*.` wildcard under it
+pub(crate) fn validate_host(domain: &str, host: &str) -> Result<String, String> {
+    let host = host.trim().trim_end_matches('.').to_ascii_lowercase();
+    let name = host.strip_prefix(WILDCARD_PREFIX).unwrap_or(&host);
+
+    if name.contains('*') {
+        return Err(format!("{host} can only have one wildcard, at the start"));
+    }
+
+    if name == domain || name.ends_with(&format!(".{domain}")) {
+        Ok(host)
+    } else {
+        Err(format!("{host} isn't part of {domain}"))
+    }
+}
+
+/// Whether a rule for `host` belongs on `domain`'s redirect page
+pub(crate) fn host_belongs_to_domain(host: &str, domain: &str) -> bool {
+    let name = host.strip_prefix(WILDCARD_PREFIX).unwrap_or(host);
+
+    name == domain || name.ends_with(&format!(".{domain}"))
+}
+
+pub(crate) fn validate_status_code(status_code: i32) -> Result<i32, String> {
+    if STATUS_CODES.iter().any(|(code, _)| *code == status_code) {
+        Ok(status_code)
+    } else {
+        Err(format!(
+            "{status_code} is not a supported redirect status code"
+        ))
+    }
+}
+
+/// Where a request should be sent, as decided by the first matching [`RedirectRule`]
+#[derive(Debug, Clone)]
+pub(crate) struct RedirectMatch {
+    pub(crate) redirect_id: Uuid,
+    pub(crate) status_code: i32,
+    pub(crate) location: String,
+}
+
+#[derive(Debug)]
+struct CompiledRule {
+    rule: RedirectRule,
+    matcher: Regex,
+}
+
+fn first_match(
+    rules: &[CompiledRule],
+    uri: &axum::http::Uri,
+    subdomain: &str,
+    accepts: impl Fn(&RedirectRule) -> bool,
+) -> Option<RedirectMatch> {
+    rules
+        .iter()
+        .filter(|compiled| accepts(&compiled.rule))
+        .find_map(|compiled| {
+            let captures = compiled.matcher.captures(uri.path())?;
+
+            Some(RedirectMatch {
+                redirect_id: compiled.rule.redirect_id,
+                status_code: compiled.rule.status_code,
+                location: compiled.rule.location(uri, &captures, subdomain),
+            })
+        })
+}
+
+#[derive(Debug, Default)]
+struct RedirectTable {
+    /// Rules for an exact host, like `coreyja.tv`
+    exact: HashMap<String, Vec<CompiledRule>>,
+    /// Rules for `*.` hosts, keyed by the part after the wildcard
+    wildcard: HashMap<String, Vec<CompiledRule>>,
+}
+
+/// In-memory copy of the enabled redirect rules, grouped by host in priority order
+///
+/// `host_redirection` runs on every request so we don't want to hit the DB each time.
+/// The cache is reloaded by [`watch_redirects`] whenever the `Redirects` table changes.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RedirectCache {
+    table: Arc<RwLock<RedirectTable>>,
+}
+
+impl RedirectCache {
+    /// Finds where to send a request, preferring exact host rules over wildcards and more
+    /// specific wildcards over broader ones
+    pub(crate) async fn resolve(&self, host: &str, uri: &axum::http::Uri) -> Option<RedirectMatch> {
+        let table = self.table.read().await;
+
+        if let Some(found) = table
+            .exact
+            .get(host)
+            .and_then(|rules| first_match(rules, uri, "", |_| true))
+        {
+            return Some(found);
+        }
+
+        // A wildcard that opted into its apex is more specific than any broader wildcard. Rules
+        // saved before `${subdomain}` was rejected for the apex are skipped rather than sending
+        // the request to a blank subdomain
+        if let Some(found) = table.wildcard.get(host).and_then(|rules| {
+            first_match(rules, uri, "", |rule| {
+                rule.match_apex && !rule.target_url.contains(SUBDOMAIN_PLACEHOLDER)
+            })
+        }) {
+            return Some(found);
+        }
+
+        // `a.b.coreyja.tv` tries `*.b.coreyja.tv` before `*.coreyja.tv`
+        for (i, _) in host.match_indices('.') {
+            let (subdomain, parent) = (&host[..i], &host[i + 1..]);
+            if !valid_subdomain(subdomain) {
+                continue;
+            }
+
+            if let Some(found) = table.wildcard.get(parent).and_then(|rules| {
+                first_match(rules, uri, subdomain, |rule| {
+                    subdomain != "www" || rule.match_www
+                })
+            }) {
+                return Some(found);
+            }
+        }
+
+        None
+    }
+
+    pub(crate) async fn refresh(&self, db: &PgPool) -> cja::Result<()> {
+        let rules = sqlx::query_as!(
+            RedirectRule,
+            "SELECT * FROM Redirects WHERE enabled = TRUE ORDER BY host, priority DESC, created_at"
+        )
+        .fetch_all(db)
+        .await?;
+
+        self.load(rules).await;
+
+        Ok(())
+    }
+
+    /// Replaces the cached rules, which should already be in priority order
+    async fn load(&self, rules: Vec<RedirectRule>) {
+        let mut count = 0;
+        let mut table = RedirectTable::default();
+        for rule in rules {
+            let matcher = match compile_path_pattern(rule.pattern_type(), &rule.path_pattern) {
+                Ok(matcher) => matcher,
+                Err(e) => {
+                    warn!(redirect_id = %rule.redirect_id, "Skipping redirect rule: {e}");
+                    continue;
+                }
+            };
+
+            let (map, key) = match rule.host.strip_prefix(WILDCARD_PREFIX) {
+                Some(parent) => (&mut table.wildcard, parent.to_string()),
+                None => (&mut table.exact, rule.host.clone()),
+            };
+
+            count += 1;
+            map.entry(key)
+                .or_default()
+                .push(CompiledRule { rule, matcher });
+        }
+
+        info!(count, "Loaded redirect rules");
+
+        *self.table.write().await = table;
+    }
+}
+
+const WATCH_RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+const WATCH_RETRY_MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Keeps the [`RedirectCache`] in sync with the `Redirects` table via `LISTEN`/`NOTIFY`
+///
+/// Never gives up: if listening or reloading fails it logs, backs off and starts over, since a
+/// stale cache is better than taking the redirects down with it.
+pub(crate) async fn watch_redirects(app_state: AppState) -> cja::Result<()> {
+    let mut delay = WATCH_RETRY_BASE_DELAY;
+
+    loop {
+        if let Err(e) = listen_for_changes(&app_state, &mut delay).await {
+            error!(error = ?e, retry_in = ?delay, "Watching for redirect changes failed");
+
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(WATCH_RETRY_MAX_DELAY);
+        }
+    }
+}
+
+/// Reloads the cache on every notification until something fails. `delay` is reset once the
+/// listener is up and the cache has been reloaded
+async fn listen_for_changes(app_state: &AppState, delay: &mut Duration) -> cja::Result<()> {
+    let mut listener = PgListener::connect_with(app_state.db()).await?;
+    listener.listen(REDIRECTS_CHANGED_CHANNEL).await?;
+
+    app_state.redirects.refresh(app_state.db()).await?;
+    *delay = WATCH_RETRY_BASE_DELAY;
+
+    loop {
+        // `try_recv` returns `None` when the connection dropped. Notifications sent while we
+        // were disconnected are lost, so reload either way once it reconnects.
+        if listener.try_recv().await?.is_none() {
+            warn!("Lost connection while listening for redirect changes, reconnecting");
+        }
+
+        app_state.redirects.refresh(app_state.db()).await?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+
+    fn rule(host: &str, target_url: &str) -> RedirectRule {
+        RedirectRule {
+            redirect_id: Uuid::new_v4(),
+            host: host.to_string(),
+            target_url: target_url.to_string(),
+            status_code: 302,
+            enabled: true,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+            mode: RedirectMode::Fixed.as_str().to_string(),
+            path_pattern: "*".to_string(),
+            pattern_type: PatternType::Glob.as_str().to_string(),
+            priority: 0,
+            match_apex: false,
+            match_www: true,
+        }
+    }
+
+    async fn cache(rules: Vec<RedirectRule>) -> RedirectCache {
+        let cache = RedirectCache::default();
+        cache.load(rules).await;
+        cache
+    }
+
+    async fn location(cache: &RedirectCache, host: &str, uri: &str) -> Option<String> {
+        cache
+            .resolve(host, &uri.parse().unwrap())
+            .await
+            .map(|found| found.location)
+    }
+
+    #[tokio::test]
+    async fn resolves_wildcard_hosts() {
+        let cache = cache(vec![
+            rule("*.coreyja.tv", "https://${subdomain}.coreyja.com/"),
+            rule(
+                "*.videos.coreyja.tv",
+                "https://coreyja.com/videos/${subdomain}",
+            ),
+            rule("live.coreyja.tv", "https://twitch.tv/coreyja"),
+            RedirectRule {
+                match_apex: true,
+                match_www: false,
+                ..rule("*.coreyja.club", "https://discord.gg/abc")
+            },
+            RedirectRule {
+                match_apex: true,
+                ..rule("*.coreyja.dev", "https://${subdomain}.coreyja.com/")
+            },
+        ])
+        .await;
+
+        assert_eq!(
+            location(&cache, "blog.coreyja.tv", "/").await.as_deref(),
+            Some("https://blog.coreyja.com/")
+        );
+        // Exact hosts win over wildcards, deeper wildcards over broader ones
+        assert_eq!(
+            location(&cache, "live.coreyja.tv", "/").await.as_deref(),
+            Some("https://twitch.tv/coreyja")
+        );
+        assert_eq!(
+            location(&cache, "rust.videos.coreyja.tv", "/")
+                .await
+                .as_deref(),
+            Some("https://coreyja.com/videos/rust")
+        );
+        assert_eq!(
+            location(&cache, "a.b.coreyja.tv", "/").await.as_deref(),
+            Some("https://a.b.coreyja.com/")
+        );
+        // The apex only matches when the rule opts in, and www only when it doesn't opt out
+        assert_eq!(location(&cache, "coreyja.tv", "/").await, None);
+        assert_eq!(
+            location(&cache, "coreyja.club", "/").await.as_deref(),
+            Some("https://discord.gg/abc")
+        );
+        assert_eq!(
+            location(&cache, "www.coreyja.tv", "/").await.as_deref(),
+            Some("https://www.coreyja.com/")
+        );
+        assert_eq!(location(&cache, "www.coreyja.club", "/").await, None);
+        // There's no subdomain to fill in on the apex, so it's left alone
+        assert_eq!(location(&cache, "coreyja.dev", "/").await, None);
+        assert_eq!(
+            location(&cache, "api.coreyja.dev", "/").await.as_deref(),
+            Some("https://api.coreyja.com/")
+        );
+        assert!(validate_target_url(
+            "*.coreyja.dev",
+            true,
+            &compile_path_pattern(PatternType::Glob, "*").unwrap(),
+            "https://${subdomain}.coreyja.com/"
+        )
+        .is_err());
+        assert_eq!(location(&cache, "coreyja.com", "/").await, None);
+    }
+
+    #[tokio::test]
+    async fn never_expands_the_host_header() {
+        let cache = cache(vec![RedirectRule {
+            path_pattern: "/*".to_string(),
+            ..rule("*.coreyja.tv", "https://${subdomain}.coreyja.com/$1")
+        }])
+        .await;
+
+        // Anything that isn't a plain label is ignored rather than put into the URL
+        assert_eq!(location(&cache, "evil.com/@x.coreyja.tv", "/").await, None);
+        assert_eq!(location(&cache, "$1.coreyja.tv", "/a").await, None);
+        assert_eq!(location(&cache, "${x}.coreyja.tv", "/a").await, None);
+        assert_eq!(
+            location(&cache, "ok.coreyja.tv", "/a").await.as_deref(),
+            Some("https://ok.coreyja.com/a")
+        );
+    }
+
+    #[test]
+    fn compiles_path_patterns() {
+        let glob = compile_path_pattern(PatternType::Glob, "/blog/*.html").unwrap();
+        assert!(glob.is_match("/blog/some-post.html"));
+        assert!(glob.is_match("/blog/2024/some-post.html"));
+        // Anchored at both ends, and `.` is literal
+        assert!(!glob.is_match("/old/blog/some-post.html"));
+        assert!(!glob.is_match("/blog/some-post.html.bak"));
+        assert!(!glob.is_match("/blog/some-postxhtml"));
+
+        let regex = compile_path_pattern(PatternType::Regex, "/v/(?P<id>[0-9]+)").unwrap();
+        assert!(regex.is_match("/v/123"));
+        assert!(!regex.is_match("/v/123/comments"));
+        assert!(!regex.is_match("/v/abc"));
+        // Alternations stay inside the anchors
+        let alternation = compile_path_pattern(PatternType::Regex, "/a|/b").unwrap();
+        assert!(!alternation.is_match("/a/x"));
+
+        assert!(compile_path_pattern(PatternType::Regex, "/v/(").is_err());
+    }
+
+    #[tokio::test]
+    async fn expands_captures_into_the_target() {
+        let cache = cache(vec![
+            RedirectRule {
+                path_pattern: "/blog/*/*".to_string(),
+                ..rule("coreyja.tv", "https://coreyja.com/posts/$2?year=$1")
+            },
+            RedirectRule {
+                path_pattern: "/v/(?P<id>[0-9]+)".to_string(),
+                pattern_type: PatternType::Regex.as_str().to_string(),
+                ..rule("coreyja.tv", "https://coreyja.com/videos/${id}")
+            },
+            RedirectRule {
+                path_pattern: "/price".to_string(),
+                ..rule("coreyja.tv", "https://coreyja.com/shop?price=$$5")
+            },
+            RedirectRule {
+                path_pattern: "/docs/*".to_string(),
+                mode: RedirectMode::PreservePath.as_str().to_string(),
+                ..rule("coreyja.tv", "https://coreyja.com/guides/$1")
+            },
+        ])
+        .await;
+
+        assert_eq!(
+            location(&cache, "coreyja.tv", "/blog/2024/hello")
+                .await
+                .as_deref(),
+            Some("https://coreyja.com/posts/hello?year=2024")
+        );
+        assert_eq!(
+            location(&cache, "coreyja.tv", "/v/42").await.as_deref(),
+            Some("https://coreyja.com/videos/42")
+        );
+        assert_eq!(
+            location(&cache, "coreyja.tv", "/price").await.as_deref(),
+            Some("https://coreyja.com/shop?price=$5")
+        );
+        assert_eq!(location(&cache, "coreyja.tv", "/v/abc").await, None);
+        // The captures already place the path, so it isn't appended again
+        assert_eq!(
+            location(&cache, "coreyja.tv", "/docs/setup")
+                .await
+                .as_deref(),
+            Some("https://coreyja.com/guides/setup")
+        );
+    }
+
+    #[test]
+    fn rejects_captures_when_keeping_the_path() {
+        assert!(validate_mode("preserve_path", "https://coreyja.com/posts/$1").is_err());
+        assert!(validate_mode("preserve_path_and_query", "https://coreyja.com/${slug}").is_err());
+        assert_eq!(
+            validate_mode("fixed", "https://coreyja.com/posts/$1"),
+            Ok(RedirectMode::Fixed)
+        );
+        assert_eq!(
+            validate_mode(
+                "preserve_path",
+                "https://${subdomain}.coreyja.com/?price=$$5"
+            ),
+            Ok(RedirectMode::PreservePath)
+        );
+    }
+
+    #[sqlx::test]
+    async fn checks_higher_priority_rules_first(pool: PgPool) {
+        for (path_pattern, priority, target_url) in [
+            ("*", 0, "https://coreyja.com/"),
+            ("/blog/*", 10, "https://coreyja.com/posts/$1"),
+            ("/blog/drafts/*", 20, "https://coreyja.com/drafts/$1"),
+        ] {
+            sqlx::query!(
+                "INSERT INTO Redirects (redirect_id, host, path_pattern, priority, target_url)
+                VALUES ($1, 'priority.test', $2, $3, $4)",
+                Uuid::new_v4(),
+                path_pattern,
+                priority,
+                target_url
+            )
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        let cache = RedirectCache::default();
+        cache.refresh(&pool).await.unwrap();
+
+        assert_eq!(
+            location(&cache, "priority.test", "/blog/drafts/wip")
+                .await
+                .as_deref(),
+            Some("https://coreyja.com/drafts/wip")
+        );
+        assert_eq!(
+            location(&cache, "priority.test", "/blog/hello")
+                .await
+                .as_deref(),
+            Some("https://coreyja.com/posts/hello")
+        );
+        assert_eq!(
+            location(&cache, "priority.test", "/about").await.as_deref(),
+            Some("https://coreyja.com/")
+        );
+    }
+
+    #[test]
+    fn rejects_targets_that_loop() {
+        let all = compile_path_pattern(PatternType::Glob, "*").unwrap();
+        assert!(validate_target_url("coreyja.tv", false, &all, "https://coreyja.tv/x").is_err());
+        assert!(
+            validate_target_url("*.coreyja.tv", false, &all, "https://foo.coreyja.tv").is_err()
+        );
+        assert!(validate_target_url(
+            "*.coreyja.tv",
+            false,
+            &all,
+            "https://${subdomain}.coreyja.tv"
+        )
+        .is_err());
+        assert!(validate_target_url("*.coreyja.tv", true, &all, "https://coreyja.tv").is_err());
+        assert!(validate_target_url("*.coreyja.tv", false, &all, "https://coreyja.tv").is_ok());
+        assert!(validate_target_url(
+            "*.coreyja.tv",
+            false,
+            &all,
+            "https://${subdomain}.coreyja.com"
+        )
+        .is_ok());
+        assert!(validate_target_url("coreyja.tv", false, &all, "javascript:alert(1)").is_err());
+
+        // Moving a path around on the same host is fine, as long as the target isn't matched again
+        let old = compile_path_pattern(PatternType::Glob, "/old").unwrap();
+        assert!(validate_target_url("coreyja.tv", false, &old, "https://coreyja.tv/new").is_ok());
+        assert!(validate_target_url("coreyja.tv", false, &old, "https://coreyja.tv/old").is_err());
+        let blog = compile_path_pattern(PatternType::Glob, "/blog/*").unwrap();
+        assert!(
+            validate_target_url("coreyja.tv", false, &blog, "https://coreyja.tv/blog/new").is_err()
+        );
+    }
+
+    #[test]
+    fn validates_hosts() {
+        assert_eq!(
+            validate_host("coreyja.tv", " *.Coreyja.TV. "),
+            Ok("*.coreyja.tv".to_string())
+        );
+        assert!(validate_host("coreyja.tv", "blog.coreyja.tv").is_ok());
+        assert!(validate_host("coreyja.tv", "*.*.coreyja.tv").is_err());
+        assert!(validate_host("coreyja.tv", "a.*.coreyja.tv").is_err());
+        assert!(validate_host("coreyja.tv", "coreyja.com").is_err());
+        assert!(validate_host("coreyja.tv", "evilcoreyja.tv").is_err());
+    }
+}
@@ -0,0 +1,74 @@
+//! The registrars we hold domains with. Jobs and routes only talk to the [`Registrar`] trait, so
+//! adding a registrar means implementing it and adding a [`RegistrarKind`]
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::apis::ApiConfig;
+
+pub(crate) mod porkbun;
+
+/// A domain as the registrar reports it
+#[derive(Debug, Clone)]
+pub(crate) struct RegistrarDomain {
+    pub(crate) domain: String,
+    pub(crate) tld: String,
+    pub(crate) purchase_date: DateTime<Utc>,
+    pub(crate) expire_date: DateTime<Utc>,
+    pub(crate) auto_renew: bool,
+    pub(crate) not_local: bool,
+    pub(crate) security_lock: bool,
+    pub(crate) whois_privacy: bool,
+    pub(crate) status: Option<String>,
+}
+
+#[async_trait::async_trait]
+pub(crate) trait Registrar: Send + Sync {
+    async fn list_domains(&self) -> color_eyre::Result<Vec<RegistrarDomain>>;
+
+    async fn get_nameservers(&self, domain: &str) -> color_eyre::Result<Vec<String>>;
+
+    async fn set_nameservers(&self, domain: &str, nameservers: &[String])
+        -> color_eyre::Result<()>;
+
+    /// What a year's renewal costs, in cents, keyed by TLD
+    async fn renewal_prices(&self) -> color_eyre::Result<HashMap<String, i64>>;
+}
+
+/// Stored in `Domains.registrar`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum RegistrarKind {
+    Porkbun,
+}
+
+impl RegistrarKind {
+    pub(crate) const ALL: [RegistrarKind; 1] = [RegistrarKind::Porkbun];
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            RegistrarKind::Porkbun => "porkbun",
+        }
+    }
+
+    pub(crate) fn parse(registrar: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|r| r.as_str() == registrar)
+            .ok_or_else(|| format!("{registrar} isn't a known registrar"))
+    }
+
+    pub(crate) fn client(&self, apis: &ApiConfig) -> color_eyre::Result<Box<dyn Registrar>> {
+        Ok(match self {
+            RegistrarKind::Porkbun => Box::new(porkbun::Porkbun::new(apis.porkbun()?.clone())),
+        })
+    }
+}
+
+impl std::fmt::Display for RegistrarKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RegistrarKind::Porkbun => write!(f, "Porkbun"),
+        }
+    }
+}
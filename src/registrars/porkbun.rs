@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+
+use crate::{
+    apis::porkbun::{DnsRecordInput, PorkbunClient},
+    dns_hosts::{DnsHost, HostedRecord, RecordInput},
+    routes::domains::DnsProvider,
+};
+
+use super::{Registrar, RegistrarDomain};
+
+/// Porkbun sends dates in this format, in UTC
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+pub(crate) struct Porkbun {
+    client: PorkbunClient,
+}
+
+impl Porkbun {
+    pub(crate) fn new(client: PorkbunClient) -> Self {
+        Self { client }
+    }
+}
+
+impl From<&RecordInput> for DnsRecordInput {
+    fn from(record: &RecordInput) -> Self {
+        DnsRecordInput {
+            name: record.subdomain.clone(),
+            record_type: record.record_type.clone(),
+            content: record.content.clone(),
+            ttl: record.ttl.to_string(),
+            prio: record.prio.map(|prio| prio.to_string()),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Registrar for Porkbun {
+    async fn list_domains(&self) -> color_eyre::Result<Vec<RegistrarDomain>> {
+        self.client
+            .list_domains()
+            .await?
+            .into_iter()
+            .map(|domain| {
+                Ok(RegistrarDomain {
+                    purchase_date: NaiveDateTime::parse_from_str(&domain.create_date, DATE_FORMAT)?
+                        .and_utc(),
+                    expire_date: NaiveDateTime::parse_from_str(&domain.expire_date, DATE_FORMAT)?
+                        .and_utc(),
+                    auto_renew: domain.auto_renew == "1",
+                    not_local: domain.not_local == 1,
+                    security_lock: domain.security_lock == "1",
+                    whois_privacy: domain.whois_privacy == "1",
+                    status: domain.status,
+                    tld: domain.tld,
+                    domain: domain.domain,
+                })
+            })
+            .collect()
+    }
+
+    async fn get_nameservers(&self, domain: &str) -> color_eyre::Result<Vec<String>> {
+        Ok(self.client.get_nameservers(domain).await?)
+    }
+
+    async fn set_nameservers(
+        &self,
+        domain: &str,
+        nameservers: &[String],
+    ) -> color_eyre::Result<()> {
+        Ok(self.client.update_nameservers(domain, nameservers).await?)
+    }
+
+    async fn renewal_prices(&self) -> color_eyre::Result<HashMap<String, i64>> {
+        self.client
+            .get_pricing()
+            .await?
+            .into_iter()
+            .map(|(tld, pricing)| {
+                let dollars: f64 = pricing.renewal.parse()?;
+                Ok((tld, (dollars * 100.0).round() as i64))
+            })
+            .collect()
+    }
+}
+
+/// Porkbun also hosts DNS for the domains registered with it
+#[async_trait::async_trait]
+impl DnsHost for Porkbun {
+    fn provider(&self) -> DnsProvider {
+        DnsProvider::Porkbun
+    }
+
+    async fn get_records(&self, domain: &str) -> color_eyre::Result<Vec<HostedRecord>> {
+        self.client
+            .list_dns_records(domain)
+            .await?
+            .into_iter()
+            .map(|record| {
+                let prio = record
+                    .prio
+                    .filter(|prio| !prio.is_empty())
+                    .map(|prio| prio.parse::<i32>())
+                    .transpose()?;
+
+                Ok(HostedRecord {
+                    ttl: record.ttl.parse()?,
+                    prio,
+                    notes: record.notes.filter(|notes| !notes.is_empty()),
+                    id: record.id,
+                    name: record.name,
+                    record_type: record.record_type,
+                    content: record.content,
+                })
+            })
+            .collect()
+    }
+
+    async fn create_record(
+        &self,
+        domain: &str,
+        record: &RecordInput,
+    ) -> color_eyre::Result<String> {
+        Ok(self
+            .client
+            .create_dns_record(domain, &record.into())
+            .await?)
+    }
+
+    async fn update_record(
+        &self,
+        domain: &str,
+        record_id: &str,
+        record: &RecordInput,
+    ) -> color_eyre::Result<()> {
+        Ok(self
+            .client
+            .edit_dns_record(domain, record_id, &record.into())
+            .await?)
+    }
+
+    async fn delete_record(&self, domain: &str, record_id: &str) -> color_eyre::Result<()> {
+        Ok(self.client.delete_dns_record(domain, record_id).await?)
+    }
+}
@@ -1,2 +1,12 @@
+pub(crate) mod analytics;
+pub(crate) mod api;
+pub(crate) mod api_tokens;
+pub(crate) mod calendar;
+pub(crate) mod dns;
 pub(crate) mod domains;
 pub(crate) mod login;
+pub(crate) mod nameservers;
+pub(crate) mod notifications;
+pub(crate) mod redirects;
+pub(crate) mod tags;
+pub(crate) mod zone;
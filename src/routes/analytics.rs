@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+
+use axum::{extract::State, response::IntoResponse};
+use chrono::{Duration, Utc};
+use cja::app_state::AppState as _;
+use maud::{html, Markup};
+
+use crate::{auth::AdminSession, AppState};
+
+/// Number of days shown in each domain's chart
+const CHART_DAYS: usize = 30;
+
+/// A tiny inline SVG bar chart, one bar per day with the oldest day on the left
+fn daily_chart(daily_hits: &[i64]) -> Markup {
+    const BAR_WIDTH: usize = 8;
+    const HEIGHT: i64 = 40;
+
+    let max = daily_hits.iter().copied().max().unwrap_or(0).max(1);
+    let width = daily_hits.len() * BAR_WIDTH;
+
+    html! {
+        svg width=(width) height=(HEIGHT) viewBox={ "0 0 " (width) " " (HEIGHT) } role="img" {
+            @for (i, hits) in daily_hits.iter().enumerate() {
+                @let bar_height = hits * HEIGHT / max;
+                rect x=(i * BAR_WIDTH) y=(HEIGHT - bar_height) width=(BAR_WIDTH - 1) height=(bar_height) fill="currentColor" {
+                    title { (hits) " hits" }
+                }
+            }
+        }
+    }
+}
+
+pub(crate) async fn show(_: AdminSession, State(app_state): State<AppState>) -> impl IntoResponse {
+    let totals = sqlx::query!(
+        r#"SELECT
+          d.domain,
+          COALESCE(SUM(r.hits) FILTER (WHERE r.granularity = 'hour' AND r.bucket_start >= NOW() - INTERVAL '24 hours'), 0)::BIGINT AS "last_day!",
+          COALESCE(SUM(r.hits) FILTER (WHERE r.granularity = 'day' AND r.bucket_start >= NOW() - INTERVAL '7 days'), 0)::BIGINT AS "last_week!",
+          COALESCE(SUM(r.hits) FILTER (WHERE r.granularity = 'day' AND r.bucket_start >= NOW() - INTERVAL '30 days'), 0)::BIGINT AS "last_month!",
+          COALESCE(SUM(r.hits) FILTER (WHERE r.granularity = 'day'), 0)::BIGINT AS "all_time!"
+        FROM Domains d
+        JOIN Redirects rd ON rd.host = d.domain OR rd.host LIKE '%.' || d.domain
+        LEFT JOIN RedirectHitRollups r ON r.redirect_id = rd.redirect_id
+        GROUP BY d.domain
+        ORDER BY 5 DESC, d.domain"#
+    )
+    .fetch_all(app_state.db())
+    .await
+    .unwrap();
+
+    let chart_start = (Utc::now() - Duration::days(CHART_DAYS as i64 - 1)).date_naive();
+    let daily = sqlx::query!(
+        r#"SELECT d.domain, r.bucket_start, SUM(r.hits)::BIGINT AS "hits!"
+        FROM Domains d
+        JOIN Redirects rd ON rd.host = d.domain OR rd.host LIKE '%.' || d.domain
+        JOIN RedirectHitRollups r ON r.redirect_id = rd.redirect_id
+        WHERE r.granularity = 'day' AND r.bucket_start >= $1
+        GROUP BY d.domain, r.bucket_start"#,
+        chart_start.and_hms_opt(0, 0, 0).unwrap().and_utc()
+    )
+    .fetch_all(app_state.db())
+    .await
+    .unwrap();
+
+    let mut charts = HashMap::<String, Vec<i64>>::new();
+    for row in daily {
+        let day = (row.bucket_start.date_naive() - chart_start).num_days();
+        if let Ok(day @ 0..CHART_DAYS) = usize::try_from(day) {
+            charts
+                .entry(row.domain)
+                .or_insert_with(|| vec![0; CHART_DAYS])[day] += row.hits;
+        }
+    }
+
+    let rules = sqlx::query!(
+        r#"SELECT
+          rd.redirect_id,
+          rd.host,
+          rd.path_pattern,
+          rd.target_url,
+          COALESCE(SUM(r.hits) FILTER (WHERE r.bucket_start >= NOW() - INTERVAL '30 days'), 0)::BIGINT AS "last_month!",
+          COALESCE(SUM(r.hits), 0)::BIGINT AS "all_time!"
+        FROM Redirects rd
+        LEFT JOIN RedirectHitRollups r ON r.redirect_id = rd.redirect_id AND r.granularity = 'day'
+        GROUP BY rd.redirect_id
+        ORDER BY 6 DESC, rd.host, rd.path_pattern"#
+    )
+    .fetch_all(app_state.db())
+    .await
+    .unwrap();
+
+    let referers = sqlx::query!(
+        r#"SELECT referer_host AS "referer_host!", COUNT(*) AS "hits!"
+        FROM RedirectHits
+        WHERE referer_host IS NOT NULL AND hit_at >= NOW() - INTERVAL '30 days'
+        GROUP BY referer_host
+        ORDER BY 2 DESC
+        LIMIT 10"#
+    )
+    .fetch_all(app_state.db())
+    .await
+    .unwrap();
+
+    let user_agents = sqlx::query!(
+        r#"SELECT user_agent_class, COUNT(*) AS "hits!"
+        FROM RedirectHits
+        WHERE hit_at >= NOW() - INTERVAL '30 days'
+        GROUP BY user_agent_class
+        ORDER BY 2 DESC"#
+    )
+    .fetch_all(app_state.db())
+    .await
+    .unwrap();
+
+    html! {
+        h1 { "Redirect Analytics" }
+
+        a href="/" { "Home" }
+
+        p { "Totals are rolled up once an hour, so the most recent hits may not be counted yet." }
+
+        h2 { "By domain" }
+
+        table {
+            thead {
+                tr {
+                    th { "Domain" }
+                    th { "Last 30 days" }
+                    th { "24 hours" }
+                    th { "7 days" }
+                    th { "30 days" }
+                    th { "All time" }
+                }
+            }
+
+            tbody {
+                @for row in &totals {
+                    tr {
+                        td { a href={ "/domains/" (row.domain) "/redirects" } { (row.domain) } }
+                        td {
+                            @if let Some(chart) = charts.get(&row.domain) {
+                                (daily_chart(chart))
+                            }
+                        }
+                        td { (row.last_day) }
+                        td { (row.last_week) }
+                        td { (row.last_month) }
+                        td { (row.all_time) }
+                    }
+                }
+            }
+        }
+
+        h2 { "By rule" }
+
+        table {
+            thead {
+                tr {
+                    th { "Host" }
+                    th { "Path" }
+                    th { "Target" }
+                    th { "30 days" }
+                    th { "All time" }
+                }
+            }
+
+            tbody {
+                @for rule in &rules {
+                    tr {
+                        td { (rule.host) }
+                        td { code { (rule.path_pattern) } }
+                        td { (rule.target_url) }
+                        td { (rule.last_month) }
+                        td { (rule.all_time) }
+                    }
+                }
+            }
+        }
+
+        h2 { "Top referers, last 30 days" }
+
+        @if referers.is_empty() {
+            p { "No referers recorded" }
+        } @else {
+            ul {
+                @for referer in &referers {
+                    li { (referer.referer_host) ": " (referer.hits) }
+                }
+            }
+        }
+
+        h2 { "Visitors, last 30 days" }
+
+        ul {
+            @for user_agent in &user_agents {
+                li { (user_agent.user_agent_class) ": " (user_agent.hits) }
+            }
+        }
+    }
+}
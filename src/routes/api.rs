@@ -0,0 +1,522 @@
+use axum::{
+    async_trait,
+    extract::{
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+        FromRequestParts,
+    },
+    http::{header, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use cja::app_state::AppState as _;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{auth::hash_token, routes::domains::Domain, AppState};
+
+pub(crate) mod domains;
+pub(crate) mod redirects;
+
+/// Everything under `/api/v1`, for scripts rather than browsers
+pub(crate) fn router() -> Router<AppState> {
+    Router::new()
+        .route("/domains", get(domains::index))
+        .route("/domains/:domain", get(domains::show))
+        .route("/domains/:domain/nameservers", get(domains::nameservers))
+        .route("/domains/:domain/dns_records", get(domains::dns_records))
+        .route(
+            "/domains/:domain/redirects",
+            get(redirects::index).post(redirects::create),
+        )
+        .route(
+            "/domains/:domain/redirects/:redirect_id",
+            get(redirects::show)
+                .put(redirects::update)
+                .delete(redirects::destroy),
+        )
+        .fallback(|| async { ApiError::not_found("No such endpoint") })
+}
+
+/// Every failure comes back as `{"error": {"code": ..., "message": ...}}`
+#[derive(Debug)]
+pub(crate) struct ApiError {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+}
+
+pub(crate) type ApiResult<T> = Result<T, ApiError>;
+
+impl ApiError {
+    pub(crate) fn unauthorized(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::UNAUTHORIZED,
+            code: "unauthorized",
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn not_found(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::NOT_FOUND,
+            code: "not_found",
+            message: message.into(),
+        }
+    }
+
+    /// The request couldn't be read at all, e.g. malformed JSON or query parameters
+    pub(crate) fn bad_request(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            code: "bad_request",
+            message: message.into(),
+        }
+    }
+
+    /// The request was readable but its values weren't acceptable
+    pub(crate) fn invalid(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::UNPROCESSABLE_ENTITY,
+            code: "invalid",
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn conflict(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::CONFLICT,
+            code: "conflict",
+            message: message.into(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = json!({ "error": { "code": self.code, "message": self.message } });
+
+        (self.status, Json(body)).into_response()
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(e: sqlx::Error) -> Self {
+        tracing::error!(error = %e, "API request failed");
+
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            code: "internal",
+            message: "Something went wrong on our end".to_string(),
+        }
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::bad_request(rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::bad_request(rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> Self {
+        Self::bad_request(rejection.body_text())
+    }
+}
+
+/// An admin's API token from the `Authorization: Bearer` header
+#[allow(dead_code)]
+pub(crate) struct ApiAuth {
+    pub(crate) api_token_id: Uuid,
+    pub(crate) user_id: Uuid,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for ApiAuth {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let token = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or_else(|| {
+                ApiError::unauthorized("Send an API token as `Authorization: Bearer <token>`")
+            })?;
+
+        let auth = sqlx::query_as!(
+            ApiAuth,
+            "UPDATE ApiTokens t SET last_used_at = NOW()
+            FROM Users u
+            WHERE t.token_hash = $1 AND u.user_id = t.user_id AND u.is_admin
+            RETURNING t.api_token_id, t.user_id",
+            hash_token(token.trim())
+        )
+        .fetch_optional(state.db())
+        .await?;
+
+        auth.ok_or_else(|| ApiError::unauthorized("That API token isn't valid"))
+    }
+}
+
+const DEFAULT_PER_PAGE: i64 = 50;
+const MAX_PER_PAGE: i64 = 100;
+
+/// `?page=` and `?per_page=` on every list endpoint
+#[derive(Debug, Deserialize)]
+pub(crate) struct Pagination {
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+impl Pagination {
+    fn page(&self) -> ApiResult<i64> {
+        match self.page {
+            Some(page) if page < 1 => Err(ApiError::invalid("page starts at 1")),
+            page => Ok(page.unwrap_or(1)),
+        }
+    }
+
+    fn per_page(&self) -> ApiResult<i64> {
+        match self.per_page {
+            Some(per_page) if !(1..=MAX_PER_PAGE).contains(&per_page) => Err(ApiError::invalid(
+                format!("per_page must be between 1 and {MAX_PER_PAGE}"),
+            )),
+            per_page => Ok(per_page.unwrap_or(DEFAULT_PER_PAGE)),
+        }
+    }
+
+    /// `(limit, offset)` for the query
+    pub(crate) fn limit_offset(&self) -> ApiResult<(i64, i64)> {
+        let per_page = self.per_page()?;
+
+        let offset = (self.page()? - 1)
+            .checked_mul(per_page)
+            .ok_or_else(|| ApiError::invalid("page is too large"))?;
+
+        Ok((per_page, offset))
+    }
+
+    pub(crate) fn page_of<T: Serialize>(
+        &self,
+        data: Vec<T>,
+        total: i64,
+    ) -> ApiResult<Json<Page<T>>> {
+        let per_page = self.per_page()?;
+
+        Ok(Json(Page {
+            data,
+            pagination: PageInfo {
+                page: self.page()?,
+                per_page,
+                total,
+                total_pages: (total + per_page - 1) / per_page,
+            },
+        }))
+    }
+}
+
+/// The shape of every list response
+#[derive(Debug, Serialize)]
+pub(crate) struct Page<T> {
+    data: Vec<T>,
+    pagination: PageInfo,
+}
+
+#[derive(Debug, Serialize)]
+struct PageInfo {
+    page: i64,
+    per_page: i64,
+    total: i64,
+    total_pages: i64,
+}
+
+pub(crate) async fn find_domain(app_state: &AppState, domain: &str) -> ApiResult<Domain> {
+    Domain::find_by_domain(app_state.db(), domain)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("{domain} isn't one of our domains")))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request};
+    use serde_json::Value;
+    use sqlx::PgPool;
+    use tower::Service;
+
+    use super::*;
+
+    const TOKEN: &str = "test-token";
+
+    async fn setup(pool: &PgPool) -> Router {
+        let user_id = Uuid::new_v4();
+        sqlx::query!(
+            "INSERT INTO Users (user_id, coreyja_user_id, is_active_sponsor, is_admin)
+            VALUES ($1, $2, false, true)",
+            user_id,
+            Uuid::new_v4()
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+        sqlx::query!(
+            "INSERT INTO ApiTokens (api_token_id, user_id, name, token_hash)
+            VALUES ($1, $2, 'tests', $3)",
+            Uuid::new_v4(),
+            user_id,
+            hash_token(TOKEN)
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+
+        for domain in ["a.test", "b.test", "c.test"] {
+            sqlx::query!(
+                "INSERT INTO Domains
+                  (domain_id, auto_renew, purchase_date, domain, expire_date, not_local, security_lock, status, tld, whois_privacy, registrar)
+                VALUES ($1, true, NOW(), $2, NOW() + INTERVAL '1 year', false, true, 'ACTIVE', 'test', true, 'porkbun')",
+                Uuid::new_v4(),
+                domain
+            )
+            .execute(pool)
+            .await
+            .unwrap();
+        }
+
+        router().with_state(AppState::with_pool(pool.clone()).unwrap())
+    }
+
+    async fn request(
+        router: &Router,
+        method: &str,
+        uri: &str,
+        token: Option<&str>,
+        body: Option<Value>,
+    ) -> (StatusCode, Value) {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let body = match body {
+            Some(body) => {
+                request = request.header(header::CONTENT_TYPE, "application/json");
+                Body::from(body.to_string())
+            }
+            None => Body::empty(),
+        };
+
+        let response = router
+            .clone()
+            .call(request.body(body).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+
+        (
+            status,
+            serde_json::from_slice(&bytes).unwrap_or(Value::Null),
+        )
+    }
+
+    #[sqlx::test]
+    async fn requires_a_token(pool: PgPool) {
+        let router = setup(&pool).await;
+
+        let (status, body) = request(&router, "GET", "/domains", None, None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(body["error"]["code"], "unauthorized");
+
+        let (status, _) = request(&router, "GET", "/domains", Some("nope"), None).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, _) = request(&router, "GET", "/domains", Some(TOKEN), None).await;
+        assert_eq!(status, StatusCode::OK);
+        let last_used_at = sqlx::query_scalar!("SELECT last_used_at FROM ApiTokens")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert!(last_used_at.is_some());
+    }
+
+    #[sqlx::test]
+    async fn paginates_domains(pool: PgPool) {
+        let router = setup(&pool).await;
+
+        let (status, body) =
+            request(&router, "GET", "/domains?per_page=2", Some(TOKEN), None).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["data"].as_array().unwrap().len(), 2);
+        assert_eq!(body["data"][0]["domain"], "a.test");
+        assert_eq!(body["data"][0]["auto_renew"], true);
+        assert_eq!(
+            body["pagination"],
+            serde_json::json!({ "page": 1, "per_page": 2, "total": 3, "total_pages": 2 })
+        );
+
+        let (_, body) = request(
+            &router,
+            "GET",
+            "/domains?per_page=2&page=2",
+            Some(TOKEN),
+            None,
+        )
+        .await;
+        assert_eq!(body["data"].as_array().unwrap().len(), 1);
+        assert_eq!(body["data"][0]["domain"], "c.test");
+
+        let (status, body) =
+            request(&router, "GET", "/domains?per_page=500", Some(TOKEN), None).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["error"]["code"], "invalid");
+
+        let (status, body) = request(
+            &router,
+            "GET",
+            "/domains?page=999999999999999999",
+            Some(TOKEN),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["error"]["code"], "invalid");
+
+        let (status, body) =
+            request(&router, "GET", "/domains?page=first", Some(TOKEN), None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "bad_request");
+    }
+
+    #[sqlx::test]
+    async fn returns_json_errors_for_missing_things(pool: PgPool) {
+        let router = setup(&pool).await;
+
+        let (status, body) = request(&router, "GET", "/domains/nope.test", Some(TOKEN), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"]["code"], "not_found");
+
+        let (status, body) = request(&router, "GET", "/nope", Some(TOKEN), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"]["code"], "not_found");
+
+        let (status, body) = request(
+            &router,
+            "GET",
+            "/domains/a.test/redirects/not-a-uuid",
+            Some(TOKEN),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "bad_request");
+    }
+
+    #[sqlx::test]
+    async fn manages_redirects(pool: PgPool) {
+        let router = setup(&pool).await;
+        let rule = serde_json::json!({ "target_url": "https://b.test/" });
+
+        let (status, created) = request(
+            &router,
+            "POST",
+            "/domains/a.test/redirects",
+            Some(TOKEN),
+            Some(rule.clone()),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(created["host"], "a.test");
+        assert_eq!(created["path_pattern"], "*");
+        assert_eq!(created["status_code"], 301);
+        let path = format!(
+            "/domains/a.test/redirects/{}",
+            created["id"].as_str().unwrap()
+        );
+
+        let (status, body) = request(
+            &router,
+            "POST",
+            "/domains/a.test/redirects",
+            Some(TOKEN),
+            Some(rule),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert_eq!(body["error"]["code"], "conflict");
+
+        let (status, body) = request(
+            &router,
+            "POST",
+            "/domains/a.test/redirects",
+            Some(TOKEN),
+            Some(serde_json::json!({ "target_url": "https://b.test/", "status_code": 200 })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body["error"]["code"], "invalid");
+
+        let (status, body) = request(
+            &router,
+            "POST",
+            "/domains/a.test/redirects",
+            Some(TOKEN),
+            Some(serde_json::json!({ "target": "https://b.test/" })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["error"]["code"], "bad_request");
+
+        let (status, updated) = request(
+            &router,
+            "PUT",
+            &path,
+            Some(TOKEN),
+            Some(serde_json::json!({ "target_url": "https://c.test/", "status_code": 302 })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(updated["target_url"], "https://c.test/");
+        assert_eq!(updated["status_code"], 302);
+
+        // Rules only show up under the domain they belong to
+        let (status, _) = request(
+            &router,
+            "GET",
+            &path.replace("a.test", "b.test"),
+            Some(TOKEN),
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (_, list) = request(
+            &router,
+            "GET",
+            "/domains/a.test/redirects",
+            Some(TOKEN),
+            None,
+        )
+        .await;
+        assert_eq!(list["pagination"]["total"], 1);
+        assert_eq!(list["data"][0]["id"], created["id"]);
+
+        let (status, _) = request(&router, "DELETE", &path, Some(TOKEN), None).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+
+        let (status, _) = request(&router, "GET", &path, Some(TOKEN), None).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,231 @@
+use axum::{
+    extract::{
+        rejection::{PathRejection, QueryRejection},
+        Path, Query, State,
+    },
+    Json,
+};
+use chrono::{DateTime, Utc};
+use cja::app_state::AppState as _;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    dns::DnsRecord,
+    routes::{
+        api::{find_domain, ApiAuth, ApiResult, Page, Pagination},
+        domains::Domain,
+        tags::Tag,
+    },
+    AppState,
+};
+
+/// A domain with everything the registrar reports about it, under the same names as Porkbun's
+/// `listAll`, plus what we track ourselves
+#[derive(Debug, Serialize)]
+pub(crate) struct DomainJson {
+    domain: String,
+    tld: String,
+    status: Option<String>,
+    create_date: DateTime<Utc>,
+    expire_date: DateTime<Utc>,
+    auto_renew: bool,
+    security_lock: bool,
+    whois_privacy: bool,
+    not_local: bool,
+    registrar: String,
+    nameservers: Vec<String>,
+    dns_provider: String,
+    intent: Option<String>,
+    notes: String,
+    tags: Vec<String>,
+    removed_at: Option<DateTime<Utc>>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl DomainJson {
+    fn new(domain: Domain, tags: Vec<Tag>) -> Self {
+        Self {
+            tags: tags.into_iter().map(|tag| tag.name).collect(),
+            domain: domain.domain,
+            tld: domain.tld,
+            status: domain.status,
+            create_date: domain.purchase_date,
+            expire_date: domain.expire_date,
+            auto_renew: domain.auto_renew,
+            security_lock: domain.security_lock,
+            whois_privacy: domain.whois_privacy,
+            not_local: domain.not_local,
+            registrar: domain.registrar,
+            nameservers: domain.nameservers,
+            dns_provider: domain.dns_provider,
+            intent: domain.intent,
+            notes: domain.notes,
+            removed_at: domain.removed_at,
+            created_at: domain.created_at,
+            updated_at: domain.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct DomainsQuery {
+    /// Also list domains the registrar no longer has
+    #[serde(default)]
+    include_removed: bool,
+}
+
+pub(crate) async fn index(
+    _: ApiAuth,
+    State(app_state): State<AppState>,
+    query: Result<Query<DomainsQuery>, QueryRejection>,
+    pagination: Result<Query<Pagination>, QueryRejection>,
+) -> ApiResult<Json<Page<DomainJson>>> {
+    let Query(query) = query?;
+    let Query(pagination) = pagination?;
+    let (limit, offset) = pagination.limit_offset()?;
+
+    let domains = sqlx::query_as!(
+        Domain,
+        "SELECT * FROM Domains
+        WHERE $1 OR removed_at IS NULL
+        ORDER BY domain
+        LIMIT $2 OFFSET $3",
+        query.include_removed,
+        limit,
+        offset
+    )
+    .fetch_all(app_state.db())
+    .await?;
+
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM Domains WHERE $1 OR removed_at IS NULL"#,
+        query.include_removed
+    )
+    .fetch_one(app_state.db())
+    .await?;
+
+    let domain_ids = domains.iter().map(|d| d.domain_id).collect::<Vec<_>>();
+    let mut tags = Tag::for_domains(app_state.db(), &domain_ids).await?;
+    let domains = domains
+        .into_iter()
+        .map(|domain| {
+            let tags = tags.remove(&domain.domain_id).unwrap_or_default();
+            DomainJson::new(domain, tags)
+        })
+        .collect();
+
+    pagination.page_of(domains, total)
+}
+
+pub(crate) async fn show(
+    _: ApiAuth,
+    State(app_state): State<AppState>,
+    path: Result<Path<String>, PathRejection>,
+) -> ApiResult<Json<DomainJson>> {
+    let Path(domain) = path?;
+    let domain = find_domain(&app_state, &domain).await?;
+
+    let tags = Tag::for_domains(app_state.db(), &[domain.domain_id])
+        .await?
+        .remove(&domain.domain_id)
+        .unwrap_or_default();
+
+    Ok(Json(DomainJson::new(domain, tags)))
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct NameserversJson {
+    domain: String,
+    nameservers: Vec<String>,
+    dns_provider: String,
+}
+
+/// As of the last sync
+pub(crate) async fn nameservers(
+    _: ApiAuth,
+    State(app_state): State<AppState>,
+    path: Result<Path<String>, PathRejection>,
+) -> ApiResult<Json<NameserversJson>> {
+    let Path(domain) = path?;
+    let domain = find_domain(&app_state, &domain).await?;
+
+    Ok(Json(NameserversJson {
+        domain: domain.domain,
+        nameservers: domain.nameservers,
+        dns_provider: domain.dns_provider,
+    }))
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct DnsRecordJson {
+    id: Uuid,
+    provider: String,
+    provider_record_id: String,
+    name: String,
+    #[serde(rename = "type")]
+    record_type: String,
+    content: String,
+    ttl: i32,
+    prio: Option<i32>,
+    notes: Option<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<DnsRecord> for DnsRecordJson {
+    fn from(record: DnsRecord) -> Self {
+        Self {
+            id: record.dns_record_id,
+            provider: record.provider,
+            provider_record_id: record.provider_record_id,
+            name: record.name,
+            record_type: record.record_type,
+            content: record.content,
+            ttl: record.ttl,
+            prio: record.prio,
+            notes: record.notes,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+        }
+    }
+}
+
+/// The records from the domain's DNS provider as of the last sync
+pub(crate) async fn dns_records(
+    _: ApiAuth,
+    State(app_state): State<AppState>,
+    path: Result<Path<String>, PathRejection>,
+    pagination: Result<Query<Pagination>, QueryRejection>,
+) -> ApiResult<Json<Page<DnsRecordJson>>> {
+    let Path(domain) = path?;
+    let Query(pagination) = pagination?;
+    let (limit, offset) = pagination.limit_offset()?;
+    let domain = find_domain(&app_state, &domain).await?;
+
+    let records = sqlx::query_as!(
+        DnsRecord,
+        "SELECT * FROM DnsRecords
+        WHERE domain_id = $1
+        ORDER BY name, record_type, content
+        LIMIT $2 OFFSET $3",
+        domain.domain_id,
+        limit,
+        offset
+    )
+    .fetch_all(app_state.db())
+    .await?;
+
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM DnsRecords WHERE domain_id = $1"#,
+        domain.domain_id
+    )
+    .fetch_one(app_state.db())
+    .await?;
+
+    pagination.page_of(
+        records.into_iter().map(DnsRecordJson::from).collect(),
+        total,
+    )
+}
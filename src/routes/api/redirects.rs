@@ -0,0 +1,314 @@
+use axum::{
+    extract::{
+        rejection::{JsonRejection, PathRejection, QueryRejection},
+        Path, Query, State,
+    },
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use cja::app_state::AppState as _;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    redirects::{
+        compile_path_pattern, validate_host, validate_mode, validate_status_code,
+        validate_target_url, PatternType, RedirectMode, RedirectRule,
+    },
+    routes::api::{find_domain, ApiAuth, ApiError, ApiResult, Page, Pagination},
+    AppState,
+};
+
+#[derive(Debug, Serialize)]
+pub(crate) struct RedirectJson {
+    id: Uuid,
+    host: String,
+    match_apex: bool,
+    match_www: bool,
+    path_pattern: String,
+    pattern_type: String,
+    priority: i32,
+    target_url: String,
+    mode: String,
+    status_code: i32,
+    enabled: bool,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<RedirectRule> for RedirectJson {
+    fn from(rule: RedirectRule) -> Self {
+        Self {
+            id: rule.redirect_id,
+            host: rule.host,
+            match_apex: rule.match_apex,
+            match_www: rule.match_www,
+            path_pattern: rule.path_pattern,
+            pattern_type: rule.pattern_type,
+            priority: rule.priority,
+            target_url: rule.target_url,
+            mode: rule.mode,
+            status_code: rule.status_code,
+            enabled: rule.enabled,
+            created_at: rule.created_at,
+            updated_at: rule.updated_at,
+        }
+    }
+}
+
+/// The body for creating or replacing a rule. Only `target_url` is required, everything else
+/// defaults the same way the dashboard's form does
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct RedirectInput {
+    host: Option<String>,
+    #[serde(default)]
+    match_apex: bool,
+    #[serde(default = "default_true")]
+    match_www: bool,
+    #[serde(default = "default_path_pattern")]
+    path_pattern: String,
+    #[serde(default = "default_pattern_type")]
+    pattern_type: String,
+    #[serde(default)]
+    priority: i32,
+    target_url: String,
+    #[serde(default = "default_mode")]
+    mode: String,
+    #[serde(default = "default_status_code")]
+    status_code: i32,
+    #[serde(default = "default_true")]
+    enabled: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_path_pattern() -> String {
+    "*".to_string()
+}
+
+fn default_pattern_type() -> String {
+    PatternType::Glob.as_str().to_string()
+}
+
+fn default_mode() -> String {
+    RedirectMode::Fixed.as_str().to_string()
+}
+
+fn default_status_code() -> i32 {
+    301
+}
+
+struct ValidRedirect {
+    host: String,
+    path_pattern: String,
+    pattern_type: PatternType,
+    target_url: String,
+    status_code: i32,
+    mode: RedirectMode,
+}
+
+impl RedirectInput {
+    fn validate(&self, domain: &str) -> Result<ValidRedirect, String> {
+        let host = validate_host(domain, self.host.as_deref().unwrap_or(domain))?;
+
+        let path_pattern = self.path_pattern.trim().to_string();
+        let pattern_type = PatternType::parse(&self.pattern_type)?;
+        let path_matcher = compile_path_pattern(pattern_type, &path_pattern)?;
+
+        Ok(ValidRedirect {
+            target_url: validate_target_url(
+                &host,
+                self.match_apex,
+                &path_matcher,
+                &self.target_url,
+            )?,
+            host,
+            path_pattern,
+            pattern_type,
+            status_code: validate_status_code(self.status_code)?,
+            mode: validate_mode(&self.mode, &self.target_url)?,
+        })
+    }
+}
+
+fn duplicate(valid: &ValidRedirect) -> ApiError {
+    ApiError::conflict(format!(
+        "{} already has a {} redirect for {}",
+        valid.host,
+        valid.pattern_type.as_str(),
+        valid.path_pattern
+    ))
+}
+
+async fn find_rule(
+    app_state: &AppState,
+    domain: &str,
+    redirect_id: Uuid,
+) -> ApiResult<RedirectRule> {
+    let domain = find_domain(app_state, domain).await?;
+
+    sqlx::query_as!(
+        RedirectRule,
+        "SELECT * FROM Redirects WHERE (host = $1 OR host LIKE '%.' || $1) AND redirect_id = $2",
+        domain.domain,
+        redirect_id
+    )
+    .fetch_optional(app_state.db())
+    .await?
+    .ok_or_else(|| ApiError::not_found(format!("{} has no redirect {redirect_id}", domain.domain)))
+}
+
+/// Every rule for the domain and its subdomains, highest priority first
+pub(crate) async fn index(
+    _: ApiAuth,
+    State(app_state): State<AppState>,
+    path: Result<Path<String>, PathRejection>,
+    pagination: Result<Query<Pagination>, QueryRejection>,
+) -> ApiResult<Json<Page<RedirectJson>>> {
+    let Path(domain) = path?;
+    let Query(pagination) = pagination?;
+    let (limit, offset) = pagination.limit_offset()?;
+    let domain = find_domain(&app_state, &domain).await?;
+
+    let rules = sqlx::query_as!(
+        RedirectRule,
+        "SELECT * FROM Redirects
+        WHERE host = $1 OR host LIKE '%.' || $1
+        ORDER BY host, priority DESC, created_at
+        LIMIT $2 OFFSET $3",
+        domain.domain,
+        limit,
+        offset
+    )
+    .fetch_all(app_state.db())
+    .await?;
+
+    let total = sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM Redirects WHERE host = $1 OR host LIKE '%.' || $1"#,
+        domain.domain
+    )
+    .fetch_one(app_state.db())
+    .await?;
+
+    pagination.page_of(rules.into_iter().map(RedirectJson::from).collect(), total)
+}
+
+pub(crate) async fn show(
+    _: ApiAuth,
+    State(app_state): State<AppState>,
+    path: Result<Path<(String, Uuid)>, PathRejection>,
+) -> ApiResult<Json<RedirectJson>> {
+    let Path((domain, redirect_id)) = path?;
+    let rule = find_rule(&app_state, &domain, redirect_id).await?;
+
+    Ok(Json(rule.into()))
+}
+
+pub(crate) async fn create(
+    _: ApiAuth,
+    State(app_state): State<AppState>,
+    path: Result<Path<String>, PathRejection>,
+    input: Result<Json<RedirectInput>, JsonRejection>,
+) -> ApiResult<(StatusCode, Json<RedirectJson>)> {
+    let Path(domain) = path?;
+    let Json(input) = input?;
+    let domain = find_domain(&app_state, &domain).await?;
+    let valid = input.validate(&domain.domain).map_err(ApiError::invalid)?;
+
+    let rule = sqlx::query_as!(
+        RedirectRule,
+        "INSERT INTO Redirects (redirect_id, host, match_apex, match_www, path_pattern, pattern_type, priority, target_url, status_code, mode, enabled)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        ON CONFLICT (host, path_pattern, pattern_type) DO NOTHING
+        RETURNING *",
+        Uuid::new_v4(),
+        valid.host,
+        input.match_apex,
+        input.match_www,
+        valid.path_pattern,
+        valid.pattern_type.as_str(),
+        input.priority,
+        valid.target_url,
+        valid.status_code,
+        valid.mode.as_str(),
+        input.enabled
+    )
+    .fetch_optional(app_state.db())
+    .await?
+    .ok_or_else(|| duplicate(&valid))?;
+
+    Ok((StatusCode::CREATED, Json(rule.into())))
+}
+
+/// Replaces the whole rule, fields left out go back to their defaults
+pub(crate) async fn update(
+    _: ApiAuth,
+    State(app_state): State<AppState>,
+    path: Result<Path<(String, Uuid)>, PathRejection>,
+    input: Result<Json<RedirectInput>, JsonRejection>,
+) -> ApiResult<Json<RedirectJson>> {
+    let Path((domain, redirect_id)) = path?;
+    let Json(input) = input?;
+    find_rule(&app_state, &domain, redirect_id).await?;
+    let valid = input.validate(&domain).map_err(ApiError::invalid)?;
+
+    let result = sqlx::query_as!(
+        RedirectRule,
+        "UPDATE Redirects
+        SET
+          host = $1,
+          match_apex = $2,
+          match_www = $3,
+          path_pattern = $4,
+          pattern_type = $5,
+          priority = $6,
+          target_url = $7,
+          status_code = $8,
+          mode = $9,
+          enabled = $10,
+          updated_at = NOW()
+        WHERE redirect_id = $11
+        RETURNING *",
+        valid.host,
+        input.match_apex,
+        input.match_www,
+        valid.path_pattern,
+        valid.pattern_type.as_str(),
+        input.priority,
+        valid.target_url,
+        valid.status_code,
+        valid.mode.as_str(),
+        input.enabled,
+        redirect_id
+    )
+    .fetch_one(app_state.db())
+    .await;
+
+    match result {
+        Ok(rule) => Ok(Json(rule.into())),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => Err(duplicate(&valid)),
+        Err(e) => Err(e.into()),
+    }
+}
+
+pub(crate) async fn destroy(
+    _: ApiAuth,
+    State(app_state): State<AppState>,
+    path: Result<Path<(String, Uuid)>, PathRejection>,
+) -> ApiResult<StatusCode> {
+    let Path((domain, redirect_id)) = path?;
+    let rule = find_rule(&app_state, &domain, redirect_id).await?;
+
+    sqlx::query!(
+        "DELETE FROM Redirects WHERE redirect_id = $1",
+        rule.redirect_id
+    )
+    .execute(app_state.db())
+    .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
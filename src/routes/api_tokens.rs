@@ -0,0 +1,161 @@
+use axum::{
+    extract::{Path, State},
+    response::{IntoResponse, Redirect, Response},
+    Form,
+};
+use cja::app_state::AppState as _;
+use maud::html;
+use serde::Deserialize;
+use tower_cookies::Cookies;
+use uuid::Uuid;
+
+use crate::{
+    auth::{hash_token, AdminSession},
+    flash::Flash,
+    AppState,
+};
+
+const INDEX_PATH: &str = "/api-tokens";
+
+pub(crate) async fn index(
+    admin: AdminSession,
+    cookies: Cookies,
+    State(app_state): State<AppState>,
+) -> Response {
+    let tokens = sqlx::query!(
+        "SELECT api_token_id, name, last_used_at, created_at FROM ApiTokens
+        WHERE user_id = $1
+        ORDER BY created_at DESC",
+        admin.user.user_id
+    )
+    .fetch_all(app_state.db())
+    .await
+    .unwrap();
+
+    let flash = Flash::take(&cookies, &app_state);
+
+    html! {
+        h1 { "API tokens" }
+
+        a href="/" { "Back" }
+
+        @if let Some(flash) = flash {
+            (flash)
+        }
+
+        p {
+            "Scripts can read domains and manage redirects through " code { "/api/v1" }
+            " by sending a token as " code { "Authorization: Bearer <token>" } "."
+        }
+
+        @if tokens.is_empty() {
+            p { "No tokens yet" }
+        } @else {
+            table {
+                thead {
+                    tr {
+                        th { "Name" }
+                        th { "Created" }
+                        th { "Last used" }
+                        th {}
+                    }
+                }
+
+                tbody {
+                    @for token in &tokens {
+                        tr {
+                            td { (token.name) }
+                            td { (token.created_at.format("%Y-%m-%d")) }
+                            td {
+                                @if let Some(last_used_at) = token.last_used_at {
+                                    (last_used_at.format("%Y-%m-%d %H:%M UTC"))
+                                } @else {
+                                    "Never"
+                                }
+                            }
+                            td {
+                                form method="post" action={ (INDEX_PATH) "/" (token.api_token_id) "/delete" } {
+                                    button type="submit" { "Revoke" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        h2 { "Create a token" }
+
+        form method="post" action=(INDEX_PATH) {
+            label {
+                "Name"
+                input type="text" name="name" required placeholder="Renewal report script";
+            }
+
+            button type="submit" { "Create token" }
+        }
+    }
+    .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ApiTokenForm {
+    name: String,
+}
+
+pub(crate) async fn create(
+    admin: AdminSession,
+    cookies: Cookies,
+    State(app_state): State<AppState>,
+    Form(form): Form<ApiTokenForm>,
+) -> Response {
+    let name = form.name.trim();
+    if name.is_empty() {
+        Flash::error("Tokens need a name").set(&cookies, &app_state);
+        return Redirect::to(INDEX_PATH).into_response();
+    }
+
+    let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+    sqlx::query!(
+        "INSERT INTO ApiTokens (api_token_id, user_id, name, token_hash) VALUES ($1, $2, $3, $4)",
+        Uuid::new_v4(),
+        admin.user.user_id,
+        name,
+        hash_token(&token)
+    )
+    .execute(app_state.db())
+    .await
+    .unwrap();
+
+    // Only the hash is stored, so this is the one chance to copy it
+    Flash::success(format!(
+        "Created {name}, copy the token now as it won't be shown again: {token}"
+    ))
+    .set(&cookies, &app_state);
+
+    Redirect::to(INDEX_PATH).into_response()
+}
+
+pub(crate) async fn destroy(
+    admin: AdminSession,
+    cookies: Cookies,
+    Path(api_token_id): Path<Uuid>,
+    State(app_state): State<AppState>,
+) -> Response {
+    let deleted = sqlx::query_scalar!(
+        "DELETE FROM ApiTokens WHERE api_token_id = $1 AND user_id = $2 RETURNING name",
+        api_token_id,
+        admin.user.user_id
+    )
+    .fetch_optional(app_state.db())
+    .await
+    .unwrap();
+
+    match deleted {
+        Some(name) => Flash::success(format!("Revoked {name}")),
+        None => Flash::error("That token doesn't exist"),
+    }
+    .set(&cookies, &app_state);
+
+    Redirect::to(INDEX_PATH).into_response()
+}
@@ -0,0 +1,146 @@
+use axum::{
+    extract::{Host, Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+};
+use chrono::Utc;
+use cja::app_state::AppState as _;
+use maud::html;
+use tower_cookies::Cookies;
+use uuid::Uuid;
+
+use crate::{
+    auth::{hash_token, AdminSession},
+    calendar::expiry_calendar,
+    expiry::alert_windows,
+    flash::Flash,
+    routes::domains::Domain,
+    AppState,
+};
+
+pub(crate) async fn show(
+    admin: AdminSession,
+    cookies: Cookies,
+    State(app_state): State<AppState>,
+) -> impl IntoResponse {
+    let flash = Flash::take(&cookies, &app_state);
+    let has_link = admin.user.calendar_token_hash.is_some();
+
+    html! {
+        h1 { "Expiry calendar" }
+
+        a href="/domains" { "Back to Domains" }
+
+        @if let Some(flash) = flash {
+            (flash)
+        }
+
+        p { "Subscribe to this feed to see every domain's expiry date in your calendar, with reminders before each one." }
+
+        @if has_link {
+            p {
+                "You have a calendar link. It's only shown once when it's generated, so make a new one if you've lost it. "
+                "Generating a new link stops the old one from working."
+            }
+        }
+
+        form method="post" action="/calendar/token" {
+            button type="submit" {
+                @if has_link { "Generate a new link" } @else { "Generate a link" }
+            }
+        }
+    }
+}
+
+pub(crate) async fn regenerate_token(
+    admin: AdminSession,
+    cookies: Cookies,
+    Host(host): Host,
+    State(app_state): State<AppState>,
+) -> impl IntoResponse {
+    let token = format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+
+    sqlx::query!(
+        "UPDATE Users SET calendar_token_hash = $1, updated_at = NOW() WHERE user_id = $2",
+        hash_token(&token),
+        admin.user.user_id
+    )
+    .execute(app_state.db())
+    .await
+    .unwrap();
+
+    // Only the hash is stored, so this is the one chance to copy it
+    Flash::success(format!(
+        "Generated a new calendar link, copy it now as it won't be shown again: https://{host}/calendar/{token}.ics"
+    ))
+    .set(&cookies, &app_state);
+
+    Redirect::to("/calendar")
+}
+
+pub(crate) async fn feed(
+    Path(calendar_file): Path<String>,
+    State(app_state): State<AppState>,
+) -> Response {
+    let Some(token) = calendar_file.strip_suffix(".ics") else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+
+    let is_admin = sqlx::query_scalar!(
+        "SELECT is_admin FROM Users WHERE calendar_token_hash = $1",
+        hash_token(token)
+    )
+    .fetch_optional(app_state.db())
+    .await
+    .unwrap();
+    if is_admin != Some(true) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let domains = sqlx::query_as!(
+        Domain,
+        "SELECT * FROM Domains WHERE removed_at IS NULL ORDER BY expire_date"
+    )
+    .fetch_all(app_state.db())
+    .await
+    .unwrap();
+
+    (
+        [(header::CONTENT_TYPE, "text/calendar; charset=utf-8")],
+        expiry_calendar(&domains, &alert_windows(), Utc::now()),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+
+    #[sqlx::test]
+    async fn finds_the_feed_by_its_token_hash(pool: PgPool) {
+        let app_state = AppState::with_pool(pool.clone()).unwrap();
+        sqlx::query!(
+            "INSERT INTO Users (user_id, coreyja_user_id, is_active_sponsor, is_admin, calendar_token_hash)
+            VALUES ($1, $2, false, true, $3)",
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            hash_token("secret")
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let found = feed(Path("secret.ics".to_string()), State(app_state.clone())).await;
+        assert_eq!(found.status(), StatusCode::OK);
+
+        // The stored hash itself isn't a way in
+        let by_hash = feed(
+            Path(format!("{}.ics", hash_token("secret"))),
+            State(app_state),
+        )
+        .await;
+        assert_eq!(by_hash.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,611 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    Form,
+};
+use cja::app_state::AppState as _;
+use maud::{html, Markup};
+use serde::Deserialize;
+use tower_cookies::Cookies;
+use uuid::Uuid;
+
+use crate::{
+    apis::ApiConfig,
+    auth::AdminSession,
+    dns::{fqdn, DnsRecord, RecordSnapshot, TtlLimits, PRIO_RECORD_TYPES, RECORD_TYPES},
+    dns_hosts::{DnsHost, RecordInput, MANAGED_PROVIDERS},
+    flash::Flash,
+    routes::domains::{domain_path, Domain},
+    AppState,
+};
+
+pub(crate) fn index_path(domain: &str) -> String {
+    format!("/domains/{domain}/dns")
+}
+
+fn record_path(domain: &str, dns_record_id: Uuid) -> String {
+    format!("/domains/{domain}/dns/{dns_record_id}")
+}
+
+async fn find_record(
+    app_state: &AppState,
+    domain: &Domain,
+    dns_record_id: Uuid,
+) -> Option<DnsRecord> {
+    sqlx::query_as!(
+        DnsRecord,
+        "SELECT * FROM DnsRecords WHERE domain_id = $1 AND dns_record_id = $2",
+        domain.domain_id,
+        dns_record_id
+    )
+    .fetch_optional(app_state.db())
+    .await
+    .unwrap()
+}
+
+/// Explains why a domain's records can't be edited here, or `None` if they can
+pub(crate) fn not_editable_reason(domain: &Domain) -> Option<String> {
+    let provider = domain.dns_provider();
+    if MANAGED_PROVIDERS.contains(&provider) {
+        return None;
+    }
+
+    Some(format!(
+        "DNS for {} is hosted by {provider}. Records can only be edited here for domains using {} DNS.",
+        domain.domain,
+        MANAGED_PROVIDERS.map(|p| p.to_string()).join(" or ")
+    ))
+}
+
+/// The client to edit `domain`'s records with, or why they can't be edited here
+pub(crate) fn editable_host(domain: &Domain, apis: &ApiConfig) -> Result<Box<dyn DnsHost>, String> {
+    if let Some(reason) = not_editable_reason(domain) {
+        return Err(reason);
+    }
+
+    let provider = domain.dns_provider();
+    domain
+        .dns_host(apis)
+        .map_err(|e| format!("{provider} isn't configured: {e}"))?
+        .ok_or_else(|| format!("Records on {provider} can't be edited here"))
+}
+
+fn record_form(domain: &Domain, record: Option<&DnsRecord>) -> Markup {
+    let action = record.map_or_else(
+        || index_path(&domain.domain),
+        |r| record_path(&domain.domain, r.dns_record_id),
+    );
+    let subdomain = record.map(|r| r.subdomain(&domain.domain));
+    let current_type = record.map_or("A", |r| r.record_type.as_str());
+    let content = record.map(|r| r.content.as_str());
+    let ttl_limits = TtlLimits::for_provider(domain.dns_provider());
+    let ttl = record.map_or(ttl_limits.lowest(), |r| r.ttl);
+    let prio = record.and_then(|r| r.prio);
+
+    html! {
+        form method="post" action=(action) {
+            label {
+                "Name"
+                input type="text" name="name" placeholder="Leave blank for the root" value=[subdomain];
+                "." (domain.domain)
+            }
+
+            label {
+                "Type"
+                select name="record_type" {
+                    @for record_type in RECORD_TYPES {
+                        option value=(record_type) selected[record_type == current_type] { (record_type) }
+                    }
+                }
+            }
+
+            label {
+                "Content"
+                input type="text" name="content" required value=[content];
+            }
+
+            label {
+                "TTL"
+                input type="number" name="ttl" min=(ttl_limits.lowest()) max=[ttl_limits.max] value=(ttl);
+                @if ttl_limits.auto {
+                    " 1 for Auto"
+                }
+            }
+
+            label {
+                "Priority (MX and SRV only)"
+                input type="number" name="prio" value=[prio];
+            }
+
+            button type="submit" { "Save record" }
+        }
+    }
+}
+
+pub(crate) async fn index(
+    _: AdminSession,
+    cookies: Cookies,
+    Path(domain): Path<String>,
+    State(app_state): State<AppState>,
+) -> Response {
+    let Some(domain) = Domain::find_by_domain(app_state.db(), &domain)
+        .await
+        .unwrap()
+    else {
+        return (StatusCode::NOT_FOUND, "Domain not found").into_response();
+    };
+
+    let records = sqlx::query_as!(
+        DnsRecord,
+        "SELECT * FROM DnsRecords WHERE domain_id = $1 ORDER BY name, record_type, content",
+        domain.domain_id
+    )
+    .fetch_all(app_state.db())
+    .await
+    .unwrap();
+
+    let flash = Flash::take(&cookies, &app_state);
+    let not_editable = not_editable_reason(&domain);
+    let ttl_limits = TtlLimits::for_provider(domain.dns_provider());
+
+    html! {
+        h1 { "DNS records for " (domain.domain) }
+
+        a href=(domain_path(&domain.domain)) { "Back to " (domain.domain) }
+        " | "
+        a href={ (index_path(&domain.domain)) "/history" } { "Change history" }
+        " | "
+        a href={ (index_path(&domain.domain)) "/export" } { "Export zone file" }
+        " | "
+        a href={ (index_path(&domain.domain)) "/import" } { "Import zone file" }
+
+        @if let Some(flash) = flash {
+            (flash)
+        }
+
+        @if let Some(reason) = &not_editable {
+            p { (reason) }
+        }
+
+        @if records.is_empty() {
+            p { "No DNS records synced yet" }
+        } @else {
+            table {
+                thead {
+                    tr {
+                        th { "Name" }
+                        th { "Type" }
+                        th { "Content" }
+                        th { "TTL" }
+                        th { "Priority" }
+                        @if not_editable.is_none() {
+                            th {}
+                        }
+                    }
+                }
+
+                tbody {
+                    @for record in &records {
+                        tr {
+                            td { (record.name) }
+                            td { (record.record_type) }
+                            td { (record.content) }
+                            td { (ttl_limits.display(record.ttl)) }
+                            td {
+                                @if let Some(prio) = record.prio {
+                                    (prio)
+                                }
+                            }
+                            @if not_editable.is_none() {
+                                td {
+                                    a href=(record_path(&domain.domain, record.dns_record_id)) { "Edit" }
+                                    form method="post" action={ (record_path(&domain.domain, record.dns_record_id)) "/delete" } {
+                                        button type="submit" { "Delete" }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        @if not_editable.is_none() {
+            h2 { "Add a record" }
+
+            (record_form(&domain, None))
+        }
+    }
+    .into_response()
+}
+
+pub(crate) async fn edit(
+    _: AdminSession,
+    cookies: Cookies,
+    Path((domain, dns_record_id)): Path<(String, Uuid)>,
+    State(app_state): State<AppState>,
+) -> Response {
+    let Some(domain) = Domain::find_by_domain(app_state.db(), &domain)
+        .await
+        .unwrap()
+    else {
+        return (StatusCode::NOT_FOUND, "Domain not found").into_response();
+    };
+    let Some(record) = find_record(&app_state, &domain, dns_record_id).await else {
+        return (StatusCode::NOT_FOUND, "DNS record not found").into_response();
+    };
+
+    let flash = Flash::take(&cookies, &app_state);
+
+    html! {
+        h1 { "Edit " (record.record_type) " record for " (record.name) }
+
+        a href=(index_path(&domain.domain)) { "Back to DNS records" }
+
+        @if let Some(flash) = flash {
+            (flash)
+        }
+
+        @if let Some(reason) = not_editable_reason(&domain) {
+            p { (reason) }
+        } @else {
+            (record_form(&domain, Some(&record)))
+        }
+    }
+    .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct DnsRecordForm {
+    name: String,
+    record_type: String,
+    content: String,
+    ttl: i32,
+    prio: Option<String>,
+}
+
+struct ValidRecord {
+    subdomain: String,
+    record_type: String,
+    content: String,
+    ttl: i32,
+    prio: Option<i32>,
+}
+
+impl ValidRecord {
+    fn input(&self) -> RecordInput {
+        RecordInput {
+            subdomain: self.subdomain.clone(),
+            record_type: self.record_type.clone(),
+            content: self.content.clone(),
+            ttl: self.ttl,
+            prio: self.prio,
+        }
+    }
+}
+
+impl DnsRecordForm {
+    fn validate(&self, ttl_limits: TtlLimits) -> Result<ValidRecord, String> {
+        let record_type = self.record_type.trim().to_ascii_uppercase();
+        if !RECORD_TYPES.contains(&record_type.as_str()) {
+            return Err(format!("{record_type} records aren't supported"));
+        }
+
+        let content = self.content.trim();
+        if content.is_empty() {
+            return Err("Content can't be blank".to_string());
+        }
+
+        let ttl = ttl_limits.validate(self.ttl)?;
+
+        let prio = self
+            .prio
+            .as_deref()
+            .map(str::trim)
+            .filter(|prio| !prio.is_empty())
+            .map(|prio| {
+                prio.parse::<u16>()
+                    .map(i32::from)
+                    .map_err(|_| format!("{prio} isn't a valid priority"))
+            })
+            .transpose()?;
+        if prio.is_some() && !PRIO_RECORD_TYPES.contains(&record_type.as_str()) {
+            return Err(format!("{record_type} records don't have a priority"));
+        }
+
+        Ok(ValidRecord {
+            subdomain: self.name.trim().trim_end_matches('.').to_ascii_lowercase(),
+            record_type,
+            content: content.to_string(),
+            ttl,
+            prio,
+        })
+    }
+}
+
+/// Validates the form and checks the domain is one we can edit, flashing the reason if not
+fn validate_change(
+    domain: &Domain,
+    form: &DnsRecordForm,
+    apis: &ApiConfig,
+) -> Result<(Box<dyn DnsHost>, ValidRecord), String> {
+    let host = editable_host(domain, apis)?;
+    let record = form.validate(TtlLimits::for_provider(host.provider()))?;
+
+    Ok((host, record))
+}
+
+pub(crate) async fn create(
+    _: AdminSession,
+    cookies: Cookies,
+    Path(domain): Path<String>,
+    State(app_state): State<AppState>,
+    Form(form): Form<DnsRecordForm>,
+) -> Response {
+    let Some(domain) = Domain::find_by_domain(app_state.db(), &domain)
+        .await
+        .unwrap()
+    else {
+        return (StatusCode::NOT_FOUND, "Domain not found").into_response();
+    };
+    let redirect_back = Redirect::to(&index_path(&domain.domain));
+
+    let (host, valid) = match validate_change(&domain, &form, &app_state.apis) {
+        Ok(validated) => validated,
+        Err(message) => {
+            Flash::error(message).set(&cookies, &app_state);
+            return redirect_back.into_response();
+        }
+    };
+
+    let provider_record_id = match host.create_record(&domain.domain, &valid.input()).await {
+        Ok(id) => id,
+        Err(e) => {
+            Flash::error(format!(
+                "{} couldn't create the record: {e}",
+                host.provider()
+            ))
+            .set(&cookies, &app_state);
+            return redirect_back.into_response();
+        }
+    };
+
+    let name = fqdn(&valid.subdomain, &domain.domain);
+    sqlx::query!(
+        "INSERT INTO DnsRecords
+          (dns_record_id, domain_id, provider, provider_record_id, name, record_type, content, ttl, prio)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+        Uuid::new_v4(),
+        domain.domain_id,
+        host.provider().as_str(),
+        provider_record_id,
+        name,
+        valid.record_type,
+        valid.content,
+        valid.ttl,
+        valid.prio
+    )
+    .execute(app_state.db())
+    .await
+    .unwrap();
+
+    Flash::success(format!("Created {} record for {name}", valid.record_type))
+        .set(&cookies, &app_state);
+
+    redirect_back.into_response()
+}
+
+pub(crate) async fn update(
+    _: AdminSession,
+    cookies: Cookies,
+    Path((domain, dns_record_id)): Path<(String, Uuid)>,
+    State(app_state): State<AppState>,
+    Form(form): Form<DnsRecordForm>,
+) -> Response {
+    let Some(domain) = Domain::find_by_domain(app_state.db(), &domain)
+        .await
+        .unwrap()
+    else {
+        return (StatusCode::NOT_FOUND, "Domain not found").into_response();
+    };
+    let Some(record) = find_record(&app_state, &domain, dns_record_id).await else {
+        return (StatusCode::NOT_FOUND, "DNS record not found").into_response();
+    };
+    let redirect_to_form = Redirect::to(&record_path(&domain.domain, dns_record_id));
+
+    let (host, valid) = match validate_change(&domain, &form, &app_state.apis) {
+        Ok(validated) => validated,
+        Err(message) => {
+            Flash::error(message).set(&cookies, &app_state);
+            return redirect_to_form.into_response();
+        }
+    };
+
+    if let Err(e) = host
+        .update_record(&domain.domain, &record.provider_record_id, &valid.input())
+        .await
+    {
+        Flash::error(format!(
+            "{} couldn't update the record: {e}",
+            host.provider()
+        ))
+        .set(&cookies, &app_state);
+        return redirect_to_form.into_response();
+    }
+
+    let name = fqdn(&valid.subdomain, &domain.domain);
+    sqlx::query!(
+        "UPDATE DnsRecords
+        SET name = $1, record_type = $2, content = $3, ttl = $4, prio = $5, updated_at = NOW()
+        WHERE dns_record_id = $6",
+        name,
+        valid.record_type,
+        valid.content,
+        valid.ttl,
+        valid.prio,
+        dns_record_id
+    )
+    .execute(app_state.db())
+    .await
+    .unwrap();
+
+    Flash::success(format!("Updated {} record for {name}", valid.record_type))
+        .set(&cookies, &app_state);
+
+    Redirect::to(&index_path(&domain.domain)).into_response()
+}
+
+pub(crate) async fn destroy(
+    _: AdminSession,
+    cookies: Cookies,
+    Path((domain, dns_record_id)): Path<(String, Uuid)>,
+    State(app_state): State<AppState>,
+) -> Response {
+    let Some(domain) = Domain::find_by_domain(app_state.db(), &domain)
+        .await
+        .unwrap()
+    else {
+        return (StatusCode::NOT_FOUND, "Domain not found").into_response();
+    };
+    let Some(record) = find_record(&app_state, &domain, dns_record_id).await else {
+        return (StatusCode::NOT_FOUND, "DNS record not found").into_response();
+    };
+    let redirect_back = Redirect::to(&index_path(&domain.domain));
+
+    let host = match editable_host(&domain, &app_state.apis) {
+        Ok(host) => host,
+        Err(message) => {
+            Flash::error(message).set(&cookies, &app_state);
+            return redirect_back.into_response();
+        }
+    };
+
+    if let Err(e) = host
+        .delete_record(&domain.domain, &record.provider_record_id)
+        .await
+    {
+        Flash::error(format!(
+            "{} couldn't delete the record: {e}",
+            host.provider()
+        ))
+        .set(&cookies, &app_state);
+        return redirect_back.into_response();
+    }
+
+    sqlx::query!(
+        "DELETE FROM DnsRecords WHERE dns_record_id = $1",
+        dns_record_id
+    )
+    .execute(app_state.db())
+    .await
+    .unwrap();
+
+    Flash::success(format!(
+        "Deleted {} record for {}",
+        record.record_type, record.name
+    ))
+    .set(&cookies, &app_state);
+
+    redirect_back.into_response()
+}
+
+fn snapshot_from_json(value: Option<serde_json::Value>) -> Option<RecordSnapshot> {
+    value.and_then(|value| serde_json::from_value(value).ok())
+}
+
+/// Timeline of the changes each sync picked up that weren't made through the dashboard
+pub(crate) async fn history(
+    _: AdminSession,
+    Path(domain): Path<String>,
+    State(app_state): State<AppState>,
+) -> Response {
+    let Some(domain) = Domain::find_by_domain(app_state.db(), &domain)
+        .await
+        .unwrap()
+    else {
+        return (StatusCode::NOT_FOUND, "Domain not found").into_response();
+    };
+
+    let last_sync = sqlx::query!(
+        "SELECT synced_at FROM DnsRecordSyncs WHERE domain_id = $1 ORDER BY synced_at DESC LIMIT 1",
+        domain.domain_id
+    )
+    .fetch_optional(app_state.db())
+    .await
+    .unwrap();
+
+    let syncs = sqlx::query!(
+        "SELECT dns_record_sync_id, synced_at FROM DnsRecordSyncs
+        WHERE domain_id = $1 AND change_count > 0
+        ORDER BY synced_at DESC
+        LIMIT 50",
+        domain.domain_id
+    )
+    .fetch_all(app_state.db())
+    .await
+    .unwrap();
+
+    let sync_ids = syncs
+        .iter()
+        .map(|sync| sync.dns_record_sync_id)
+        .collect::<Vec<_>>();
+    let mut changes = HashMap::<Uuid, Vec<_>>::new();
+    for change in sqlx::query!(
+        "SELECT dns_record_sync_id, change_type, previous, current FROM DnsRecordChanges
+        WHERE dns_record_sync_id = ANY($1)
+        ORDER BY created_at",
+        &sync_ids
+    )
+    .fetch_all(app_state.db())
+    .await
+    .unwrap()
+    {
+        changes.entry(change.dns_record_sync_id).or_default().push((
+            change.change_type,
+            snapshot_from_json(change.previous),
+            snapshot_from_json(change.current),
+        ));
+    }
+
+    html! {
+        h1 { "DNS change history for " (domain.domain) }
+
+        a href=(index_path(&domain.domain)) { "Back to DNS records" }
+
+        @match last_sync {
+            Some(sync) => p { "Last synced " (sync.synced_at.format("%Y-%m-%d %H:%M UTC")) },
+            None => p { "DNS records haven't been synced yet" },
+        }
+
+        @if syncs.is_empty() {
+            p { "No changes have been detected since the first sync" }
+        }
+
+        @for sync in &syncs {
+            h2 { (sync.synced_at.format("%Y-%m-%d %H:%M UTC")) }
+
+            ul {
+                @for (change_type, previous, current) in changes.get(&sync.dns_record_sync_id).into_iter().flatten() {
+                    li {
+                        strong { (change_type) }
+                        " "
+                        @match (previous, current) {
+                            (Some(previous), Some(current)) => {
+                                del { (previous) }
+                                " → "
+                                ins { (current) }
+                            }
+                            (Some(previous), None) => del { (previous) },
+                            (None, Some(current)) => ins { (current) },
+                            (None, None) => {}
+                        }
+                    }
+                }
+            }
+        }
+    }
+    .into_response()
+}
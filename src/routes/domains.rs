@@ -1,12 +1,34 @@
-use crate::{auth::AdminSession, AppState};
-use axum::{extract::State, response::IntoResponse};
-use cja::app_state::AppState as _;
-use maud::html;
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    apis::ApiConfig,
+    auth::AdminSession,
+    dns::DnsRecord,
+    dns_hosts::{self, DnsHost},
+    expiry::{alert_windows, expiring_domains},
+    flash::Flash,
+    jobs::{refresh_domain_nameservers::RefreshDomainNameservers, JobRunStatus},
+    markdown,
+    redirects::{host_belongs_to_domain, RedirectRule},
+    registrars::{Registrar, RegistrarKind},
+    routes::tags::{tag_datalist, tag_editor, Tag},
+    AppState,
+};
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    Form,
+};
+use cja::{app_state::AppState as _, jobs::Job};
+use maud::{html, Markup};
+use serde::Deserialize;
+use tower_cookies::Cookies;
 use uuid::Uuid;
 
 #[allow(dead_code)]
-pub(crate) struct PorkbunDomain {
-    pub(crate) porkbun_domain_id: Uuid,
+pub(crate) struct Domain {
+    pub(crate) domain_id: Uuid,
     pub(crate) auto_renew: bool,
     pub(crate) purchase_date: chrono::DateTime<chrono::Utc>,
     pub(crate) domain: String,
@@ -19,6 +41,65 @@ pub(crate) struct PorkbunDomain {
     pub(crate) nameservers: Vec<String>,
     pub(crate) created_at: chrono::DateTime<chrono::Utc>,
     pub(crate) updated_at: chrono::DateTime<chrono::Utc>,
+    pub(crate) registrar: String,
+    /// When the registrar stopped listing the domain, after a transfer out or letting it lapse.
+    /// The row stays so its history does too
+    pub(crate) removed_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// [`DnsProvider::as_str`] for `nameservers`, updated with them so the list can filter on it
+    pub(crate) dns_provider: String,
+    /// Markdown, render with [`markdown::render`]
+    pub(crate) notes: String,
+    /// [`DomainIntent::as_str`], `None` until someone decides
+    pub(crate) intent: Option<String>,
+}
+
+/// Why we're holding on to a domain
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum DomainIntent {
+    ActiveProject,
+    Redirect,
+    Parked,
+    Defensive,
+    LetExpire,
+}
+
+impl DomainIntent {
+    pub(crate) const ALL: [DomainIntent; 5] = [
+        DomainIntent::ActiveProject,
+        DomainIntent::Redirect,
+        DomainIntent::Parked,
+        DomainIntent::Defensive,
+        DomainIntent::LetExpire,
+    ];
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            DomainIntent::ActiveProject => "active_project",
+            DomainIntent::Redirect => "redirect",
+            DomainIntent::Parked => "parked",
+            DomainIntent::Defensive => "defensive",
+            DomainIntent::LetExpire => "let_expire",
+        }
+    }
+
+    pub(crate) fn parse(intent: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|i| i.as_str() == intent)
+            .ok_or_else(|| format!("{intent} isn't a known intent"))
+    }
+}
+
+impl std::fmt::Display for DomainIntent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DomainIntent::ActiveProject => write!(f, "Active project"),
+            DomainIntent::Redirect => write!(f, "Redirect"),
+            DomainIntent::Parked => write!(f, "Parked"),
+            DomainIntent::Defensive => write!(f, "Defensive"),
+            DomainIntent::LetExpire => write!(f, "Let expire"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -32,6 +113,56 @@ pub enum DnsProvider {
     Unknown,
 }
 
+impl DnsProvider {
+    pub(crate) const ALL: [DnsProvider; 6] = [
+        DnsProvider::Porkbun,
+        DnsProvider::Cloudflare,
+        DnsProvider::GoogleDomains,
+        DnsProvider::Vercel,
+        DnsProvider::Route53,
+        DnsProvider::Unknown,
+    ];
+
+    /// Stored alongside synced records so ids from different providers don't collide, and on
+    /// `Domains` for filtering
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            DnsProvider::Porkbun => "porkbun",
+            DnsProvider::Cloudflare => "cloudflare",
+            DnsProvider::GoogleDomains => "google_domains",
+            DnsProvider::Vercel => "vercel",
+            DnsProvider::Route53 => "route53",
+            DnsProvider::Unknown => "unknown",
+        }
+    }
+
+    pub(crate) fn parse(provider: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|p| p.as_str() == provider)
+            .ok_or_else(|| format!("{provider} isn't a known DNS provider"))
+    }
+
+    /// Who answers DNS for a domain with these nameservers, it only counts if all of them agree
+    pub(crate) fn from_nameservers(nameservers: &[String]) -> Self {
+        let all = |matches: fn(&str) -> bool| nameservers.iter().all(|ns| matches(ns));
+
+        if all(|ns| ns.ends_with(".porkbun.com")) {
+            DnsProvider::Porkbun
+        } else if all(|ns| ns.ends_with(".cloudflare.com")) {
+            DnsProvider::Cloudflare
+        } else if all(|ns| ns.ends_with(".googledomains.com")) {
+            DnsProvider::GoogleDomains
+        } else if all(|ns| ns.ends_with(".vercel-dns.com")) {
+            DnsProvider::Vercel
+        } else if all(|ns| ns.contains("awsdns")) {
+            DnsProvider::Route53
+        } else {
+            DnsProvider::Unknown
+        }
+    }
+}
+
 impl std::fmt::Display for DnsProvider {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -45,79 +176,587 @@ impl std::fmt::Display for DnsProvider {
     }
 }
 
-impl PorkbunDomain {
-    pub fn using_porkbun_dns(&self) -> bool {
-        self.nameservers
-            .iter()
-            .all(|ns| ns.ends_with(".porkbun.com"))
+pub(crate) fn domain_path(domain: &str) -> String {
+    format!("/domains/{domain}")
+}
+
+impl Domain {
+    pub(crate) async fn find_by_domain(
+        db: &sqlx::PgPool,
+        domain: &str,
+    ) -> sqlx::Result<Option<Self>> {
+        sqlx::query_as!(Domain, "SELECT * FROM Domains WHERE domain = $1", domain)
+            .fetch_optional(db)
+            .await
+    }
+
+    pub(crate) fn registrar_kind(&self) -> color_eyre::Result<RegistrarKind> {
+        RegistrarKind::parse(&self.registrar).map_err(|e| color_eyre::eyre::eyre!(e))
     }
 
-    pub fn using_cloudflare_dns(&self) -> bool {
-        self.nameservers
-            .iter()
-            .all(|ns| ns.ends_with(".cloudflare.com"))
+    /// The registrar's display name, or the raw column value if it isn't one we know about
+    pub(crate) fn registrar_name(&self) -> String {
+        self.registrar_kind()
+            .map_or_else(|_| self.registrar.clone(), |kind| kind.to_string())
     }
 
-    pub fn using_google_domains_dns(&self) -> bool {
-        self.nameservers
-            .iter()
-            .all(|ns| ns.ends_with(".googledomains.com"))
+    pub(crate) fn registrar(&self, apis: &ApiConfig) -> color_eyre::Result<Box<dyn Registrar>> {
+        self.registrar_kind()?.client(apis)
     }
 
-    pub fn using_vercel_dns(&self) -> bool {
-        self.nameservers
-            .iter()
-            .all(|ns| ns.ends_with(".vercel-dns.com"))
+    /// A client for the provider serving this domain's records, `None` if we can't manage it
+    pub(crate) fn dns_host(
+        &self,
+        apis: &ApiConfig,
+    ) -> color_eyre::Result<Option<Box<dyn DnsHost>>> {
+        dns_hosts::client(self.dns_provider(), apis)
     }
 
-    pub fn using_route53_dns(&self) -> bool {
-        self.nameservers.iter().all(|ns| ns.contains("awsdns"))
+    /// Whole days left until `expire_date`, negative once it has passed
+    pub(crate) fn days_until_expiry(&self) -> i64 {
+        (self.expire_date - chrono::Utc::now()).num_days()
     }
 
     pub fn dns_provider(&self) -> DnsProvider {
-        if self.using_porkbun_dns() {
-            return DnsProvider::Porkbun;
+        DnsProvider::from_nameservers(&self.nameservers)
+    }
+
+    pub(crate) fn intent(&self) -> Option<DomainIntent> {
+        self.intent
+            .as_deref()
+            .and_then(|intent| DomainIntent::parse(intent).ok())
+    }
+
+    /// The first line of the notes as plain text, for places without room for all of them
+    pub(crate) fn notes_summary(&self) -> Option<&str> {
+        self.notes
+            .lines()
+            .map(|line| line.trim().trim_start_matches(['#', '-', '*', '>']).trim())
+            .find(|line| !line.is_empty())
+    }
+}
+
+const DOMAINS_PER_PAGE: i64 = 50;
+
+/// Ten years, past anything a registrar will sell
+const MAX_EXPIRING_WITHIN_DAYS: i32 = 3650;
+
+/// The columns the domains list can be sorted by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum DomainSort {
+    Name,
+    Expiry,
+    Purchased,
+}
+
+impl DomainSort {
+    pub(crate) const ALL: [DomainSort; 3] =
+        [DomainSort::Name, DomainSort::Expiry, DomainSort::Purchased];
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            DomainSort::Name => "name",
+            DomainSort::Expiry => "expiry",
+            DomainSort::Purchased => "purchased",
+        }
+    }
+
+    pub(crate) fn parse(sort: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|s| s.as_str() == sort)
+            .ok_or_else(|| format!("{sort} isn't a column domains can be sorted by"))
+    }
+
+    /// Names read A to Z, dates newest first
+    fn default_ascending(&self) -> bool {
+        matches!(self, DomainSort::Name)
+    }
+}
+
+impl std::fmt::Display for DomainSort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DomainSort::Name => write!(f, "Domain"),
+            DomainSort::Expiry => write!(f, "Expires"),
+            DomainSort::Purchased => write!(f, "Purchased"),
+        }
+    }
+}
+
+/// The query string of the domains list. Everything is a string so an empty form field means
+/// "any" rather than failing to parse
+#[derive(Debug, Clone, Default, Deserialize)]
+pub(crate) struct DomainFilters {
+    #[serde(default)]
+    q: String,
+    #[serde(default)]
+    tld: String,
+    #[serde(default)]
+    dns_provider: String,
+    /// `on` or `off`
+    #[serde(default)]
+    auto_renew: String,
+    /// Days
+    #[serde(default)]
+    expiring_within: String,
+    /// `yes` or `no`
+    #[serde(default)]
+    has_redirect: String,
+    /// A tag name
+    #[serde(default)]
+    tag: String,
+    /// A [`DomainIntent`], or `none` for domains without one
+    #[serde(default)]
+    intent: String,
+    #[serde(default)]
+    sort: String,
+    /// `asc` or `desc`
+    #[serde(default)]
+    dir: String,
+    #[serde(default)]
+    page: String,
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+impl DomainFilters {
+    fn search(&self) -> Option<String> {
+        non_empty(&self.q).map(|q| q.to_lowercase())
+    }
+
+    fn tld(&self) -> Option<String> {
+        non_empty(&self.tld).map(|tld| tld.trim_start_matches('.').to_lowercase())
+    }
+
+    fn dns_provider(&self) -> Option<DnsProvider> {
+        DnsProvider::parse(&self.dns_provider).ok()
+    }
+
+    fn auto_renew(&self) -> Option<bool> {
+        match self.auto_renew.as_str() {
+            "on" => Some(true),
+            "off" => Some(false),
+            _ => None,
         }
+    }
 
-        if self.using_cloudflare_dns() {
-            return DnsProvider::Cloudflare;
+    /// Capped so the interval stays within Postgres' timestamp range
+    fn expiring_within(&self) -> Option<i32> {
+        self.expiring_within
+            .trim()
+            .parse()
+            .ok()
+            .filter(|days| *days >= 0)
+            .map(|days: i32| days.min(MAX_EXPIRING_WITHIN_DAYS))
+    }
+
+    fn has_redirect(&self) -> Option<bool> {
+        match self.has_redirect.as_str() {
+            "yes" => Some(true),
+            "no" => Some(false),
+            _ => None,
         }
+    }
 
-        if self.using_google_domains_dns() {
-            return DnsProvider::GoogleDomains;
+    fn tag(&self) -> Option<String> {
+        non_empty(&self.tag)
+    }
+
+    /// `Some("none")` matches domains nobody has given an intent yet
+    fn intent(&self) -> Option<&'static str> {
+        match self.intent.as_str() {
+            "none" => Some("none"),
+            intent => DomainIntent::parse(intent).ok().map(|i| i.as_str()),
         }
+    }
 
-        if self.using_vercel_dns() {
-            return DnsProvider::Vercel;
+    fn sort(&self) -> DomainSort {
+        DomainSort::parse(&self.sort).unwrap_or(DomainSort::Purchased)
+    }
+
+    fn ascending(&self) -> bool {
+        match self.dir.as_str() {
+            "asc" => true,
+            "desc" => false,
+            _ => self.sort().default_ascending(),
         }
+    }
 
-        if self.using_route53_dns() {
-            return DnsProvider::Route53;
+    fn page(&self) -> i64 {
+        self.page.parse().unwrap_or(1).max(1)
+    }
+
+    fn is_filtered(&self) -> bool {
+        self.search().is_some()
+            || self.tld().is_some()
+            || self.dns_provider().is_some()
+            || self.auto_renew().is_some()
+            || self.expiring_within().is_some()
+            || self.has_redirect().is_some()
+            || self.tag().is_some()
+            || self.intent().is_some()
+    }
+
+    /// A link to the list with these filters, the given sort and page
+    fn url(&self, sort: DomainSort, ascending: bool, page: i64) -> String {
+        let mut url = reqwest::Url::parse("http://domains/domains").unwrap();
+        {
+            let mut query = url.query_pairs_mut();
+            for (key, value) in [
+                ("q", &self.q),
+                ("tld", &self.tld),
+                ("dns_provider", &self.dns_provider),
+                ("auto_renew", &self.auto_renew),
+                ("expiring_within", &self.expiring_within),
+                ("has_redirect", &self.has_redirect),
+                ("tag", &self.tag),
+                ("intent", &self.intent),
+            ] {
+                if !value.trim().is_empty() {
+                    query.append_pair(key, value.trim());
+                }
+            }
+            query.append_pair("sort", sort.as_str());
+            query.append_pair("dir", if ascending { "asc" } else { "desc" });
+            if page > 1 {
+                query.append_pair("page", &page.to_string());
+            }
         }
 
-        DnsProvider::Unknown
+        format!("/domains?{}", url.query().unwrap_or_default())
+    }
+
+    /// A column header that sorts by `sort`, flipping the direction if it's already sorted by it
+    fn sort_header(&self, sort: DomainSort) -> Markup {
+        let current = self.sort() == sort;
+        let ascending = if current {
+            !self.ascending()
+        } else {
+            sort.default_ascending()
+        };
+
+        html! {
+            a href=(self.url(sort, ascending, 1)) {
+                (sort)
+                @if current {
+                    @if self.ascending() { " ▲" } @else { " ▼" }
+                }
+            }
+        }
     }
 }
 
-pub(crate) async fn show(_: AdminSession, State(app_state): State<AppState>) -> impl IntoResponse {
-    let domains = sqlx::query_as!(
-        PorkbunDomain,
-        "SELECT * FROM PorkbunDomains ORDER BY purchase_date DESC"
+/// How many domains match the filters, for the pagination
+async fn count_domains(db: &sqlx::PgPool, filters: &DomainFilters) -> sqlx::Result<i64> {
+    sqlx::query_scalar!(
+        r#"SELECT COUNT(*) AS "count!" FROM Domains d
+        WHERE removed_at IS NULL
+          AND ($1::TEXT IS NULL OR strpos(lower(domain), $1) > 0)
+          AND ($2::TEXT IS NULL OR tld = $2)
+          AND ($3::TEXT IS NULL OR dns_provider = $3)
+          AND ($4::BOOLEAN IS NULL OR auto_renew = $4)
+          AND ($5::INT IS NULL OR expire_date <= NOW() + make_interval(days => $5))
+          AND ($6::BOOLEAN IS NULL OR $6 = EXISTS (
+            SELECT 1 FROM Redirects r WHERE r.host = d.domain OR r.host LIKE '%.' || d.domain
+          ))
+          AND ($7::TEXT IS NULL OR EXISTS (
+            SELECT 1 FROM DomainTags dt JOIN Tags t ON t.tag_id = dt.tag_id
+            WHERE dt.domain_id = d.domain_id AND t.name = $7
+          ))
+          AND ($8::TEXT IS NULL OR intent = $8 OR ($8 = 'none' AND intent IS NULL))"#,
+        filters.search(),
+        filters.tld(),
+        filters.dns_provider().map(|p| p.as_str()),
+        filters.auto_renew(),
+        filters.expiring_within(),
+        filters.has_redirect(),
+        filters.tag(),
+        filters.intent(),
+    )
+    .fetch_one(db)
+    .await
+}
+
+/// One page of the domains matching the filters, in the order they asked for
+async fn find_domains(
+    db: &sqlx::PgPool,
+    filters: &DomainFilters,
+    page: i64,
+) -> sqlx::Result<Vec<Domain>> {
+    let sort = filters.sort();
+    let ascending = filters.ascending();
+    // Pages past the end are clamped by the caller, this only guards against overflow
+    let offset = (page - 1).checked_mul(DOMAINS_PER_PAGE).unwrap_or(i64::MAX);
+
+    sqlx::query_as!(
+        Domain,
+        "SELECT * FROM Domains d
+        WHERE removed_at IS NULL
+          AND ($1::TEXT IS NULL OR strpos(lower(domain), $1) > 0)
+          AND ($2::TEXT IS NULL OR tld = $2)
+          AND ($3::TEXT IS NULL OR dns_provider = $3)
+          AND ($4::BOOLEAN IS NULL OR auto_renew = $4)
+          AND ($5::INT IS NULL OR expire_date <= NOW() + make_interval(days => $5))
+          AND ($6::BOOLEAN IS NULL OR $6 = EXISTS (
+            SELECT 1 FROM Redirects r WHERE r.host = d.domain OR r.host LIKE '%.' || d.domain
+          ))
+          AND ($11::TEXT IS NULL OR EXISTS (
+            SELECT 1 FROM DomainTags dt JOIN Tags t ON t.tag_id = dt.tag_id
+            WHERE dt.domain_id = d.domain_id AND t.name = $11
+          ))
+          AND ($12::TEXT IS NULL OR intent = $12 OR ($12 = 'none' AND intent IS NULL))
+        ORDER BY
+          CASE WHEN $7::TEXT = 'name' AND $8::BOOLEAN THEN domain END ASC,
+          CASE WHEN $7 = 'name' AND NOT $8 THEN domain END DESC,
+          CASE WHEN $7 = 'expiry' AND $8 THEN expire_date END ASC,
+          CASE WHEN $7 = 'expiry' AND NOT $8 THEN expire_date END DESC,
+          CASE WHEN $7 = 'purchased' AND $8 THEN purchase_date END ASC,
+          CASE WHEN $7 = 'purchased' AND NOT $8 THEN purchase_date END DESC,
+          domain
+        LIMIT $9 OFFSET $10",
+        filters.search(),
+        filters.tld(),
+        filters.dns_provider().map(|p| p.as_str()),
+        filters.auto_renew(),
+        filters.expiring_within(),
+        filters.has_redirect(),
+        sort.as_str(),
+        ascending,
+        DOMAINS_PER_PAGE,
+        offset,
+        filters.tag(),
+        filters.intent()
+    )
+    .fetch_all(db)
+    .await
+}
+
+pub(crate) async fn show(
+    _: AdminSession,
+    cookies: Cookies,
+    Query(filters): Query<DomainFilters>,
+    State(app_state): State<AppState>,
+) -> impl IntoResponse {
+    let sort = filters.sort();
+    let ascending = filters.ascending();
+
+    let total = count_domains(app_state.db(), &filters).await.unwrap();
+    let last_page = ((total + DOMAINS_PER_PAGE - 1) / DOMAINS_PER_PAGE).max(1);
+    let page = filters.page().min(last_page);
+    let first_shown = (page - 1) * DOMAINS_PER_PAGE + 1;
+
+    let domains = find_domains(app_state.db(), &filters, page).await.unwrap();
+
+    let tlds = sqlx::query_scalar!(
+        "SELECT DISTINCT tld FROM Domains WHERE removed_at IS NULL ORDER BY tld"
+    )
+    .fetch_all(app_state.db())
+    .await
+    .unwrap();
+
+    let all_tags = Tag::all(app_state.db()).await.unwrap();
+    let domain_ids = domains.iter().map(|d| d.domain_id).collect::<Vec<_>>();
+    let tags = Tag::for_domains(app_state.db(), &domain_ids).await.unwrap();
+    let return_to = filters.url(sort, ascending, page);
+
+    let former_domains = sqlx::query_as!(
+        Domain,
+        "SELECT * FROM Domains WHERE removed_at IS NOT NULL ORDER BY removed_at DESC"
     )
     .fetch_all(app_state.db())
     .await
     .unwrap();
 
+    let all_redirects = sqlx::query_as!(
+        RedirectRule,
+        "SELECT * FROM Redirects ORDER BY priority DESC, created_at"
+    )
+    .fetch_all(app_state.db())
+    .await
+    .unwrap();
+    let redirects = domains
+        .iter()
+        .map(|domain| {
+            let rules = all_redirects
+                .iter()
+                .filter(|rule| host_belongs_to_domain(&rule.host, &domain.domain))
+                .collect::<Vec<_>>();
+
+            (domain.domain.clone(), rules)
+        })
+        .collect::<HashMap<_, _>>();
+
+    let changed_dns = sqlx::query_scalar!(
+        "SELECT domain_id FROM (
+          SELECT DISTINCT ON (domain_id) domain_id, change_count
+          FROM DnsRecordSyncs
+          ORDER BY domain_id, synced_at DESC
+        ) latest
+        WHERE change_count > 0"
+    )
+    .fetch_all(app_state.db())
+    .await
+    .unwrap()
+    .into_iter()
+    .collect::<HashSet<_>>();
+
+    let expiring = expiring_domains(
+        app_state.db(),
+        alert_windows().first().copied().unwrap_or_default(),
+    )
+    .await
+    .unwrap();
+
+    let flash = Flash::take(&cookies, &app_state);
+
     html! {
         h1 { "Domains" }
 
-        h2 { "Porkbun Domains" }
+        @if let Some(flash) = flash {
+            (flash)
+        }
+
+        @if !expiring.is_empty() {
+            h2 { "Expiring soon" }
+
+            a href="/calendar" { "Subscribe to the expiry calendar" }
+
+            table {
+                thead {
+                    tr {
+                        th { "Domain" }
+                        th { "Expires" }
+                        th { "Days left" }
+                        th { "Auto-renew" }
+                    }
+                }
+
+                tbody {
+                    @for domain in &expiring {
+                        tr {
+                            td { a href=(domain_path(&domain.domain)) { (domain.domain) } }
+                            td { (domain.expire_date.format("%Y-%m-%d")) }
+                            td { (domain.days_until_expiry()) }
+                            td {
+                                @if domain.auto_renew {
+                                    "On"
+                                } @else {
+                                    strong { "Off" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        h2 { "All Domains" }
+
+        a href="/tags" { "Tags" }
+
+        form method="get" action="/domains" {
+            label {
+                "Search"
+                input type="search" name="q" value=(filters.q);
+            }
+
+            label {
+                "TLD"
+                select name="tld" {
+                    option value="" { "Any" }
+                    @for tld in &tlds {
+                        option value=(tld) selected[filters.tld().as_ref() == Some(tld)] { "." (tld) }
+                    }
+                }
+            }
+
+            label {
+                "DNS provider"
+                select name="dns_provider" {
+                    option value="" { "Any" }
+                    @for provider in DnsProvider::ALL {
+                        option value=(provider.as_str()) selected[filters.dns_provider() == Some(provider)] { (provider) }
+                    }
+                }
+            }
+
+            label {
+                "Auto-renew"
+                select name="auto_renew" {
+                    option value="" { "Any" }
+                    option value="on" selected[filters.auto_renew() == Some(true)] { "On" }
+                    option value="off" selected[filters.auto_renew() == Some(false)] { "Off" }
+                }
+            }
+
+            label {
+                "Expiring within (days)"
+                input type="number" name="expiring_within" min="0" value=(filters.expiring_within);
+            }
+
+            label {
+                "Tag"
+                select name="tag" {
+                    option value="" { "Any" }
+                    @for tag in &all_tags {
+                        option value=(tag.name) selected[filters.tag().as_ref() == Some(&tag.name)] { (tag.name) }
+                    }
+                }
+            }
+
+            label {
+                "Intent"
+                select name="intent" {
+                    option value="" { "Any" }
+                    @for intent in DomainIntent::ALL {
+                        option value=(intent.as_str()) selected[filters.intent() == Some(intent.as_str())] { (intent) }
+                    }
+                    option value="none" selected[filters.intent() == Some("none")] { "Not set" }
+                }
+            }
+
+            label {
+                "Redirect"
+                select name="has_redirect" {
+                    option value="" { "Any" }
+                    option value="yes" selected[filters.has_redirect() == Some(true)] { "Has a redirect" }
+                    option value="no" selected[filters.has_redirect() == Some(false)] { "No redirect" }
+                }
+            }
+
+            input type="hidden" name="sort" value=(sort.as_str());
+            input type="hidden" name="dir" value=(if ascending { "asc" } else { "desc" });
+
+            button type="submit" { "Filter" }
+            @if filters.is_filtered() {
+                " "
+                a href="/domains" { "Clear" }
+            }
+        }
+
+        p {
+            @if total == 0 {
+                "No domains match"
+            } @else {
+                "Showing " (first_shown) "–" (first_shown + domains.len() as i64 - 1) " of " (total)
+            }
+        }
 
         table {
             thead {
                 tr {
-                    th { "Domain" }
+                    th { (filters.sort_header(DomainSort::Name)) }
+                    th { "Intent" }
+                    th { "Registrar" }
+                    th { (filters.sort_header(DomainSort::Purchased)) }
+                    th { (filters.sort_header(DomainSort::Expiry)) }
                     th { "DNS Provider" }
+                    th { "Redirect" }
+                    th { "Tags" }
                 }
             }
 
@@ -125,17 +764,691 @@ pub(crate) async fn show(_: AdminSession, State(app_state): State<AppState>) ->
                 @for domain in domains {
                     @let dns_provider = domain.dns_provider();
                     tr {
-                        td { (domain.domain) }
+                        td { a href=(domain_path(&domain.domain)) { (domain.domain) } }
+                        td {
+                            @if let Some(intent) = domain.intent() {
+                                (intent)
+                            }
+                            @if let Some(summary) = domain.notes_summary() {
+                                br;
+                                small { (summary) }
+                            }
+                        }
+                        td { (domain.registrar_name()) }
+                        td { (domain.purchase_date.format("%Y-%m-%d")) }
+                        td { (domain.expire_date.format("%Y-%m-%d")) }
                         td {
                             (dns_provider)
                             br;
                             @if dns_provider == DnsProvider::Unknown {
                                 (domain.nameservers.join(", "))
                             }
+                            br;
+                            a href={ "/domains/" (domain.domain) "/dns" } { "DNS records" }
+                            " "
+                            a href={ "/domains/" (domain.domain) "/nameservers" } { "Change nameservers" }
+                            @if changed_dns.contains(&domain.domain_id) {
+                                " "
+                                a.badge href={ "/domains/" (domain.domain) "/dns/history" } { "Changed since last sync" }
+                            }
                          }
+                        td {
+                            @match redirects.get(&domain.domain).map(Vec::as_slice) {
+                                Some([]) | None => {}
+                                Some([rule]) => {
+                                    (rule.target_url)
+                                    @if !rule.enabled {
+                                        " (disabled)"
+                                    }
+                                    br;
+                                }
+                                Some(rules) => {
+                                    (rules.len()) " rules"
+                                    br;
+                                }
+                            }
+                            a href={ "/domains/" (domain.domain) "/redirects" } { "Manage redirects" }
+                        }
+                        td {
+                            (tag_editor(&domain.domain, tags.get(&domain.domain_id).map(Vec::as_slice).unwrap_or_default(), &return_to))
+                        }
                     }
                 }
             }
         }
+
+        (tag_datalist(&all_tags))
+
+        @if last_page > 1 {
+            nav {
+                @if page > 1 {
+                    a href=(filters.url(sort, ascending, page - 1)) { "Previous" }
+                    " "
+                }
+                "Page " (page) " of " (last_page)
+                @if page < last_page {
+                    " "
+                    a href=(filters.url(sort, ascending, page + 1)) { "Next" }
+                }
+            }
+        }
+
+        @if !former_domains.is_empty() {
+            h2 { "Former domains" }
+
+            p { "No longer listed by their registrar, after a transfer out or being left to expire. They come back here if the registrar lists them again." }
+
+            table {
+                thead {
+                    tr {
+                        th { "Domain" }
+                        th { "Registrar" }
+                        th { "Expiry" }
+                        th { "Removed" }
+                        th { "History" }
+                    }
+                }
+
+                tbody {
+                    @for domain in former_domains {
+                        tr {
+                            td { a href=(domain_path(&domain.domain)) { (domain.domain) } }
+                            td { (domain.registrar_name()) }
+                            td { (domain.expire_date.format("%Y-%m-%d")) }
+                            td {
+                                @if let Some(removed_at) = domain.removed_at {
+                                    (removed_at.format("%Y-%m-%d"))
+                                }
+                            }
+                            td {
+                                a href={ "/domains/" (domain.domain) "/dns/history" } { "DNS history" }
+                                " "
+                                a href={ "/domains/" (domain.domain) "/redirects" } { "Redirects" }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "Yes"
+    } else {
+        "No"
+    }
+}
+
+pub(crate) async fn detail(
+    _: AdminSession,
+    cookies: Cookies,
+    Path(domain): Path<String>,
+    State(app_state): State<AppState>,
+) -> Response {
+    let Some(domain) = Domain::find_by_domain(app_state.db(), &domain)
+        .await
+        .unwrap()
+    else {
+        return (StatusCode::NOT_FOUND, "Domain not found").into_response();
+    };
+
+    let records = sqlx::query_as!(
+        DnsRecord,
+        "SELECT * FROM DnsRecords WHERE domain_id = $1 ORDER BY name, record_type, content",
+        domain.domain_id
+    )
+    .fetch_all(app_state.db())
+    .await
+    .unwrap();
+
+    let last_sync = sqlx::query_scalar!(
+        "SELECT synced_at FROM DnsRecordSyncs WHERE domain_id = $1 ORDER BY synced_at DESC LIMIT 1",
+        domain.domain_id
+    )
+    .fetch_optional(app_state.db())
+    .await
+    .unwrap();
+
+    let redirects = sqlx::query_as!(
+        RedirectRule,
+        "SELECT * FROM Redirects
+        WHERE enabled AND (host = $1 OR host LIKE '%.' || $1)
+        ORDER BY host, priority DESC, created_at",
+        domain.domain
+    )
+    .fetch_all(app_state.db())
+    .await
+    .unwrap();
+
+    let runs = sqlx::query!(
+        "SELECT job_name, status, error, started_at, finished_at FROM DomainJobRuns
+        WHERE domain_id = $1
+        ORDER BY finished_at DESC
+        LIMIT 25",
+        domain.domain_id
+    )
+    .fetch_all(app_state.db())
+    .await
+    .unwrap();
+    let errors = runs
+        .iter()
+        .filter(|run| JobRunStatus::parse(&run.status) == Ok(JobRunStatus::Failed))
+        .take(5)
+        .collect::<Vec<_>>();
+
+    let queued = sqlx::query!(
+        "SELECT name, run_at, locked_at FROM Jobs
+        WHERE payload->>'domain_id' = $1
+        ORDER BY run_at",
+        domain.domain_id.to_string()
+    )
+    .fetch_all(app_state.db())
+    .await
+    .unwrap();
+
+    let all_tags = Tag::all(app_state.db()).await.unwrap();
+    let tags = Tag::for_domains(app_state.db(), &[domain.domain_id])
+        .await
+        .unwrap()
+        .remove(&domain.domain_id)
+        .unwrap_or_default();
+
+    let flash = Flash::take(&cookies, &app_state);
+    let dns_provider = domain.dns_provider();
+    let path = domain_path(&domain.domain);
+
+    html! {
+        h1 { (domain.domain) }
+
+        a href="/domains" { "Back to Domains" }
+
+        @if let Some(flash) = flash {
+            (flash)
+        }
+
+        @if let Some(removed_at) = domain.removed_at {
+            p {
+                strong { (domain.registrar_name()) " stopped listing this domain on " (removed_at.format("%Y-%m-%d")) "." }
+                " It's kept here for its history but no longer synced."
+            }
+        }
+
+        nav {
+            a href={ (path) "/dns" } { "DNS records" }
+            " | "
+            a href={ (path) "/dns/history" } { "DNS history" }
+            " | "
+            a href={ (path) "/nameservers" } { "Change nameservers" }
+            " | "
+            a href={ (path) "/redirects" } { "Redirects" }
+        }
+
+        @if domain.removed_at.is_none() {
+            form method="post" action={ (path) "/refresh" } {
+                button type="submit" { "Refresh now" }
+            }
+        }
+
+        h2 { "Purpose" }
+
+        @if let Some(intent) = domain.intent() {
+            p { strong { (intent) } }
+        }
+        @if domain.notes.trim().is_empty() {
+            p { "No notes yet" }
+        } @else {
+            div.notes { (markdown::render(&domain.notes)) }
+        }
+
+        details {
+            summary { "Edit intent and notes" }
+
+            form method="post" action={ (path) "/notes" } {
+                label {
+                    "Intent"
+                    select name="intent" {
+                        option value="" { "Not set" }
+                        @for intent in DomainIntent::ALL {
+                            option value=(intent.as_str()) selected[domain.intent() == Some(intent)] { (intent) }
+                        }
+                    }
+                }
+
+                label {
+                    "Notes (markdown)"
+                    textarea name="notes" rows="8" { (domain.notes) }
+                }
+
+                button type="submit" { "Save" }
+            }
+        }
+
+        h2 { "Tags" }
+
+        (tag_editor(&domain.domain, &tags, &path))
+        (tag_datalist(&all_tags))
+
+        h2 { "Registration" }
+
+        table {
+            tbody {
+                tr { th { "Registrar" } td { (domain.registrar_name()) } }
+                tr { th { "Status" } td { (domain.status.as_deref().unwrap_or("Unknown")) } }
+                tr { th { "Purchased" } td { (domain.purchase_date.format("%Y-%m-%d")) } }
+                tr {
+                    th { "Expires" }
+                    td { (domain.expire_date.format("%Y-%m-%d")) " (" (domain.days_until_expiry()) " days)" }
+                }
+                tr { th { "Auto-renew" } td { (yes_no(domain.auto_renew)) } }
+                tr { th { "Security lock" } td { (yes_no(domain.security_lock)) } }
+                tr { th { "WHOIS privacy" } td { (yes_no(domain.whois_privacy)) } }
+                tr { th { "Not local" } td { (yes_no(domain.not_local)) } }
+                tr { th { "Last updated" } td { (domain.updated_at.format("%Y-%m-%d %H:%M UTC")) } }
+            }
+        }
+
+        h2 { "Nameservers" }
+
+        p { "DNS is hosted by " (dns_provider) "." }
+
+        @if domain.nameservers.is_empty() {
+            p { "Nameservers haven't been synced yet" }
+        } @else {
+            ul {
+                @for ns in &domain.nameservers {
+                    li { code { (ns) } }
+                }
+            }
+        }
+
+        h2 { "DNS records" }
+
+        @match last_sync {
+            Some(synced_at) => p { "Last synced " (synced_at.format("%Y-%m-%d %H:%M UTC")) },
+            None => p { "DNS records haven't been synced yet" },
+        }
+
+        @if !records.is_empty() {
+            table {
+                thead {
+                    tr {
+                        th { "Name" }
+                        th { "Type" }
+                        th { "Content" }
+                        th { "TTL" }
+                        th { "Priority" }
+                    }
+                }
+
+                tbody {
+                    @for record in &records {
+                        tr {
+                            td { (record.name) }
+                            td { (record.record_type) }
+                            td { (record.content) }
+                            td { (record.ttl) }
+                            td {
+                                @if let Some(prio) = record.prio {
+                                    (prio)
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        h2 { "Active redirects" }
+
+        @if redirects.is_empty() {
+            p { "No redirects are enabled" }
+        } @else {
+            table {
+                thead {
+                    tr {
+                        th { "Host" }
+                        th { "Path" }
+                        th { "Target" }
+                        th { "Status" }
+                    }
+                }
+
+                tbody {
+                    @for rule in &redirects {
+                        tr {
+                            td { (rule.host) }
+                            td { code { (rule.path_pattern) } }
+                            td { (rule.target_url) }
+                            td { (rule.status_code) }
+                        }
+                    }
+                }
+            }
+        }
+
+        h2 { "Recent sync errors" }
+
+        @if errors.is_empty() {
+            p { "No recent errors" }
+        } @else {
+            ul {
+                @for run in &errors {
+                    li {
+                        (run.finished_at.format("%Y-%m-%d %H:%M UTC")) " " (run.job_name) ": "
+                        code { (run.error.as_deref().unwrap_or_default()) }
+                    }
+                }
+            }
+        }
+
+        h2 { "Jobs" }
+
+        @if !queued.is_empty() {
+            h3 { "Queued" }
+
+            ul {
+                @for job in &queued {
+                    li {
+                        (job.name)
+                        @if job.locked_at.is_some() {
+                            " running now"
+                        } @else {
+                            " at " (job.run_at.format("%Y-%m-%d %H:%M:%S UTC"))
+                        }
+                    }
+                }
+            }
+        }
+
+        @if runs.is_empty() {
+            p { "No jobs have run for this domain yet" }
+        } @else {
+            table {
+                thead {
+                    tr {
+                        th { "Job" }
+                        th { "Result" }
+                        th { "Started" }
+                        th { "Took" }
+                    }
+                }
+
+                tbody {
+                    @for run in &runs {
+                        tr {
+                            td { (run.job_name) }
+                            td {
+                                @match JobRunStatus::parse(&run.status) {
+                                    Ok(status) => (status),
+                                    Err(_) => (run.status),
+                                }
+                            }
+                            td { (run.started_at.format("%Y-%m-%d %H:%M:%S UTC")) }
+                            td { ((run.finished_at - run.started_at).num_milliseconds()) "ms" }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    .into_response()
+}
+
+/// Queues a nameserver and DNS record sync for the domain straight away
+pub(crate) async fn refresh(
+    _: AdminSession,
+    cookies: Cookies,
+    Path(domain): Path<String>,
+    State(app_state): State<AppState>,
+) -> Response {
+    let Some(domain) = Domain::find_by_domain(app_state.db(), &domain)
+        .await
+        .unwrap()
+    else {
+        return (StatusCode::NOT_FOUND, "Domain not found").into_response();
+    };
+
+    // The nameserver refresh queues the DNS record sync once it knows the current provider
+    RefreshDomainNameservers::new(domain.domain_id)
+        .enqueue(
+            app_state.clone(),
+            "Refreshed from the dashboard".to_string(),
+        )
+        .await
+        .unwrap();
+
+    Flash::success("Queued a refresh of the nameservers and DNS records").set(&cookies, &app_state);
+
+    Redirect::to(&domain_path(&domain.domain)).into_response()
+}
+
+/// Plenty for a few paragraphs, but not a place to paste whole documents
+const MAX_NOTES_LEN: usize = 10_000;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct NotesForm {
+    #[serde(default)]
+    intent: String,
+    #[serde(default)]
+    notes: String,
+}
+
+impl NotesForm {
+    fn intent(&self) -> Result<Option<DomainIntent>, String> {
+        match self.intent.trim() {
+            "" => Ok(None),
+            intent => DomainIntent::parse(intent).map(Some),
+        }
+    }
+}
+
+pub(crate) async fn update_notes(
+    _: AdminSession,
+    cookies: Cookies,
+    Path(domain): Path<String>,
+    State(app_state): State<AppState>,
+    Form(form): Form<NotesForm>,
+) -> Response {
+    let Some(domain) = Domain::find_by_domain(app_state.db(), &domain)
+        .await
+        .unwrap()
+    else {
+        return (StatusCode::NOT_FOUND, "Domain not found").into_response();
+    };
+
+    let notes = form.notes.trim();
+    match form.intent() {
+        Ok(_) if notes.chars().count() > MAX_NOTES_LEN => {
+            Flash::error(format!("Notes can be at most {MAX_NOTES_LEN} characters"))
+        }
+        Ok(intent) => {
+            sqlx::query!(
+                "UPDATE Domains SET intent = $1, notes = $2 WHERE domain_id = $3",
+                intent.map(|i| i.as_str()),
+                notes,
+                domain.domain_id
+            )
+            .execute(app_state.db())
+            .await
+            .unwrap();
+
+            Flash::success("Saved the intent and notes")
+        }
+        Err(message) => Flash::error(message),
+    }
+    .set(&cookies, &app_state);
+
+    Redirect::to(&domain_path(&domain.domain)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+
+    async fn insert_domain(
+        pool: &PgPool,
+        domain: &str,
+        expires_in_days: i32,
+        auto_renew: bool,
+    ) -> Uuid {
+        let domain_id = Uuid::new_v4();
+        let tld = domain.rsplit('.').next().unwrap();
+        sqlx::query!(
+            "INSERT INTO Domains
+              (domain_id, auto_renew, purchase_date, domain, expire_date, not_local, security_lock, status, tld, whois_privacy, registrar)
+            VALUES ($1, $2, NOW() - make_interval(days => $3), $4, NOW() + make_interval(days => $3), false, false, NULL, $5, false, 'porkbun')",
+            domain_id,
+            auto_renew,
+            expires_in_days,
+            domain,
+            tld
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+
+        domain_id
+    }
+
+    /// a.dev expires first and was bought last, c.com the other way around
+    async fn seed(pool: &PgPool) {
+        let a = insert_domain(pool, "a.dev", 10, false).await;
+        insert_domain(pool, "b.com", 100, true).await;
+        insert_domain(pool, "c.com", 1000, true).await;
+
+        sqlx::query!(
+            "INSERT INTO Redirects (redirect_id, host, target_url) VALUES ($1, 'www.b.com', 'https://a.dev/')",
+            Uuid::new_v4()
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+
+        let tag_id = Uuid::new_v4();
+        sqlx::query!(
+            "INSERT INTO Tags (tag_id, name) VALUES ($1, 'client')",
+            tag_id
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+        sqlx::query!(
+            "INSERT INTO DomainTags (domain_id, tag_id) VALUES ($1, $2)",
+            a,
+            tag_id
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+        sqlx::query!(
+            "UPDATE Domains SET intent = 'parked' WHERE domain_id = $1",
+            a
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    fn filters(query: &str) -> DomainFilters {
+        let uri = format!("/domains?{query}").parse().unwrap();
+        Query::try_from_uri(&uri).unwrap().0
+    }
+
+    async fn names(pool: &PgPool, query: &str) -> Vec<String> {
+        let filters = filters(query);
+        find_domains(pool, &filters, filters.page())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|d| d.domain)
+            .collect()
+    }
+
+    #[sqlx::test]
+    async fn filters_domains(pool: PgPool) {
+        seed(&pool).await;
+
+        assert_eq!(names(&pool, "").await, ["a.dev", "b.com", "c.com"]);
+        assert_eq!(names(&pool, "q=B.C").await, ["b.com"]);
+        assert_eq!(names(&pool, "tld=.com").await, ["b.com", "c.com"]);
+        assert_eq!(names(&pool, "auto_renew=off").await, ["a.dev"]);
+        assert_eq!(
+            names(&pool, "expiring_within=200").await,
+            ["a.dev", "b.com"]
+        );
+        assert_eq!(names(&pool, "has_redirect=yes").await, ["b.com"]);
+        assert_eq!(names(&pool, "has_redirect=no").await, ["a.dev", "c.com"]);
+        assert_eq!(names(&pool, "tag=client").await, ["a.dev"]);
+        assert_eq!(names(&pool, "intent=parked").await, ["a.dev"]);
+        assert_eq!(names(&pool, "intent=none").await, ["b.com", "c.com"]);
+        assert_eq!(
+            names(&pool, "tld=com&auto_renew=off").await,
+            Vec::<String>::new()
+        );
+
+        assert_eq!(count_domains(&pool, &filters("tld=com")).await.unwrap(), 2);
+    }
+
+    #[sqlx::test]
+    async fn sorts_domains(pool: PgPool) {
+        seed(&pool).await;
+
+        assert_eq!(
+            names(&pool, "sort=name&dir=desc").await,
+            ["c.com", "b.com", "a.dev"]
+        );
+        assert_eq!(
+            names(&pool, "sort=expiry").await,
+            ["c.com", "b.com", "a.dev"]
+        );
+        assert_eq!(
+            names(&pool, "sort=expiry&dir=asc").await,
+            ["a.dev", "b.com", "c.com"]
+        );
+        // Newest purchase first by default
+        assert_eq!(
+            names(&pool, "sort=purchased").await,
+            ["a.dev", "b.com", "c.com"]
+        );
+    }
+
+    #[sqlx::test]
+    async fn paginates_domains(pool: PgPool) {
+        for i in 0..DOMAINS_PER_PAGE + 5 {
+            insert_domain(&pool, &format!("domain-{i:03}.com"), 30, true).await;
+        }
+
+        assert_eq!(
+            names(&pool, "sort=name").await.len(),
+            DOMAINS_PER_PAGE as usize
+        );
+        assert_eq!(
+            names(&pool, "sort=name&page=2").await,
+            (DOMAINS_PER_PAGE..DOMAINS_PER_PAGE + 5)
+                .map(|i| format!("domain-{i:03}.com"))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[sqlx::test]
+    async fn survives_out_of_range_query_strings(pool: PgPool) {
+        seed(&pool).await;
+
+        assert_eq!(
+            names(&pool, "page=999999999999999999").await,
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            names(&pool, "expiring_within=99999999").await,
+            ["a.dev", "b.com", "c.com"]
+        );
+        assert_eq!(
+            filters("expiring_within=99999999").expiring_within(),
+            Some(MAX_EXPIRING_WITHIN_DAYS)
+        );
     }
 }
@@ -0,0 +1,385 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    Form,
+};
+use cja::{app_state::AppState as _, jobs::Job};
+use maud::html;
+use serde::Deserialize;
+use tower_cookies::Cookies;
+
+use crate::{
+    apis::{porkbun, ApiConfig},
+    auth::AdminSession,
+    flash::Flash,
+    jobs::refresh_domain_nameservers::RefreshDomainNameservers,
+    routes::domains::{domain_path, Domain},
+    AppState,
+};
+
+fn nameservers_path(domain: &str) -> String {
+    format!("/domains/{domain}/nameservers")
+}
+
+/// A ready made set of nameservers to pick instead of typing them out
+struct Preset {
+    value: &'static str,
+    label: String,
+    nameservers: Vec<String>,
+}
+
+/// The Porkbun defaults, plus the nameservers Cloudflare assigned if the domain has a zone there.
+/// Also returns why there is no Cloudflare preset, if there isn't one
+async fn presets(domain: &Domain, apis: &ApiConfig) -> (Vec<Preset>, Option<String>) {
+    let mut presets = vec![Preset {
+        value: "porkbun",
+        label: "Porkbun DNS".to_string(),
+        nameservers: porkbun::DEFAULT_NAMESERVERS.map(String::from).to_vec(),
+    }];
+
+    let cloudflare = async {
+        let Some(client) = &apis.cloudflare else {
+            return Err(
+                "Cloudflare isn't configured, set CLOUDFLARE_API_TOKEN to use a Cloudflare zone"
+                    .to_string(),
+            );
+        };
+
+        client
+            .list_zones(Some(&domain.domain))
+            .await
+            .map_err(|e| format!("Couldn't look up the Cloudflare zone: {e}"))?
+            .into_iter()
+            .find(|zone| zone.name == domain.domain && !zone.name_servers.is_empty())
+            .ok_or_else(|| format!("There is no Cloudflare zone for {}", domain.domain))
+    }
+    .await;
+
+    match cloudflare {
+        Ok(zone) => {
+            presets.push(Preset {
+                value: "cloudflare",
+                label: format!("Cloudflare zone ({})", zone.status),
+                nameservers: zone.name_servers,
+            });
+            (presets, None)
+        }
+        Err(reason) => (presets, Some(reason)),
+    }
+}
+
+/// Splits on whitespace and commas, lowercasing and dropping trailing dots
+fn parse_nameservers(input: &str) -> Result<Vec<String>, String> {
+    let mut nameservers: Vec<String> = vec![];
+
+    for ns in input
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|ns| !ns.is_empty())
+    {
+        let ns = ns.trim_end_matches('.').to_ascii_lowercase();
+        let valid = ns.contains('.')
+            && ns.split('.').all(|label| {
+                !label.is_empty() && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            });
+        if !valid {
+            return Err(format!("{ns} isn't a valid nameserver"));
+        }
+
+        if !nameservers.contains(&ns) {
+            nameservers.push(ns);
+        }
+    }
+
+    if nameservers.len() < 2 {
+        return Err("Enter at least two nameservers".to_string());
+    }
+
+    Ok(nameservers)
+}
+
+/// Whether two lists name the same servers, ignoring order and case
+fn same_nameservers(a: &[String], b: &[String]) -> bool {
+    let normalize = |list: &[String]| {
+        let mut list = list
+            .iter()
+            .map(|ns| ns.trim_end_matches('.').to_ascii_lowercase())
+            .collect::<Vec<_>>();
+        list.sort();
+        list
+    };
+
+    normalize(a) == normalize(b)
+}
+
+pub(crate) async fn edit(
+    _: AdminSession,
+    cookies: Cookies,
+    Path(domain): Path<String>,
+    State(app_state): State<AppState>,
+) -> Response {
+    let Some(domain) = Domain::find_by_domain(app_state.db(), &domain)
+        .await
+        .unwrap()
+    else {
+        return (StatusCode::NOT_FOUND, "Domain not found").into_response();
+    };
+
+    let (presets, no_cloudflare) = presets(&domain, &app_state.apis).await;
+    let flash = Flash::take(&cookies, &app_state);
+
+    html! {
+        h1 { "Nameservers for " (domain.domain) }
+
+        a href=(domain_path(&domain.domain)) { "Back to " (domain.domain) }
+
+        @if let Some(flash) = flash {
+            (flash)
+        }
+
+        p { "DNS is currently hosted by " (domain.dns_provider()) "." }
+
+        ul {
+            @for ns in &domain.nameservers {
+                li { code { (ns) } }
+            }
+        }
+
+        form method="post" action=(nameservers_path(&domain.domain)) {
+            fieldset {
+                legend { "New nameservers" }
+
+                @for preset in &presets {
+                    label {
+                        input type="radio" name="preset" value=(preset.value) checked[same_nameservers(&preset.nameservers, &domain.nameservers)];
+                        (preset.label) ": " (preset.nameservers.join(", "))
+                    }
+                }
+
+                label {
+                    input type="radio" name="preset" value="custom";
+                    "Custom, one per line"
+                }
+                textarea name="custom" rows="4" {}
+            }
+
+            @if let Some(reason) = no_cloudflare {
+                p { (reason) }
+            }
+
+            button type="submit" { "Review change" }
+        }
+    }
+    .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct NameserversForm {
+    preset: Option<String>,
+    #[serde(default)]
+    custom: String,
+}
+
+pub(crate) async fn confirm(
+    _: AdminSession,
+    cookies: Cookies,
+    Path(domain): Path<String>,
+    State(app_state): State<AppState>,
+    Form(form): Form<NameserversForm>,
+) -> Response {
+    let Some(domain) = Domain::find_by_domain(app_state.db(), &domain)
+        .await
+        .unwrap()
+    else {
+        return (StatusCode::NOT_FOUND, "Domain not found").into_response();
+    };
+
+    let nameservers = match form.preset.as_deref() {
+        Some("custom") => parse_nameservers(&form.custom),
+        Some(value) => presets(&domain, &app_state.apis)
+            .await
+            .0
+            .into_iter()
+            .find(|preset| preset.value == value)
+            .map(|preset| preset.nameservers)
+            .ok_or_else(|| format!("{value} isn't available for {}", domain.domain)),
+        None => Err("Pick the nameservers to switch to".to_string()),
+    };
+    let nameservers = nameservers.and_then(|nameservers| {
+        if same_nameservers(&nameservers, &domain.nameservers) {
+            Err("Those are already the nameservers".to_string())
+        } else {
+            Ok(nameservers)
+        }
+    });
+    let nameservers = match nameservers {
+        Ok(nameservers) => nameservers,
+        Err(message) => {
+            Flash::error(message).set(&cookies, &app_state);
+            return Redirect::to(&nameservers_path(&domain.domain)).into_response();
+        }
+    };
+
+    html! {
+        h1 { "Change nameservers for " (domain.domain) "?" }
+
+        a href=(nameservers_path(&domain.domain)) { "Start over" }
+
+        p {
+            strong { "This changes who answers DNS for " (domain.domain) "." }
+            " Any records that don't exist on the new nameservers stop resolving, and resolvers can keep using the old nameservers for up to a couple of days."
+        }
+
+        table {
+            thead {
+                tr {
+                    th { "Current" }
+                    th { "New" }
+                }
+            }
+            tbody {
+                tr {
+                    td {
+                        @for ns in &domain.nameservers {
+                            del { code { (ns) } }
+                            br;
+                        }
+                    }
+                    td {
+                        @for ns in &nameservers {
+                            ins { code { (ns) } }
+                            br;
+                        }
+                    }
+                }
+            }
+        }
+
+        form method="post" action={ (nameservers_path(&domain.domain)) "/apply" } {
+            input type="hidden" name="nameservers" value=(nameservers.join("\n"));
+            button type="submit" { "Yes, change the nameservers" }
+        }
+    }
+    .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ApplyNameserversForm {
+    nameservers: String,
+}
+
+pub(crate) async fn apply(
+    _: AdminSession,
+    cookies: Cookies,
+    Path(domain): Path<String>,
+    State(app_state): State<AppState>,
+    Form(form): Form<ApplyNameserversForm>,
+) -> Response {
+    let Some(domain) = Domain::find_by_domain(app_state.db(), &domain)
+        .await
+        .unwrap()
+    else {
+        return (StatusCode::NOT_FOUND, "Domain not found").into_response();
+    };
+    let redirect_back = Redirect::to(&nameservers_path(&domain.domain));
+
+    let nameservers = match parse_nameservers(&form.nameservers) {
+        Ok(nameservers) => nameservers,
+        Err(message) => {
+            Flash::error(message).set(&cookies, &app_state);
+            return redirect_back.into_response();
+        }
+    };
+
+    let result = async {
+        domain
+            .registrar(&app_state.apis)?
+            .set_nameservers(&domain.domain, &nameservers)
+            .await
+    }
+    .await;
+    if let Err(e) = result {
+        Flash::error(format!(
+            "{} couldn't change the nameservers: {e}",
+            domain.registrar_name()
+        ))
+        .set(&cookies, &app_state);
+        return redirect_back.into_response();
+    }
+
+    // Read the nameservers back straight away rather than waiting for the next cron run, so
+    // the page reflects what the registrar actually has. This also queues syncing the records
+    // from the new provider
+    let refresh = RefreshDomainNameservers::new(domain.domain_id);
+    if let Err(e) = refresh.run(app_state.clone()).await {
+        Flash::error(format!(
+            "The nameservers were changed but reading them back failed: {e}"
+        ))
+        .set(&cookies, &app_state);
+        return redirect_back.into_response();
+    }
+
+    let updated = Domain::find_by_domain(app_state.db(), &domain.domain)
+        .await
+        .unwrap()
+        .expect("the domain was just loaded");
+    if same_nameservers(&updated.nameservers, &nameservers) {
+        Flash::success(format!(
+            "{} now uses {}",
+            domain.domain,
+            nameservers.join(", ")
+        ))
+    } else {
+        Flash::error(format!(
+            "{} accepted the change but still reports {}",
+            domain.registrar_name(),
+            updated.nameservers.join(", ")
+        ))
+    }
+    .set(&cookies, &app_state);
+
+    redirect_back.into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_nameservers() {
+        assert_eq!(
+            parse_nameservers(
+                "Ada.NS.Cloudflare.com.\nbob.ns.cloudflare.com, ada.ns.cloudflare.com"
+            ),
+            Ok(strings(&["ada.ns.cloudflare.com", "bob.ns.cloudflare.com"]))
+        );
+        assert!(parse_nameservers("ada.ns.cloudflare.com").is_err());
+        assert!(parse_nameservers("ada.ns.cloudflare.com ada.ns.cloudflare.com").is_err());
+        assert!(parse_nameservers("ada.ns.cloudflare.com localhost").is_err());
+        assert!(parse_nameservers("ada.ns.cloudflare.com bob..cloudflare.com").is_err());
+        assert!(parse_nameservers("ada.ns.cloudflare.com bob_ns.cloudflare.com").is_err());
+        assert!(parse_nameservers("").is_err());
+    }
+
+    #[test]
+    fn compares_nameservers_ignoring_order_and_case() {
+        let current = strings(&["ada.ns.cloudflare.com", "bob.ns.cloudflare.com"]);
+
+        assert!(same_nameservers(
+            &current,
+            &strings(&["BOB.ns.cloudflare.com.", "ada.ns.cloudflare.com"])
+        ));
+        assert!(!same_nameservers(
+            &current,
+            &strings(&["ada.ns.cloudflare.com"])
+        ));
+        assert!(!same_nameservers(
+            &current,
+            &strings(&["ada.ns.cloudflare.com", "carl.ns.cloudflare.com"])
+        ));
+    }
+}
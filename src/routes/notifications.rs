@@ -0,0 +1,408 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    Form,
+};
+use cja::app_state::AppState as _;
+use maud::{html, Markup};
+use serde::Deserialize;
+use tower_cookies::Cookies;
+use uuid::Uuid;
+
+use crate::{
+    auth::AdminSession,
+    flash::Flash,
+    notifications::{deliver_to, ChannelKind, Event, Notification, NotificationChannel},
+    AppState,
+};
+
+const INDEX_PATH: &str = "/notifications";
+
+fn channel_path(notification_channel_id: Uuid) -> String {
+    format!("{INDEX_PATH}/{notification_channel_id}")
+}
+
+async fn find_channel(
+    app_state: &AppState,
+    notification_channel_id: Uuid,
+) -> Option<NotificationChannel> {
+    sqlx::query_as!(
+        NotificationChannel,
+        "SELECT * FROM NotificationChannels WHERE notification_channel_id = $1",
+        notification_channel_id
+    )
+    .fetch_optional(app_state.db())
+    .await
+    .unwrap()
+}
+
+fn channel_form(channel: Option<&NotificationChannel>) -> Markup {
+    let action = channel.map_or_else(
+        || INDEX_PATH.to_string(),
+        |c| channel_path(c.notification_channel_id),
+    );
+    let name = channel.map(|c| c.name.as_str());
+    let current_kind = channel.and_then(|c| c.kind().ok());
+    let target = channel.map(|c| c.target.as_str());
+    let enabled = channel.is_none_or(|c| c.enabled);
+
+    html! {
+        form method="post" action=(action) {
+            label {
+                "Name"
+                input type="text" name="name" required value=[name];
+            }
+
+            label {
+                "Kind"
+                select name="kind" {
+                    @for kind in ChannelKind::ALL {
+                        option value=(kind.as_str()) selected[Some(kind) == current_kind] { (kind) }
+                    }
+                }
+            }
+
+            label {
+                "Email address or webhook URL"
+                input type="text" name="target" required value=[target];
+            }
+
+            fieldset {
+                legend { "Events" }
+                @for event in Event::SUBSCRIBABLE {
+                    label {
+                        input type="checkbox" name=(event.as_str()) value="true" checked[channel.is_none_or(|c| c.subscribes_to(event))];
+                        (event.description())
+                    }
+                }
+            }
+
+            label {
+                input type="checkbox" name="enabled" value="true" checked[enabled];
+                "Enabled"
+            }
+
+            button type="submit" { "Save channel" }
+        }
+    }
+}
+
+pub(crate) async fn index(
+    _: AdminSession,
+    cookies: Cookies,
+    State(app_state): State<AppState>,
+) -> impl IntoResponse {
+    let channels = sqlx::query_as!(
+        NotificationChannel,
+        "SELECT * FROM NotificationChannels ORDER BY name"
+    )
+    .fetch_all(app_state.db())
+    .await
+    .unwrap();
+
+    let deliveries = sqlx::query!(
+        "SELECT d.*, c.name AS channel_name
+        FROM NotificationDeliveries d
+        JOIN NotificationChannels c ON c.notification_channel_id = d.notification_channel_id
+        ORDER BY d.created_at DESC
+        LIMIT 50"
+    )
+    .fetch_all(app_state.db())
+    .await
+    .unwrap();
+
+    let flash = Flash::take(&cookies, &app_state);
+
+    html! {
+        h1 { "Notifications" }
+
+        a href="/" { "Home" }
+
+        @if let Some(flash) = flash {
+            (flash)
+        }
+
+        p { "Events no channel subscribes to are only written to the log. Email channels use the server in SMTP_URL and send from SMTP_FROM." }
+
+        h2 { "Channels" }
+
+        @if channels.is_empty() {
+            p { "No channels yet" }
+        } @else {
+            table {
+                thead {
+                    tr {
+                        th { "Name" }
+                        th { "Kind" }
+                        th { "Target" }
+                        th { "Events" }
+                        th { "Enabled" }
+                        th {}
+                    }
+                }
+
+                tbody {
+                    @for channel in &channels {
+                        tr {
+                            td { (channel.name) }
+                            td { (channel.kind) }
+                            td { (channel.target) }
+                            td { (channel.events.join(", ")) }
+                            td { @if channel.enabled { "Yes" } @else { "No" } }
+                            td {
+                                a href=(channel_path(channel.notification_channel_id)) { "Edit" }
+                                form method="post" action={ (channel_path(channel.notification_channel_id)) "/test" } {
+                                    button type="submit" { "Send test" }
+                                }
+                                form method="post" action={ (channel_path(channel.notification_channel_id)) "/delete" } {
+                                    button type="submit" { "Delete" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        h2 { "Add a channel" }
+
+        (channel_form(None))
+
+        h2 { "Recent deliveries" }
+
+        @if deliveries.is_empty() {
+            p { "Nothing has been sent yet" }
+        } @else {
+            table {
+                thead {
+                    tr {
+                        th { "Created" }
+                        th { "Channel" }
+                        th { "Event" }
+                        th { "Subject" }
+                        th { "Status" }
+                        th { "Attempts" }
+                        th { "Last error" }
+                    }
+                }
+
+                tbody {
+                    @for delivery in &deliveries {
+                        tr {
+                            td { (delivery.created_at.format("%Y-%m-%d %H:%M UTC")) }
+                            td { (delivery.channel_name) }
+                            td { (delivery.event) }
+                            td { (delivery.subject) }
+                            td { (delivery.status) }
+                            td { (delivery.attempts) }
+                            td {
+                                @if let Some(error) = &delivery.last_error {
+                                    (error)
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub(crate) async fn edit(
+    _: AdminSession,
+    cookies: Cookies,
+    Path(notification_channel_id): Path<Uuid>,
+    State(app_state): State<AppState>,
+) -> Response {
+    let Some(channel) = find_channel(&app_state, notification_channel_id).await else {
+        return (StatusCode::NOT_FOUND, "Channel not found").into_response();
+    };
+
+    let flash = Flash::take(&cookies, &app_state);
+
+    html! {
+        h1 { "Edit " (channel.name) }
+
+        a href=(INDEX_PATH) { "Back to Notifications" }
+
+        @if let Some(flash) = flash {
+            (flash)
+        }
+
+        (channel_form(Some(&channel)))
+    }
+    .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ChannelForm {
+    name: String,
+    kind: String,
+    target: String,
+    domain_expiring: Option<String>,
+    dns_drift: Option<String>,
+    sync_failure: Option<String>,
+    enabled: Option<String>,
+}
+
+struct ValidChannel {
+    name: String,
+    kind: ChannelKind,
+    target: String,
+    events: Vec<String>,
+    enabled: bool,
+}
+
+impl ChannelForm {
+    fn validate(&self) -> Result<ValidChannel, String> {
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err("Name can't be blank".to_string());
+        }
+
+        let kind = ChannelKind::parse(&self.kind)?;
+        let target = kind.validate_target(&self.target)?;
+
+        let events = [
+            (Event::DomainExpiring, &self.domain_expiring),
+            (Event::DnsDrift, &self.dns_drift),
+            (Event::SyncFailure, &self.sync_failure),
+        ]
+        .into_iter()
+        .filter(|(_, checked)| checked.is_some())
+        .map(|(event, _)| event.as_str().to_string())
+        .collect();
+
+        Ok(ValidChannel {
+            name: name.to_string(),
+            kind,
+            target,
+            events,
+            enabled: self.enabled.is_some(),
+        })
+    }
+}
+
+pub(crate) async fn create(
+    _: AdminSession,
+    cookies: Cookies,
+    State(app_state): State<AppState>,
+    Form(form): Form<ChannelForm>,
+) -> Response {
+    let channel = match form.validate() {
+        Ok(channel) => channel,
+        Err(message) => {
+            Flash::error(message).set(&cookies, &app_state);
+            return Redirect::to(INDEX_PATH).into_response();
+        }
+    };
+
+    sqlx::query!(
+        "INSERT INTO NotificationChannels (notification_channel_id, name, kind, target, events, enabled)
+        VALUES ($1, $2, $3, $4, $5, $6)",
+        Uuid::new_v4(),
+        channel.name,
+        channel.kind.as_str(),
+        channel.target,
+        &channel.events,
+        channel.enabled
+    )
+    .execute(app_state.db())
+    .await
+    .unwrap();
+
+    Flash::success(format!("Added {}", channel.name)).set(&cookies, &app_state);
+
+    Redirect::to(INDEX_PATH).into_response()
+}
+
+pub(crate) async fn update(
+    _: AdminSession,
+    cookies: Cookies,
+    Path(notification_channel_id): Path<Uuid>,
+    State(app_state): State<AppState>,
+    Form(form): Form<ChannelForm>,
+) -> Response {
+    let channel = match form.validate() {
+        Ok(channel) => channel,
+        Err(message) => {
+            Flash::error(message).set(&cookies, &app_state);
+            return Redirect::to(&channel_path(notification_channel_id)).into_response();
+        }
+    };
+
+    let result = sqlx::query!(
+        "UPDATE NotificationChannels
+        SET name = $1, kind = $2, target = $3, events = $4, enabled = $5, updated_at = NOW()
+        WHERE notification_channel_id = $6",
+        channel.name,
+        channel.kind.as_str(),
+        channel.target,
+        &channel.events,
+        channel.enabled,
+        notification_channel_id
+    )
+    .execute(app_state.db())
+    .await
+    .unwrap();
+
+    if result.rows_affected() == 0 {
+        return (StatusCode::NOT_FOUND, "Channel not found").into_response();
+    }
+
+    Flash::success(format!("Updated {}", channel.name)).set(&cookies, &app_state);
+
+    Redirect::to(INDEX_PATH).into_response()
+}
+
+pub(crate) async fn destroy(
+    _: AdminSession,
+    cookies: Cookies,
+    Path(notification_channel_id): Path<Uuid>,
+    State(app_state): State<AppState>,
+) -> Response {
+    let Some(channel) = find_channel(&app_state, notification_channel_id).await else {
+        return (StatusCode::NOT_FOUND, "Channel not found").into_response();
+    };
+
+    sqlx::query!(
+        "DELETE FROM NotificationChannels WHERE notification_channel_id = $1",
+        notification_channel_id
+    )
+    .execute(app_state.db())
+    .await
+    .unwrap();
+
+    Flash::success(format!("Deleted {}", channel.name)).set(&cookies, &app_state);
+
+    Redirect::to(INDEX_PATH).into_response()
+}
+
+pub(crate) async fn test(
+    _: AdminSession,
+    cookies: Cookies,
+    Path(notification_channel_id): Path<Uuid>,
+    State(app_state): State<AppState>,
+) -> Response {
+    let Some(channel) = find_channel(&app_state, notification_channel_id).await else {
+        return (StatusCode::NOT_FOUND, "Channel not found").into_response();
+    };
+
+    let notification = Notification {
+        event: Event::Test,
+        subject: format!("Test notification for {}", channel.name),
+        body: "If you can read this the channel is set up correctly.".to_string(),
+    };
+    deliver_to(&app_state, &channel, &notification)
+        .await
+        .unwrap();
+
+    Flash::success(format!(
+        "Queued a test notification for {}, check the deliveries below",
+        channel.name
+    ))
+    .set(&cookies, &app_state);
+
+    Redirect::to(INDEX_PATH).into_response()
+}
@@ -0,0 +1,498 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    Form,
+};
+use cja::app_state::AppState as _;
+use maud::{html, Markup};
+use serde::Deserialize;
+use tower_cookies::Cookies;
+use uuid::Uuid;
+
+use crate::{
+    auth::AdminSession,
+    flash::Flash,
+    redirects::{
+        compile_path_pattern, validate_host, validate_mode, validate_status_code,
+        validate_target_url, PatternType, RedirectMode, RedirectRule, STATUS_CODES,
+    },
+    routes::domains::{domain_path, Domain},
+    AppState,
+};
+
+async fn find_rule(app_state: &AppState, domain: &str, redirect_id: Uuid) -> Option<RedirectRule> {
+    sqlx::query_as!(
+        RedirectRule,
+        "SELECT * FROM Redirects WHERE (host = $1 OR host LIKE '%.' || $1) AND redirect_id = $2",
+        domain,
+        redirect_id
+    )
+    .fetch_optional(app_state.db())
+    .await
+    .unwrap()
+}
+
+fn index_path(domain: &str) -> String {
+    format!("/domains/{domain}/redirects")
+}
+
+fn rule_path(domain: &str, redirect_id: Uuid) -> String {
+    format!("/domains/{domain}/redirects/{redirect_id}")
+}
+
+/// The create and edit form, pre-filled from `rule` when editing
+async fn rule_form(app_state: &AppState, domain: &str, rule: Option<&RedirectRule>) -> Markup {
+    let other_domains = sqlx::query_scalar!(
+        "SELECT domain FROM Domains WHERE domain != $1 AND removed_at IS NULL ORDER BY domain",
+        domain
+    )
+    .fetch_all(app_state.db())
+    .await
+    .unwrap();
+
+    let action = rule.map_or_else(|| index_path(domain), |r| rule_path(domain, r.redirect_id));
+    let host = rule.map_or(domain, |r| r.host.as_str());
+    let path_pattern = rule.map_or("*", |r| r.path_pattern.as_str());
+    let current_pattern_type = rule.map_or(PatternType::Glob, |r| r.pattern_type());
+    let priority = rule.map_or(0, |r| r.priority);
+    let current_target = rule.map(|r| r.target_url.as_str());
+    let current_mode = rule.map_or(RedirectMode::Fixed, |r| r.mode());
+    let current_status = rule.map_or(301, |r| r.status_code);
+    let enabled = rule.is_none_or(|r| r.enabled);
+    let match_apex = rule.is_some_and(|r| r.match_apex);
+    let match_www = rule.is_none_or(|r| r.match_www);
+
+    html! {
+        form method="post" action=(action) {
+            label {
+                "Host"
+                input type="text" name="host" required value=(host);
+            }
+
+            label {
+                input type="checkbox" name="match_apex" value="true" checked[match_apex];
+                "Wildcard hosts also match " (domain) " itself"
+            }
+
+            label {
+                input type="checkbox" name="match_www" value="true" checked[match_www];
+                "Wildcard hosts also match www"
+            }
+
+            label {
+                "Path pattern"
+                input type="text" name="path_pattern" required value=(path_pattern);
+            }
+
+            label {
+                "Pattern type"
+                select name="pattern_type" {
+                    @for pattern_type in PatternType::ALL {
+                        option value=(pattern_type.as_str()) selected[pattern_type == current_pattern_type] { (pattern_type) }
+                    }
+                }
+            }
+
+            label {
+                "Priority"
+                input type="number" name="priority" value=(priority);
+            }
+
+            label {
+                "Redirect to one of my domains"
+                select name="target_domain" {
+                    option value="" { "Use the URL below" }
+                    @for other in &other_domains {
+                        @let other_url = format!("https://{other}/");
+                        option value=(other) selected[current_target == Some(other_url.as_str())] { (other) }
+                    }
+                }
+            }
+
+            label {
+                "Or any URL"
+                input type="text" name="target_url" placeholder="https://example.com/some/path/$1" value=[current_target];
+            }
+
+            label {
+                "Mode"
+                select name="mode" {
+                    @for mode in RedirectMode::ALL {
+                        option value=(mode.as_str()) selected[mode == current_mode] { (mode) }
+                    }
+                }
+            }
+
+            label {
+                "Status code"
+                select name="status_code" {
+                    @for (code, label) in STATUS_CODES {
+                        option value=(code) selected[code == current_status] { (label) }
+                    }
+                }
+            }
+
+            label {
+                input type="checkbox" name="enabled" value="true" checked[enabled];
+                "Enabled"
+            }
+
+            button type="submit" { "Save redirect" }
+        }
+
+        p {
+            "Use a host like " code { "*." (domain) } " to match every subdomain. The matched subdomain can be used in the target as "
+            code { "${subdomain}" } "."
+        }
+
+        p {
+            "Wildcard patterns match the whole path and each " code { "*" } " can be used in the target as "
+            code { "$1" } ", " code { "$2" } " and so on. Regex patterns can use numbered or named groups, like "
+            code { "${slug}" } ". Write " code { "$$" } " for a literal " code { "$" } ". Rules with a higher priority are checked first."
+        }
+    }
+}
+
+pub(crate) async fn index(
+    _: AdminSession,
+    cookies: Cookies,
+    Path(domain): Path<String>,
+    State(app_state): State<AppState>,
+) -> Response {
+    let Some(domain) = Domain::find_by_domain(app_state.db(), &domain)
+        .await
+        .unwrap()
+    else {
+        return (StatusCode::NOT_FOUND, "Domain not found").into_response();
+    };
+
+    let rules = sqlx::query_as!(
+        RedirectRule,
+        "SELECT * FROM Redirects
+        WHERE host = $1 OR host LIKE '%.' || $1
+        ORDER BY host, priority DESC, created_at",
+        domain.domain
+    )
+    .fetch_all(app_state.db())
+    .await
+    .unwrap();
+
+    let flash = Flash::take(&cookies, &app_state);
+
+    html! {
+        h1 { "Redirects for " (domain.domain) }
+
+        a href=(domain_path(&domain.domain)) { "Back to " (domain.domain) }
+
+        @if let Some(flash) = flash {
+            (flash)
+        }
+
+        @if rules.is_empty() {
+            p { "No redirects configured yet" }
+        } @else {
+            table {
+                thead {
+                    tr {
+                        th { "Host" }
+                        th { "Priority" }
+                        th { "Path" }
+                        th { "Target" }
+                        th { "Mode" }
+                        th { "Status" }
+                        th {}
+                    }
+                }
+
+                tbody {
+                    @for rule in &rules {
+                        tr {
+                            td {
+                                (rule.host)
+                                @if rule.is_wildcard() {
+                                    @if rule.match_apex {
+                                        br; "and " (domain.domain)
+                                    }
+                                    @if !rule.match_www {
+                                        br; "except www"
+                                    }
+                                }
+                            }
+                            td { (rule.priority) }
+                            td { code { (rule.path_pattern) } " (" (rule.pattern_type()) ")" }
+                            td {
+                                (rule.target_url)
+                                @if !rule.enabled {
+                                    " (disabled)"
+                                }
+                            }
+                            td { (rule.mode()) }
+                            td { (rule.status_code) }
+                            td {
+                                a href=(rule_path(&domain.domain, rule.redirect_id)) { "Edit" }
+                                form method="post" action={ (rule_path(&domain.domain, rule.redirect_id)) "/delete" } {
+                                    button type="submit" { "Delete" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        h2 { "Add a redirect" }
+
+        (rule_form(&app_state, &domain.domain, None).await)
+    }
+    .into_response()
+}
+
+pub(crate) async fn edit(
+    _: AdminSession,
+    cookies: Cookies,
+    Path((domain, redirect_id)): Path<(String, Uuid)>,
+    State(app_state): State<AppState>,
+) -> Response {
+    let Some(rule) = find_rule(&app_state, &domain, redirect_id).await else {
+        return (StatusCode::NOT_FOUND, "Redirect not found").into_response();
+    };
+
+    let flash = Flash::take(&cookies, &app_state);
+
+    html! {
+        h1 { "Edit redirect for " (domain) }
+
+        a href=(index_path(&domain)) { "Back to Redirects" }
+
+        @if let Some(flash) = flash {
+            (flash)
+        }
+
+        (rule_form(&app_state, &domain, Some(&rule)).await)
+    }
+    .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct RedirectForm {
+    host: String,
+    match_apex: Option<String>,
+    match_www: Option<String>,
+    path_pattern: String,
+    pattern_type: String,
+    priority: i32,
+    target_domain: Option<String>,
+    target_url: Option<String>,
+    status_code: i32,
+    mode: String,
+    enabled: Option<String>,
+}
+
+struct ValidRedirect {
+    host: String,
+    path_pattern: String,
+    pattern_type: PatternType,
+    target_url: String,
+    status_code: i32,
+    mode: RedirectMode,
+}
+
+impl RedirectForm {
+    /// A domain picked from the dropdown wins over the free-form URL
+    fn target(&self) -> Option<String> {
+        let non_empty = |s: &Option<String>| s.as_ref().filter(|s| !s.trim().is_empty()).cloned();
+
+        non_empty(&self.target_domain)
+            .map(|domain| format!("https://{domain}/"))
+            .or_else(|| non_empty(&self.target_url))
+    }
+
+    fn validate(&self, domain: &str) -> Result<ValidRedirect, String> {
+        let target = self
+            .target()
+            .ok_or_else(|| "Pick a domain or enter a URL to redirect to".to_string())?;
+
+        let host = validate_host(domain, &self.host)?;
+
+        let path_pattern = self.path_pattern.trim().to_string();
+        let pattern_type = PatternType::parse(&self.pattern_type)?;
+        let path_matcher = compile_path_pattern(pattern_type, &path_pattern)?;
+
+        Ok(ValidRedirect {
+            target_url: validate_target_url(
+                &host,
+                self.match_apex.is_some(),
+                &path_matcher,
+                &target,
+            )?,
+            host,
+            path_pattern,
+            pattern_type,
+            status_code: validate_status_code(self.status_code)?,
+            mode: validate_mode(&self.mode, &target)?,
+        })
+    }
+}
+
+pub(crate) async fn create(
+    _: AdminSession,
+    cookies: Cookies,
+    Path(domain): Path<String>,
+    State(app_state): State<AppState>,
+    Form(form): Form<RedirectForm>,
+) -> Response {
+    let Some(domain) = Domain::find_by_domain(app_state.db(), &domain)
+        .await
+        .unwrap()
+    else {
+        return (StatusCode::NOT_FOUND, "Domain not found").into_response();
+    };
+    let redirect_back = Redirect::to(&index_path(&domain.domain));
+
+    let valid = match form.validate(&domain.domain) {
+        Ok(valid) => valid,
+        Err(message) => {
+            Flash::error(message).set(&cookies, &app_state);
+            return redirect_back.into_response();
+        }
+    };
+
+    let inserted = sqlx::query!(
+        "INSERT INTO Redirects (redirect_id, host, match_apex, match_www, path_pattern, pattern_type, priority, target_url, status_code, mode, enabled)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        ON CONFLICT (host, path_pattern, pattern_type) DO NOTHING",
+        Uuid::new_v4(),
+        valid.host,
+        form.match_apex.is_some(),
+        form.match_www.is_some(),
+        valid.path_pattern,
+        valid.pattern_type.as_str(),
+        form.priority,
+        valid.target_url,
+        valid.status_code,
+        valid.mode.as_str(),
+        form.enabled.is_some()
+    )
+    .execute(app_state.db())
+    .await
+    .unwrap()
+    .rows_affected();
+
+    if inserted > 0 {
+        Flash::success(format!(
+            "{}{} now redirects to {}",
+            valid.host, valid.path_pattern, valid.target_url
+        ))
+        .set(&cookies, &app_state);
+    } else {
+        Flash::error(format!(
+            "{} already has a {} redirect for {}",
+            valid.host,
+            valid.pattern_type.as_str(),
+            valid.path_pattern
+        ))
+        .set(&cookies, &app_state);
+    }
+
+    redirect_back.into_response()
+}
+
+pub(crate) async fn update(
+    _: AdminSession,
+    cookies: Cookies,
+    Path((domain, redirect_id)): Path<(String, Uuid)>,
+    State(app_state): State<AppState>,
+    Form(form): Form<RedirectForm>,
+) -> Response {
+    if find_rule(&app_state, &domain, redirect_id).await.is_none() {
+        return (StatusCode::NOT_FOUND, "Redirect not found").into_response();
+    }
+    let redirect_to_form = Redirect::to(&rule_path(&domain, redirect_id));
+
+    let valid = match form.validate(&domain) {
+        Ok(valid) => valid,
+        Err(message) => {
+            Flash::error(message).set(&cookies, &app_state);
+            return redirect_to_form.into_response();
+        }
+    };
+
+    let result = sqlx::query!(
+        "UPDATE Redirects
+        SET
+          host = $1,
+          match_apex = $2,
+          match_www = $3,
+          path_pattern = $4,
+          pattern_type = $5,
+          priority = $6,
+          target_url = $7,
+          status_code = $8,
+          mode = $9,
+          enabled = $10,
+          updated_at = NOW()
+        WHERE redirect_id = $11",
+        valid.host,
+        form.match_apex.is_some(),
+        form.match_www.is_some(),
+        valid.path_pattern,
+        valid.pattern_type.as_str(),
+        form.priority,
+        valid.target_url,
+        valid.status_code,
+        valid.mode.as_str(),
+        form.enabled.is_some(),
+        redirect_id
+    )
+    .execute(app_state.db())
+    .await;
+
+    if let Err(sqlx::Error::Database(e)) = &result {
+        if e.is_unique_violation() {
+            Flash::error(format!(
+                "{} already has a {} redirect for {}",
+                valid.host,
+                valid.pattern_type.as_str(),
+                valid.path_pattern
+            ))
+            .set(&cookies, &app_state);
+
+            return redirect_to_form.into_response();
+        }
+    }
+    result.unwrap();
+
+    Flash::success(format!(
+        "{}{} now redirects to {}",
+        valid.host, valid.path_pattern, valid.target_url
+    ))
+    .set(&cookies, &app_state);
+
+    Redirect::to(&index_path(&domain)).into_response()
+}
+
+pub(crate) async fn destroy(
+    _: AdminSession,
+    cookies: Cookies,
+    Path((domain, redirect_id)): Path<(String, Uuid)>,
+    State(app_state): State<AppState>,
+) -> Response {
+    let deleted = sqlx::query!(
+        "DELETE FROM Redirects WHERE (host = $1 OR host LIKE '%.' || $1) AND redirect_id = $2",
+        domain,
+        redirect_id
+    )
+    .execute(app_state.db())
+    .await
+    .unwrap()
+    .rows_affected();
+
+    if deleted > 0 {
+        Flash::success(format!("Removed a redirect from {domain}")).set(&cookies, &app_state);
+    } else {
+        Flash::error(format!("Couldn't find that redirect for {domain}")).set(&cookies, &app_state);
+    }
+
+    Redirect::to(&index_path(&domain)).into_response()
+}
@@ -0,0 +1,389 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    Form,
+};
+use cja::app_state::AppState as _;
+use maud::{html, Markup};
+use serde::Deserialize;
+use tower_cookies::Cookies;
+use uuid::Uuid;
+
+use crate::{auth::AdminSession, flash::Flash, routes::domains::Domain, AppState};
+
+const INDEX_PATH: &str = "/tags";
+
+/// Tag names are free text, within reason
+const MAX_TAG_LEN: usize = 50;
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub(crate) struct Tag {
+    pub(crate) tag_id: Uuid,
+    pub(crate) name: String,
+    pub(crate) created_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl Tag {
+    pub(crate) async fn all(db: &sqlx::PgPool) -> sqlx::Result<Vec<Self>> {
+        sqlx::query_as!(Tag, "SELECT * FROM Tags ORDER BY name")
+            .fetch_all(db)
+            .await
+    }
+
+    /// The tags on each of `domain_ids`, domains without tags are left out
+    pub(crate) async fn for_domains(
+        db: &sqlx::PgPool,
+        domain_ids: &[Uuid],
+    ) -> sqlx::Result<HashMap<Uuid, Vec<Self>>> {
+        let rows = sqlx::query!(
+            "SELECT dt.domain_id, t.tag_id, t.name, t.created_at
+            FROM DomainTags dt
+            JOIN Tags t ON t.tag_id = dt.tag_id
+            WHERE dt.domain_id = ANY($1)
+            ORDER BY t.name",
+            domain_ids
+        )
+        .fetch_all(db)
+        .await?;
+
+        let mut tags = HashMap::<Uuid, Vec<Self>>::new();
+        for row in rows {
+            tags.entry(row.domain_id).or_default().push(Tag {
+                tag_id: row.tag_id,
+                name: row.name,
+                created_at: row.created_at,
+            });
+        }
+
+        Ok(tags)
+    }
+}
+
+fn validate_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+
+    if name.is_empty() {
+        return Err("Tags need a name".to_string());
+    }
+    if name.chars().count() > MAX_TAG_LEN {
+        return Err(format!("Tags can be at most {MAX_TAG_LEN} characters"));
+    }
+
+    Ok(name.to_string())
+}
+
+/// Only follow links back into the app, never to another site. Browsers treat `\` like `/` and
+/// drop tabs and newlines, so `/\evil.example` or `/\t/evil.example` would be protocol-relative
+fn safe_return_to(return_to: &str) -> &str {
+    if return_to.starts_with('/')
+        && !return_to.starts_with("//")
+        && !return_to.contains('\\')
+        && !return_to.chars().any(char::is_control)
+    {
+        return_to
+    } else {
+        "/domains"
+    }
+}
+
+/// The domains list showing only this tag's domains
+pub(crate) fn filter_path(name: &str) -> String {
+    let mut url = reqwest::Url::parse("http://domains/domains").unwrap();
+    url.query_pairs_mut().append_pair("tag", name);
+
+    format!("/domains?{}", url.query().unwrap_or_default())
+}
+
+pub(crate) fn format_cents(cents: i64) -> String {
+    format!("${}.{:02}", cents / 100, cents % 100)
+}
+
+/// Suggestions for the tag inputs rendered by [`tag_editor`], include it once per page
+pub(crate) fn tag_datalist(tags: &[Tag]) -> Markup {
+    html! {
+        datalist id="tag-names" {
+            @for tag in tags {
+                option value=(tag.name) {}
+            }
+        }
+    }
+}
+
+/// A domain's tags with buttons to remove them and a field to add another, coming back to
+/// `return_to` afterwards
+pub(crate) fn tag_editor(domain: &str, tags: &[Tag], return_to: &str) -> Markup {
+    html! {
+        @for tag in tags {
+            form method="post" action={ "/domains/" (domain) "/tags/" (tag.tag_id) "/delete" } {
+                a href=(filter_path(&tag.name)) { (tag.name) }
+                input type="hidden" name="return_to" value=(return_to);
+                button type="submit" title={ "Remove " (tag.name) } { "×" }
+            }
+            " "
+        }
+
+        form method="post" action={ "/domains/" (domain) "/tags" } {
+            input type="text" name="name" list="tag-names" placeholder="Add a tag" required maxlength=(MAX_TAG_LEN);
+            input type="hidden" name="return_to" value=(return_to);
+            button type="submit" { "Tag" }
+        }
+    }
+}
+
+pub(crate) async fn index(
+    _: AdminSession,
+    cookies: Cookies,
+    State(app_state): State<AppState>,
+) -> Response {
+    let tags = sqlx::query!(
+        r#"SELECT
+          t.tag_id,
+          t.name,
+          COUNT(d.domain_id) AS "domains!",
+          MIN(d.expire_date) AS next_expiry,
+          SUM(p.renewal_cents)::BIGINT AS renewal_cents,
+          COUNT(d.domain_id) FILTER (WHERE p.renewal_cents IS NULL) AS "unpriced!"
+        FROM Tags t
+        LEFT JOIN DomainTags dt ON dt.tag_id = t.tag_id
+        LEFT JOIN Domains d ON d.domain_id = dt.domain_id AND d.removed_at IS NULL
+        LEFT JOIN TldPrices p ON p.registrar = d.registrar AND p.tld = d.tld
+        GROUP BY t.tag_id, t.name
+        ORDER BY t.name"#
+    )
+    .fetch_all(app_state.db())
+    .await
+    .unwrap();
+
+    let flash = Flash::take(&cookies, &app_state);
+
+    html! {
+        h1 { "Tags" }
+
+        a href="/domains" { "Back to Domains" }
+
+        @if let Some(flash) = flash {
+            (flash)
+        }
+
+        @if tags.is_empty() {
+            p { "No tags yet, add one below or from a domain" }
+        } @else {
+            table {
+                thead {
+                    tr {
+                        th { "Tag" }
+                        th { "Domains" }
+                        th { "Next expiry" }
+                        th { "Yearly renewal" }
+                        th {}
+                    }
+                }
+
+                tbody {
+                    @for tag in &tags {
+                        tr {
+                            td { a href=(filter_path(&tag.name)) { (tag.name) } }
+                            td { (tag.domains) }
+                            td {
+                                @if let Some(next_expiry) = tag.next_expiry {
+                                    (next_expiry.format("%Y-%m-%d"))
+                                }
+                            }
+                            td {
+                                (format_cents(tag.renewal_cents.unwrap_or_default()))
+                                @if tag.unpriced > 0 {
+                                    br;
+                                    "plus " (tag.unpriced) " without a known price"
+                                }
+                            }
+                            td {
+                                form method="post" action={ (INDEX_PATH) "/" (tag.tag_id) "/delete" } {
+                                    button type="submit" { "Delete" }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        h2 { "Add a tag" }
+
+        form method="post" action=(INDEX_PATH) {
+            label {
+                "Name"
+                input type="text" name="name" required maxlength=(MAX_TAG_LEN);
+            }
+
+            button type="submit" { "Add tag" }
+        }
+    }
+    .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct TagForm {
+    name: String,
+}
+
+pub(crate) async fn create(
+    _: AdminSession,
+    cookies: Cookies,
+    State(app_state): State<AppState>,
+    Form(form): Form<TagForm>,
+) -> Response {
+    match validate_name(&form.name) {
+        Ok(name) => {
+            sqlx::query!(
+                "INSERT INTO Tags (tag_id, name) VALUES ($1, $2) ON CONFLICT (name) DO NOTHING",
+                Uuid::new_v4(),
+                name
+            )
+            .execute(app_state.db())
+            .await
+            .unwrap();
+
+            Flash::success(format!("Added the {name} tag"))
+        }
+        Err(message) => Flash::error(message),
+    }
+    .set(&cookies, &app_state);
+
+    Redirect::to(INDEX_PATH).into_response()
+}
+
+pub(crate) async fn destroy(
+    _: AdminSession,
+    cookies: Cookies,
+    Path(tag_id): Path<Uuid>,
+    State(app_state): State<AppState>,
+) -> Response {
+    let deleted = sqlx::query_scalar!("DELETE FROM Tags WHERE tag_id = $1 RETURNING name", tag_id)
+        .fetch_optional(app_state.db())
+        .await
+        .unwrap();
+
+    match deleted {
+        Some(name) => Flash::success(format!("Deleted the {name} tag")),
+        None => Flash::error("That tag doesn't exist"),
+    }
+    .set(&cookies, &app_state);
+
+    Redirect::to(INDEX_PATH).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct DomainTagForm {
+    name: String,
+    #[serde(default)]
+    return_to: String,
+}
+
+/// Tags a domain, creating the tag if it's new
+pub(crate) async fn tag_domain(
+    _: AdminSession,
+    cookies: Cookies,
+    Path(domain): Path<String>,
+    State(app_state): State<AppState>,
+    Form(form): Form<DomainTagForm>,
+) -> Response {
+    let Some(domain) = Domain::find_by_domain(app_state.db(), &domain)
+        .await
+        .unwrap()
+    else {
+        return (StatusCode::NOT_FOUND, "Domain not found").into_response();
+    };
+
+    match validate_name(&form.name) {
+        Ok(name) => {
+            let tag_id = sqlx::query_scalar!(
+                "INSERT INTO Tags (tag_id, name) VALUES ($1, $2)
+                ON CONFLICT (name) DO UPDATE SET name = excluded.name
+                RETURNING tag_id",
+                Uuid::new_v4(),
+                name
+            )
+            .fetch_one(app_state.db())
+            .await
+            .unwrap();
+
+            sqlx::query!(
+                "INSERT INTO DomainTags (domain_id, tag_id) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+                domain.domain_id,
+                tag_id
+            )
+            .execute(app_state.db())
+            .await
+            .unwrap();
+        }
+        Err(message) => Flash::error(message).set(&cookies, &app_state),
+    }
+
+    Redirect::to(safe_return_to(&form.return_to)).into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct UntagForm {
+    #[serde(default)]
+    return_to: String,
+}
+
+pub(crate) async fn untag_domain(
+    _: AdminSession,
+    Path((domain, tag_id)): Path<(String, Uuid)>,
+    State(app_state): State<AppState>,
+    Form(form): Form<UntagForm>,
+) -> Response {
+    let Some(domain) = Domain::find_by_domain(app_state.db(), &domain)
+        .await
+        .unwrap()
+    else {
+        return (StatusCode::NOT_FOUND, "Domain not found").into_response();
+    };
+
+    sqlx::query!(
+        "DELETE FROM DomainTags WHERE domain_id = $1 AND tag_id = $2",
+        domain.domain_id,
+        tag_id
+    )
+    .execute(app_state.db())
+    .await
+    .unwrap();
+
+    Redirect::to(safe_return_to(&form.return_to)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_names() {
+        assert_eq!(
+            validate_name("  client work "),
+            Ok("client work".to_string())
+        );
+        assert!(validate_name("   ").is_err());
+        assert!(validate_name(&"a".repeat(MAX_TAG_LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn only_returns_within_the_app() {
+        assert_eq!(safe_return_to("/domains?tag=a"), "/domains?tag=a");
+        assert_eq!(safe_return_to("//evil.example"), "/domains");
+        assert_eq!(safe_return_to("/\\evil.example"), "/domains");
+        assert_eq!(safe_return_to("/\t/evil.example"), "/domains");
+        assert_eq!(safe_return_to("https://evil.example"), "/domains");
+        assert_eq!(safe_return_to(""), "/domains");
+    }
+
+    #[test]
+    fn links_to_the_filtered_list() {
+        assert_eq!(filter_path("client work"), "/domains?tag=client+work");
+        assert_eq!(format_cents(1108), "$11.08");
+    }
+}
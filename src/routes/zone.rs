@@ -0,0 +1,402 @@
+use axum::{
+    extract::{multipart::MultipartError, Multipart, Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    Form,
+};
+use chrono::Utc;
+use cja::app_state::AppState as _;
+use maud::html;
+use serde::Deserialize;
+use tower_cookies::Cookies;
+use uuid::Uuid;
+
+use crate::{
+    auth::AdminSession,
+    dns::{subdomain_of, DnsRecord, TtlLimits, MIN_TTL},
+    dns_hosts::RecordInput,
+    flash::Flash,
+    routes::{
+        dns::{editable_host, index_path, not_editable_reason},
+        domains::Domain,
+    },
+    zone::{plan_import, ImportMode, Soa, Zone, ZoneRecord},
+    AppState,
+};
+
+fn import_path(domain: &str) -> String {
+    format!("{}/import", index_path(domain))
+}
+
+async fn current_records(app_state: &AppState, domain: &Domain) -> Vec<DnsRecord> {
+    sqlx::query_as!(
+        DnsRecord,
+        "SELECT * FROM DnsRecords WHERE domain_id = $1 ORDER BY name, record_type, content",
+        domain.domain_id
+    )
+    .fetch_all(app_state.db())
+    .await
+    .unwrap()
+}
+
+pub(crate) async fn export(
+    _: AdminSession,
+    Path(domain): Path<String>,
+    State(app_state): State<AppState>,
+) -> Response {
+    let Some(domain) = Domain::find_by_domain(app_state.db(), &domain)
+        .await
+        .unwrap()
+    else {
+        return (StatusCode::NOT_FOUND, "Domain not found").into_response();
+    };
+
+    let records = current_records(&app_state, &domain).await;
+
+    // Providers generate their own SOA, this one is mostly so the file loads in other tools
+    let soa = domain.nameservers.first().map(|nameserver| Soa {
+        ttl: 3600,
+        mname: nameserver.trim_end_matches('.').to_ascii_lowercase(),
+        rname: format!("hostmaster.{}", domain.domain),
+        serial: Utc::now().format("%Y%m%d00").to_string().parse().unwrap(),
+        refresh: 10800,
+        retry: 3600,
+        expire: 604800,
+        minimum: MIN_TTL as u32,
+    });
+    let zone = Zone {
+        origin: domain.domain.clone(),
+        default_ttl: None,
+        soa,
+        records: records.iter().map(ZoneRecord::from).collect(),
+    };
+
+    (
+        [
+            (header::CONTENT_TYPE, "text/dns; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}.zone\"", domain.domain),
+            ),
+        ],
+        zone.to_zone_file(),
+    )
+        .into_response()
+}
+
+pub(crate) async fn import_form(
+    _: AdminSession,
+    cookies: Cookies,
+    Path(domain): Path<String>,
+    State(app_state): State<AppState>,
+) -> Response {
+    let Some(domain) = Domain::find_by_domain(app_state.db(), &domain)
+        .await
+        .unwrap()
+    else {
+        return (StatusCode::NOT_FOUND, "Domain not found").into_response();
+    };
+
+    let flash = Flash::take(&cookies, &app_state);
+
+    html! {
+        h1 { "Import a zone file for " (domain.domain) }
+
+        a href=(index_path(&domain.domain)) { "Back to DNS records" }
+
+        @if let Some(flash) = flash {
+            (flash)
+        }
+
+        @if let Some(reason) = not_editable_reason(&domain) {
+            p { (reason) " You can still preview an import to compare it with the synced records." }
+        }
+
+        p { "You'll see a preview of every change before anything is sent to " (domain.dns_provider()) ". SOA and apex NS records are ignored." }
+
+        form method="post" action=(import_path(&domain.domain)) enctype="multipart/form-data" {
+            label {
+                "Zone file"
+                input type="file" name="zone_file" accept=".zone,.txt,.db,text/dns,text/plain";
+            }
+
+            label {
+                "Or paste it here"
+                textarea name="zone" rows="20" cols="80" {}
+            }
+
+            fieldset {
+                legend { "Mode" }
+                @for mode in ImportMode::ALL {
+                    label {
+                        input type="radio" name="mode" value=(mode.as_str()) checked[mode == ImportMode::Merge];
+                        (mode.description())
+                    }
+                }
+            }
+
+            button type="submit" { "Preview import" }
+        }
+    }
+    .into_response()
+}
+
+/// Parses an uploaded zone, making sure it only has records for `domain`. TTLs are brought
+/// within what the domain's DNS provider accepts so the preview matches what will be saved
+fn parse_upload(domain: &Domain, zone_file: &str) -> Result<Zone, String> {
+    let mut zone = Zone::parse(zone_file, &domain.domain).map_err(|e| e.to_string())?;
+
+    let ttl_limits = TtlLimits::for_provider(domain.dns_provider());
+    let suffix = format!(".{}", domain.domain);
+    for record in &mut zone.records {
+        if record.name != domain.domain && !record.name.ends_with(&suffix) {
+            return Err(format!(
+                "{} isn't part of {}, check the $ORIGIN",
+                record.name, domain.domain
+            ));
+        }
+        record.ttl = ttl_limits.clamp(record.ttl);
+    }
+    zone.origin.clone_from(&domain.domain);
+
+    Ok(zone)
+}
+
+#[derive(Debug, Default)]
+struct ZoneUpload {
+    zone: String,
+    mode: String,
+}
+
+async fn read_upload(mut multipart: Multipart) -> Result<ZoneUpload, MultipartError> {
+    let mut upload = ZoneUpload::default();
+    let mut pasted = String::new();
+
+    while let Some(field) = multipart.next_field().await? {
+        match field.name() {
+            Some("zone_file") => upload.zone = field.text().await?,
+            Some("zone") => pasted = field.text().await?,
+            Some("mode") => upload.mode = field.text().await?,
+            _ => {}
+        }
+    }
+
+    if upload.zone.trim().is_empty() {
+        upload.zone = pasted;
+    }
+
+    Ok(upload)
+}
+
+pub(crate) async fn preview(
+    _: AdminSession,
+    cookies: Cookies,
+    Path(domain): Path<String>,
+    State(app_state): State<AppState>,
+    multipart: Multipart,
+) -> Response {
+    let Some(domain) = Domain::find_by_domain(app_state.db(), &domain)
+        .await
+        .unwrap()
+    else {
+        return (StatusCode::NOT_FOUND, "Domain not found").into_response();
+    };
+    let redirect_back = Redirect::to(&import_path(&domain.domain));
+
+    let upload = match read_upload(multipart).await {
+        Ok(upload) => upload,
+        Err(e) => return e.into_response(),
+    };
+    let parsed = ImportMode::parse(&upload.mode)
+        .and_then(|mode| Ok((mode, parse_upload(&domain, &upload.zone)?)));
+    let (mode, zone) = match parsed {
+        Ok(parsed) => parsed,
+        Err(message) => {
+            Flash::error(message).set(&cookies, &app_state);
+            return redirect_back.into_response();
+        }
+    };
+
+    let records = current_records(&app_state, &domain).await;
+    let plan = plan_import(&records, &zone, mode);
+    let not_editable = not_editable_reason(&domain);
+
+    html! {
+        h1 { "Import preview for " (domain.domain) }
+
+        a href=(import_path(&domain.domain)) { "Start over" }
+
+        p { (mode.description()) }
+
+        @if plan.is_empty() {
+            p { "The zone file matches the current records, there is nothing to import." }
+        } @else {
+            @if !plan.create.is_empty() {
+                h2 { "Create" }
+                ul {
+                    @for record in &plan.create {
+                        li { ins { code { (record.to_line(&domain.domain)) } } }
+                    }
+                }
+            }
+
+            @if !plan.update.is_empty() {
+                h2 { "Update" }
+                ul {
+                    @for (current, record) in &plan.update {
+                        li {
+                            del { code { (ZoneRecord::from(*current).to_line(&domain.domain)) } }
+                            " → "
+                            ins { code { (record.to_line(&domain.domain)) } }
+                        }
+                    }
+                }
+            }
+
+            @if !plan.delete.is_empty() {
+                h2 { "Delete" }
+                ul {
+                    @for current in &plan.delete {
+                        li { del { code { (ZoneRecord::from(*current).to_line(&domain.domain)) } } }
+                    }
+                }
+            }
+        }
+
+        p { (plan.unchanged) " records are unchanged." }
+
+        @if let Some(reason) = &not_editable {
+            p { (reason) }
+        } @else if !plan.is_empty() {
+            form method="post" action={ (import_path(&domain.domain)) "/apply" } {
+                input type="hidden" name="zone" value=(upload.zone);
+                input type="hidden" name="mode" value=(mode.as_str());
+                button type="submit" { "Apply these changes" }
+            }
+        }
+    }
+    .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct ApplyImportForm {
+    zone: String,
+    mode: String,
+}
+
+fn record_input(record: &ZoneRecord, domain: &str) -> RecordInput {
+    RecordInput {
+        subdomain: subdomain_of(&record.name, domain),
+        record_type: record.record_type.clone(),
+        content: record.content.clone(),
+        ttl: record.ttl,
+        prio: record.prio,
+    }
+}
+
+pub(crate) async fn apply(
+    _: AdminSession,
+    cookies: Cookies,
+    Path(domain): Path<String>,
+    State(app_state): State<AppState>,
+    Form(form): Form<ApplyImportForm>,
+) -> Response {
+    let Some(domain) = Domain::find_by_domain(app_state.db(), &domain)
+        .await
+        .unwrap()
+    else {
+        return (StatusCode::NOT_FOUND, "Domain not found").into_response();
+    };
+
+    let validated = editable_host(&domain, &app_state.apis).and_then(|host| {
+        let mode = ImportMode::parse(&form.mode)?;
+        let zone = parse_upload(&domain, &form.zone)?;
+        Ok((mode, zone, host))
+    });
+    let (mode, zone, host) = match validated {
+        Ok(validated) => validated,
+        Err(message) => {
+            Flash::error(message).set(&cookies, &app_state);
+            return Redirect::to(&import_path(&domain.domain)).into_response();
+        }
+    };
+
+    let records = current_records(&app_state, &domain).await;
+    let plan = plan_import(&records, &zone, mode);
+    let mut applied = 0;
+
+    // Deletes go first so a name can switch record types, e.g. from a CNAME to an A record
+    let result: color_eyre::Result<()> = async {
+        for current in &plan.delete {
+            host
+                .delete_record(&domain.domain, &current.provider_record_id)
+                .await?;
+            sqlx::query!(
+                "DELETE FROM DnsRecords WHERE dns_record_id = $1",
+                current.dns_record_id
+            )
+            .execute(app_state.db())
+            .await?;
+            applied += 1;
+        }
+
+        for (current, record) in &plan.update {
+            host
+                .update_record(
+                    &domain.domain,
+                    &current.provider_record_id,
+                    &record_input(record, &domain.domain),
+                )
+                .await?;
+            sqlx::query!(
+                "UPDATE DnsRecords SET ttl = $1, updated_at = NOW() WHERE dns_record_id = $2",
+                record.ttl,
+                current.dns_record_id
+            )
+            .execute(app_state.db())
+            .await?;
+            applied += 1;
+        }
+
+        for record in &plan.create {
+            let provider_record_id = host
+                .create_record(&domain.domain, &record_input(record, &domain.domain))
+                .await?;
+            sqlx::query!(
+                "INSERT INTO DnsRecords
+                  (dns_record_id, domain_id, provider, provider_record_id, name, record_type, content, ttl, prio)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+                Uuid::new_v4(),
+                domain.domain_id,
+                host.provider().as_str(),
+                provider_record_id,
+                record.name,
+                record.record_type,
+                record.content,
+                record.ttl,
+                record.prio
+            )
+            .execute(app_state.db())
+            .await?;
+            applied += 1;
+        }
+
+        Ok(())
+    }
+    .await;
+
+    match result {
+        Ok(()) => Flash::success(format!(
+            "Imported zone file: {} created, {} updated, {} deleted",
+            plan.create.len(),
+            plan.update.len(),
+            plan.delete.len()
+        )),
+        Err(e) => Flash::error(format!(
+            "Import stopped after {applied} changes: {e}. Run the preview again to see what is left."
+        )),
+    }
+    .set(&cookies, &app_state);
+
+    Redirect::to(&index_path(&domain.domain)).into_response()
+}
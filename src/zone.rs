@@ -0,0 +1,818 @@
+//! Reading and writing RFC 1035 zone files.
+//!
+//! Record contents use the same shape Porkbun does, so hostnames are stored without the trailing
+//! dot, MX and SRV priorities live in `prio` and the rest of an SRV record is `weight port target`
+
+use std::net::{Ipv4Addr, Ipv6Addr};
+
+use crate::dns::{DnsRecord, MIN_TTL};
+
+/// TXT strings longer than this have to be split into several quoted strings
+const MAX_TXT_STRING_LEN: usize = 255;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Soa {
+    pub(crate) ttl: i32,
+    pub(crate) mname: String,
+    pub(crate) rname: String,
+    pub(crate) serial: u32,
+    pub(crate) refresh: u32,
+    pub(crate) retry: u32,
+    pub(crate) expire: u32,
+    pub(crate) minimum: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ZoneRecord {
+    pub(crate) name: String,
+    pub(crate) ttl: i32,
+    pub(crate) record_type: String,
+    pub(crate) content: String,
+    pub(crate) prio: Option<i32>,
+}
+
+impl From<&DnsRecord> for ZoneRecord {
+    fn from(record: &DnsRecord) -> Self {
+        ZoneRecord {
+            name: record.name.clone(),
+            ttl: record.ttl,
+            record_type: record.record_type.clone(),
+            content: record.content.clone(),
+            prio: record.prio,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Zone {
+    pub(crate) origin: String,
+    pub(crate) default_ttl: Option<i32>,
+    pub(crate) soa: Option<Soa>,
+    pub(crate) records: Vec<ZoneRecord>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ZoneError {
+    pub(crate) line: usize,
+    pub(crate) message: String,
+}
+
+impl std::fmt::Display for ZoneError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Line {}: {}", self.line, self.message)
+    }
+}
+
+fn error<T>(line: usize, message: impl Into<String>) -> Result<T, ZoneError> {
+    Err(ZoneError {
+        line,
+        message: message.into(),
+    })
+}
+
+/// `name` as it should appear in the owner column, relative to `origin` where possible
+fn relative_name(name: &str, origin: &str) -> String {
+    if name == origin {
+        return "@".to_string();
+    }
+
+    match name.strip_suffix(&format!(".{origin}")) {
+        Some(subdomain) => subdomain.to_string(),
+        None => format!("{name}."),
+    }
+}
+
+/// Resolves a name from the zone file against `origin`, dropping the trailing dot
+fn absolute_name(name: &str, origin: &str) -> String {
+    if name == "@" {
+        origin.to_string()
+    } else if let Some(name) = name.strip_suffix('.') {
+        name.to_string()
+    } else {
+        format!("{name}.{origin}")
+    }
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Splits TXT content into quoted strings short enough for a single character-string
+fn quote_txt(content: &str) -> String {
+    let mut strings = vec![];
+    let mut start = 0;
+    for (i, c) in content.char_indices() {
+        if i + c.len_utf8() - start > MAX_TXT_STRING_LEN {
+            strings.push(quote(&content[start..i]));
+            start = i;
+        }
+    }
+    strings.push(quote(&content[start..]));
+
+    strings.join(" ")
+}
+
+impl ZoneRecord {
+    fn rdata(&self) -> String {
+        match self.record_type.as_str() {
+            "CNAME" | "NS" | "ALIAS" => format!("{}.", self.content),
+            "MX" => format!("{} {}.", self.prio.unwrap_or(0), self.content),
+            "SRV" => match self
+                .content
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .as_slice()
+            {
+                [weight, port, target] => {
+                    format!("{} {weight} {port} {target}.", self.prio.unwrap_or(0))
+                }
+                _ => format!("{} {}", self.prio.unwrap_or(0), self.content),
+            },
+            "TXT" => quote_txt(&self.content),
+            _ => self.content.clone(),
+        }
+    }
+
+    /// This record as a single zone file line
+    pub(crate) fn to_line(&self, origin: &str) -> String {
+        format!(
+            "{}\t{}\tIN\t{}\t{}",
+            relative_name(&self.name, origin),
+            self.ttl,
+            self.record_type,
+            self.rdata()
+        )
+    }
+}
+
+impl Zone {
+    pub(crate) fn to_zone_file(&self) -> String {
+        let mut out = format!("$ORIGIN {}.\n", self.origin);
+        if let Some(ttl) = self.default_ttl {
+            out.push_str(&format!("$TTL {ttl}\n"));
+        }
+        out.push('\n');
+
+        if let Some(soa) = &self.soa {
+            out.push_str(&format!(
+                "@\t{}\tIN\tSOA\t{}. {}. (\n\t\t{} ; serial\n\t\t{} ; refresh\n\t\t{} ; retry\n\t\t{} ; expire\n\t\t{} ; minimum\n)\n\n",
+                soa.ttl, soa.mname, soa.rname, soa.serial, soa.refresh, soa.retry, soa.expire, soa.minimum
+            ));
+        }
+
+        for record in &self.records {
+            out.push_str(&record.to_line(&self.origin));
+            out.push('\n');
+        }
+
+        out
+    }
+
+    /// Parses a zone file, using `origin` until a `$ORIGIN` directive says otherwise
+    pub(crate) fn parse(input: &str, origin: &str) -> Result<Zone, ZoneError> {
+        let mut zone = Zone {
+            origin: origin.trim_end_matches('.').to_ascii_lowercase(),
+            default_ttl: None,
+            soa: None,
+            records: vec![],
+        };
+        let mut last_owner: Option<String> = None;
+        let mut last_ttl: Option<i32> = None;
+
+        for entry in entries(input)? {
+            let line = entry.line;
+            let mut tokens = entry.tokens.into_iter().peekable();
+
+            if !entry.continues_owner {
+                if let Some(Token::Word(word)) = tokens.peek() {
+                    if word.starts_with('$') {
+                        let directive = word.to_ascii_uppercase();
+                        tokens.next();
+                        let argument = match tokens.next() {
+                            Some(Token::Word(argument)) => argument,
+                            _ => return error(line, format!("{directive} needs an argument")),
+                        };
+
+                        match directive.as_str() {
+                            "$ORIGIN" => {
+                                zone.origin =
+                                    absolute_name(&argument, &zone.origin).to_ascii_lowercase();
+                            }
+                            "$TTL" => zone.default_ttl = Some(parse_ttl(&argument, line)?),
+                            _ => return error(line, format!("{directive} isn't supported")),
+                        }
+                        continue;
+                    }
+                }
+            }
+
+            let owner = if entry.continues_owner {
+                match &last_owner {
+                    Some(owner) => owner.clone(),
+                    None => return error(line, "Record has no owner name"),
+                }
+            } else {
+                match tokens.next() {
+                    Some(Token::Word(owner)) => {
+                        absolute_name(&owner, &zone.origin).to_ascii_lowercase()
+                    }
+                    _ => return error(line, "Expected an owner name"),
+                }
+            };
+            last_owner = Some(owner.clone());
+
+            let mut ttl = None;
+            let record_type = loop {
+                match tokens.next() {
+                    Some(Token::Word(word)) if word.eq_ignore_ascii_case("IN") => {}
+                    Some(Token::Word(word))
+                        if ttl.is_none() && word.starts_with(|c: char| c.is_ascii_digit()) =>
+                    {
+                        ttl = Some(parse_ttl(&word, line)?);
+                    }
+                    Some(Token::Word(word)) => break word.to_ascii_uppercase(),
+                    _ => return error(line, "Expected a record type"),
+                }
+            };
+            let rdata = tokens.collect::<Vec<_>>();
+
+            if record_type == "SOA" {
+                let ttl = ttl.or(zone.default_ttl).unwrap_or(MIN_TTL);
+                zone.soa = Some(parse_soa(&rdata, ttl, &zone.origin, line)?);
+                last_ttl = Some(ttl);
+                continue;
+            }
+
+            let Some(ttl) = ttl.or(zone.default_ttl).or(last_ttl) else {
+                return error(line, "Record has no TTL and there is no $TTL");
+            };
+            last_ttl = Some(ttl);
+
+            let (content, prio) = parse_rdata(&record_type, &rdata, &zone.origin, line)?;
+            zone.records.push(ZoneRecord {
+                name: owner,
+                ttl,
+                record_type,
+                content,
+                prio,
+            });
+        }
+
+        Ok(zone)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+}
+
+impl Token {
+    fn text(&self) -> &str {
+        match self {
+            Token::Word(text) | Token::Quoted(text) => text,
+        }
+    }
+}
+
+/// One logical entry, which can span several lines inside parentheses
+struct Entry {
+    line: usize,
+    continues_owner: bool,
+    tokens: Vec<Token>,
+}
+
+fn entries(input: &str) -> Result<Vec<Entry>, ZoneError> {
+    let mut entries = vec![];
+    let mut current: Option<Entry> = None;
+    let mut depth = 0usize;
+
+    for (i, line) in input.lines().enumerate() {
+        let line_number = i + 1;
+        let entry = current.get_or_insert_with(|| Entry {
+            line: line_number,
+            continues_owner: line.starts_with([' ', '\t']),
+            tokens: vec![],
+        });
+
+        tokenize_line(line, line_number, &mut depth, &mut entry.tokens)?;
+
+        if depth == 0 {
+            if let Some(entry) = current.take() {
+                if !entry.tokens.is_empty() {
+                    entries.push(entry);
+                }
+            }
+        }
+    }
+
+    if let Some(entry) = current {
+        return error(entry.line, "Unclosed parenthesis");
+    }
+
+    Ok(entries)
+}
+
+fn tokenize_line(
+    line: &str,
+    line_number: usize,
+    depth: &mut usize,
+    tokens: &mut Vec<Token>,
+) -> Result<(), ZoneError> {
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ';' => break,
+            '(' => *depth += 1,
+            ')' => match depth.checked_sub(1) {
+                Some(new_depth) => *depth = new_depth,
+                None => return error(line_number, "Unexpected )"),
+            },
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(escaped) => text.push(escaped),
+                            None => return error(line_number, "Unterminated string"),
+                        },
+                        Some(c) => text.push(c),
+                        None => return error(line_number, "Unterminated string"),
+                    }
+                }
+                tokens.push(Token::Quoted(text));
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut word = String::from(c);
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || matches!(next, ';' | '(' | ')' | '"') {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses a TTL, allowing BIND style units like `1h30m`. TTLs are unsigned, and RFC 2181 caps
+/// them at 2^31 - 1
+fn parse_ttl(ttl: &str, line: usize) -> Result<i32, ZoneError> {
+    parse_seconds(ttl)
+        .and_then(|seconds| i32::try_from(seconds).ok())
+        .ok_or_else(|| ZoneError {
+            line,
+            message: format!("{ttl} isn't a valid TTL"),
+        })
+}
+
+/// Parses a number of seconds, either plain or with BIND style units. `None` for anything
+/// negative or that doesn't fit in 32 bits
+fn parse_seconds(value: &str) -> Option<u32> {
+    if !value.is_empty() && value.chars().all(|c| c.is_ascii_digit()) {
+        return value.parse().ok();
+    }
+
+    let mut total: u32 = 0;
+    let mut number = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let unit = match c.to_ascii_lowercase() {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 60 * 60 * 24,
+            'w' => 60 * 60 * 24 * 7,
+            _ => return None,
+        };
+        total = number
+            .parse::<u32>()
+            .ok()?
+            .checked_mul(unit)
+            .and_then(|value| total.checked_add(value))?;
+        number.clear();
+    }
+
+    number.is_empty().then_some(total)
+}
+
+fn word<'a>(
+    tokens: &'a [Token],
+    index: usize,
+    what: &str,
+    line: usize,
+) -> Result<&'a str, ZoneError> {
+    match tokens.get(index) {
+        Some(Token::Word(word)) => Ok(word),
+        _ => error(line, format!("Expected {what}")),
+    }
+}
+
+fn number<T: std::str::FromStr>(
+    tokens: &[Token],
+    index: usize,
+    what: &str,
+    line: usize,
+) -> Result<T, ZoneError> {
+    let value = word(tokens, index, what, line)?;
+    value
+        .parse()
+        .or_else(|_| error(line, format!("{value} isn't a valid {what}")))
+}
+
+fn duration(tokens: &[Token], index: usize, what: &str, line: usize) -> Result<u32, ZoneError> {
+    let value = word(tokens, index, what, line)?;
+    parse_seconds(value).map_or_else(|| error(line, format!("{value} isn't a valid {what}")), Ok)
+}
+
+fn expect_len(
+    tokens: &[Token],
+    len: usize,
+    record_type: &str,
+    line: usize,
+) -> Result<(), ZoneError> {
+    if tokens.len() != len {
+        return error(
+            line,
+            format!(
+                "{record_type} records need {len} values, found {}",
+                tokens.len()
+            ),
+        );
+    }
+
+    Ok(())
+}
+
+fn parse_soa(tokens: &[Token], ttl: i32, origin: &str, line: usize) -> Result<Soa, ZoneError> {
+    expect_len(tokens, 7, "SOA", line)?;
+
+    Ok(Soa {
+        ttl,
+        mname: absolute_name(word(tokens, 0, "a primary nameserver", line)?, origin),
+        rname: absolute_name(word(tokens, 1, "a responsible mailbox", line)?, origin),
+        serial: number(tokens, 2, "serial", line)?,
+        refresh: duration(tokens, 3, "refresh", line)?,
+        retry: duration(tokens, 4, "retry", line)?,
+        expire: duration(tokens, 5, "expire", line)?,
+        minimum: duration(tokens, 6, "minimum", line)?,
+    })
+}
+
+/// Turns the data part of a record into Porkbun style content and priority
+fn parse_rdata(
+    record_type: &str,
+    tokens: &[Token],
+    origin: &str,
+    line: usize,
+) -> Result<(String, Option<i32>), ZoneError> {
+    match record_type {
+        "A" => {
+            expect_len(tokens, 1, record_type, line)?;
+            let address = word(tokens, 0, "an IPv4 address", line)?;
+            if address.parse::<Ipv4Addr>().is_err() {
+                return error(line, format!("{address} isn't a valid IPv4 address"));
+            }
+            Ok((address.to_string(), None))
+        }
+        "AAAA" => {
+            expect_len(tokens, 1, record_type, line)?;
+            let address = word(tokens, 0, "an IPv6 address", line)?;
+            if address.parse::<Ipv6Addr>().is_err() {
+                return error(line, format!("{address} isn't a valid IPv6 address"));
+            }
+            Ok((address.to_string(), None))
+        }
+        "CNAME" | "NS" | "ALIAS" => {
+            expect_len(tokens, 1, record_type, line)?;
+            let target = word(tokens, 0, "a hostname", line)?;
+            Ok((absolute_name(target, origin), None))
+        }
+        "MX" => {
+            expect_len(tokens, 2, record_type, line)?;
+            let preference = number::<u16>(tokens, 0, "preference", line)?;
+            let exchange = word(tokens, 1, "a mail server", line)?;
+            Ok((absolute_name(exchange, origin), Some(preference.into())))
+        }
+        "SRV" => {
+            expect_len(tokens, 4, record_type, line)?;
+            let priority = number::<u16>(tokens, 0, "priority", line)?;
+            let weight = number::<u16>(tokens, 1, "weight", line)?;
+            let port = number::<u16>(tokens, 2, "port", line)?;
+            let target = absolute_name(word(tokens, 3, "a target", line)?, origin);
+            Ok((format!("{weight} {port} {target}"), Some(priority.into())))
+        }
+        "TXT" => {
+            if tokens.is_empty() {
+                return error(line, "TXT records need at least one string");
+            }
+            Ok((tokens.iter().map(Token::text).collect(), None))
+        }
+        "CAA" | "TLSA" | "HTTPS" | "SVCB" => {
+            if tokens.is_empty() {
+                return error(line, format!("{record_type} records need a value"));
+            }
+            let content = tokens
+                .iter()
+                .map(|token| match token {
+                    Token::Word(word) => word.clone(),
+                    Token::Quoted(text) => quote(text),
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            Ok((content, None))
+        }
+        _ => error(line, format!("{record_type} records aren't supported")),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ImportMode {
+    /// Add and update records from the file, leave everything else alone
+    Merge,
+    /// Make the domain's records match the file exactly
+    Replace,
+}
+
+impl ImportMode {
+    pub(crate) const ALL: [ImportMode; 2] = [ImportMode::Merge, ImportMode::Replace];
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            ImportMode::Merge => "merge",
+            ImportMode::Replace => "replace",
+        }
+    }
+
+    pub(crate) fn parse(mode: &str) -> Result<Self, String> {
+        Self::ALL
+            .into_iter()
+            .find(|m| m.as_str() == mode)
+            .ok_or_else(|| format!("{mode} isn't a valid import mode"))
+    }
+
+    pub(crate) fn description(&self) -> &'static str {
+        match self {
+            ImportMode::Merge => {
+                "Merge: add and update records, keep records missing from the file"
+            }
+            ImportMode::Replace => "Replace: also delete records missing from the file",
+        }
+    }
+}
+
+/// What importing a zone would change about a domain's current records
+#[derive(Debug, Default)]
+pub(crate) struct ImportPlan<'a> {
+    pub(crate) create: Vec<&'a ZoneRecord>,
+    pub(crate) update: Vec<(&'a DnsRecord, &'a ZoneRecord)>,
+    pub(crate) delete: Vec<&'a DnsRecord>,
+    pub(crate) unchanged: usize,
+}
+
+impl ImportPlan<'_> {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.create.is_empty() && self.update.is_empty() && self.delete.is_empty()
+    }
+}
+
+/// Apex NS records follow the domain's nameserver settings, so imports never touch them
+pub(crate) fn is_apex_ns(name: &str, record_type: &str, domain: &str) -> bool {
+    record_type == "NS" && name == domain
+}
+
+/// Records are matched on everything but their TTL, so a matching record with a different TTL
+/// is updated and anything else is created or deleted
+pub(crate) fn plan_import<'a>(
+    existing: &'a [DnsRecord],
+    zone: &'a Zone,
+    mode: ImportMode,
+) -> ImportPlan<'a> {
+    let mut plan = ImportPlan::default();
+    let mut matched = vec![false; existing.len()];
+
+    for record in &zone.records {
+        if is_apex_ns(&record.name, &record.record_type, &zone.origin) {
+            continue;
+        }
+
+        let found = existing.iter().enumerate().find(|(i, current)| {
+            !matched[*i]
+                && current.name == record.name
+                && current.record_type == record.record_type
+                && current.content == record.content
+                && current.prio == record.prio
+        });
+
+        match found {
+            Some((i, current)) => {
+                matched[i] = true;
+                if current.ttl == record.ttl {
+                    plan.unchanged += 1;
+                } else {
+                    plan.update.push((current, record));
+                }
+            }
+            None => plan.create.push(record),
+        }
+    }
+
+    if mode == ImportMode::Replace {
+        plan.delete = existing
+            .iter()
+            .zip(matched)
+            .filter(|(current, matched)| {
+                !matched && !is_apex_ns(&current.name, &current.record_type, &zone.origin)
+            })
+            .map(|(current, _)| current)
+            .collect();
+    }
+
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(
+        name: &str,
+        ttl: i32,
+        record_type: &str,
+        content: &str,
+        prio: Option<i32>,
+    ) -> ZoneRecord {
+        ZoneRecord {
+            name: name.to_string(),
+            ttl,
+            record_type: record_type.to_string(),
+            content: content.to_string(),
+            prio,
+        }
+    }
+
+    fn example_zone() -> Zone {
+        Zone {
+            origin: "example.com".to_string(),
+            default_ttl: Some(600),
+            soa: Some(Soa {
+                ttl: 3600,
+                mname: "curitiba.ns.porkbun.com".to_string(),
+                rname: "hostmaster.example.com".to_string(),
+                serial: 2024110101,
+                refresh: 10800,
+                retry: 3600,
+                expire: 604800,
+                minimum: 600,
+            }),
+            records: vec![
+                record("example.com", 600, "A", "192.0.2.1", None),
+                record("example.com", 600, "AAAA", "2001:db8::1", None),
+                record("www.example.com", 3600, "CNAME", "example.com", None),
+                record("example.com", 600, "MX", "mail.example.com", Some(10)),
+                record("example.com", 600, "MX", "backup.mx.other.net", Some(20)),
+                record(
+                    "example.com",
+                    600,
+                    "TXT",
+                    "v=spf1 include:_spf.example.net ~all",
+                    None,
+                ),
+                record("example.com", 86400, "NS", "curitiba.ns.porkbun.com", None),
+                record(
+                    "_sip._tcp.example.com",
+                    600,
+                    "SRV",
+                    "5 5060 sip.example.com",
+                    Some(10),
+                ),
+                record(
+                    "example.com",
+                    600,
+                    "CAA",
+                    "0 issue \"letsencrypt.org\"",
+                    None,
+                ),
+                record("quotes.example.com", 600, "TXT", "say \"hi\" \\o/", None),
+            ],
+        }
+    }
+
+    #[test]
+    fn round_trips_a_zone() {
+        let zone = example_zone();
+
+        let parsed = Zone::parse(&zone.to_zone_file(), "example.com").unwrap();
+
+        assert_eq!(parsed, zone);
+    }
+
+    #[test]
+    fn round_trips_long_txt_records() {
+        let dkim = format!("v=DKIM1; k=rsa; p={}", "A".repeat(600));
+        let zone = Zone {
+            origin: "example.com".to_string(),
+            default_ttl: None,
+            soa: None,
+            records: vec![record(
+                "mail._domainkey.example.com",
+                600,
+                "TXT",
+                &dkim,
+                None,
+            )],
+        };
+
+        let file = zone.to_zone_file();
+        assert_eq!(file.matches('"').count(), 6);
+        assert_eq!(Zone::parse(&file, "example.com").unwrap(), zone);
+    }
+
+    #[test]
+    fn parses_a_hand_written_zone() {
+        let file = r#"
+; Exported from somewhere else
+$ORIGIN example.org.
+$TTL 1h
+@   IN  SOA ns1.example.org. admin.example.org. (
+        2024010101 ; serial
+        2h         ; refresh
+        15m        ; retry
+        1w         ; expire
+        300 )      ; minimum
+
+@       IN  A       192.0.2.10
+        IN  MX  10  mail        ; relative to the origin
+www 300 IN  CNAME   @
+txt         TXT     "part one " "part two"
+$ORIGIN sub.example.org.
+api 600 IN  A       192.0.2.20
+"#;
+
+        let zone = Zone::parse(file, "ignored.com").unwrap();
+
+        assert_eq!(zone.origin, "sub.example.org");
+        assert_eq!(zone.default_ttl, Some(3600));
+        let soa = zone.soa.unwrap();
+        assert_eq!(soa.mname, "ns1.example.org");
+        assert_eq!(soa.serial, 2024010101);
+        assert_eq!(soa.refresh, 7200);
+        assert_eq!(soa.minimum, 300);
+        assert_eq!(
+            zone.records,
+            vec![
+                record("example.org", 3600, "A", "192.0.2.10", None),
+                record("example.org", 3600, "MX", "mail.example.org", Some(10)),
+                record("www.example.org", 300, "CNAME", "example.org", None),
+                record("txt.example.org", 3600, "TXT", "part one part two", None),
+                record("api.sub.example.org", 600, "A", "192.0.2.20", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_the_line_of_an_error() {
+        let file = "$TTL 600\n@ IN A 192.0.2.1\n@ IN HINFO \"PC\" \"Linux\"\n";
+
+        let error = Zone::parse(file, "example.com").unwrap_err();
+
+        assert_eq!(error.line, 3);
+        assert_eq!(error.message, "HINFO records aren't supported");
+    }
+
+    #[test]
+    fn rejects_records_without_a_ttl() {
+        let error = Zone::parse("@ IN A 192.0.2.1\n", "example.com").unwrap_err();
+
+        assert_eq!(error.line, 1);
+    }
+
+    #[test]
+    fn rejects_unbalanced_parentheses() {
+        assert!(Zone::parse("$TTL 600\n@ IN A ( 192.0.2.1\n", "example.com").is_err());
+        assert!(Zone::parse("$TTL 600\n@ IN A 192.0.2.1 )\n", "example.com").is_err());
+    }
+
+    #[test]
+    fn rejects_ttls_outside_the_unsigned_range() {
+        for ttl in ["-5", "+5", "2147483648", "4294967296", "1w-1h"] {
+            let error = Zone::parse(&format!("$TTL {ttl}\n"), "example.com").unwrap_err();
+            assert_eq!(error.message, format!("{ttl} isn't a valid TTL"));
+        }
+
+        let zone = Zone::parse("$TTL 2147483647\n", "example.com").unwrap();
+        assert_eq!(zone.default_ttl, Some(i32::MAX));
+    }
+}